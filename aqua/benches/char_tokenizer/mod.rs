@@ -0,0 +1,713 @@
+//! The tokenizer as it was before it scanned the bytes of the input in place.
+//! It copies the input into a `Vec<char>` first. It is kept to compare
+//! the two in the benchmarks and produces the same tokens.
+
+use tergo_tokenizer::error::{TokenizeError, TokenizeErrorKind};
+use tergo_tokenizer::tokens::{
+    CommentedToken,
+    Token::{self, *},
+};
+
+/// Tokenizer for an R program.
+///
+/// Transforms an R program into an array of language tokens.
+pub struct CharTokenizer<'a> {
+    line: u32,
+    offset: usize,
+    it: usize,
+    byte: usize,
+    source: Vec<char>,
+    raw_source: &'a str,
+    lossless: bool,
+}
+
+impl<'a> CharTokenizer<'a> {
+    /// Returns a new tokenizer from an R program.
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            line: 0,
+            offset: 0,
+            it: 0,
+            byte: 0,
+            source: input.chars().collect::<Vec<_>>(),
+            raw_source: input,
+            lossless: false,
+        }
+    }
+
+    /// Returns an array of tokens.
+    pub fn tokenize(&mut self) -> Result<Vec<CommentedToken<'a>>, TokenizeError> {
+        let mut tokens = vec![];
+        while self.it < self.source.len() {
+            let start = (self.line, self.offset, self.byte);
+            let pushed = tokens.len();
+            match self.source[self.it] {
+                ' ' | '\t' => {
+                    while matches!(self.source.get(self.it), Some(' ' | '\t')) {
+                        self.next();
+                    }
+                    if self.lossless {
+                        self.push_token(
+                            Whitespace(&self.raw_source[start.2..self.byte]),
+                            &mut tokens,
+                        );
+                    }
+                }
+                '\r' => {
+                    // CRLF is a single line break, a lone CR is a line break too
+                    if self.lookahead() == Some('\n') {
+                        self.next();
+                    }
+                    self.push_token(Newline, &mut tokens);
+                    self.next_line();
+                }
+                '\n' => {
+                    self.push_token(Newline, &mut tokens);
+                    self.next_line();
+                }
+                ';' => {
+                    self.push_token(Semicolon, &mut tokens);
+                    self.next();
+                }
+                ',' => {
+                    self.push_token(Comma, &mut tokens);
+                    self.next();
+                }
+                '(' => {
+                    self.push_token(LParen, &mut tokens);
+                    self.next();
+                }
+                ')' => {
+                    self.push_token(RParen, &mut tokens);
+                    self.next();
+                }
+                '{' => {
+                    self.push_token(LBrace, &mut tokens);
+                    self.next();
+                }
+                '}' => {
+                    self.push_token(RBrace, &mut tokens);
+                    self.next();
+                }
+                '[' => {
+                    self.push_token(LBracket, &mut tokens);
+                    self.next();
+                }
+                ']' => {
+                    self.push_token(RBracket, &mut tokens);
+                    self.next();
+                }
+                '\'' | '\"' => {
+                    self.string_literal(&mut tokens)?;
+                    self.next();
+                }
+                '*' => {
+                    match self.lookahead() {
+                        // That's undocumented, but it actually works...
+                        Some('*') => {
+                            self.push_token(Power, &mut tokens);
+                            self.next();
+                        }
+                        _ => self.push_token(Multiply, &mut tokens),
+                    }
+                    self.next();
+                }
+                '/' => {
+                    self.push_token(Divide, &mut tokens);
+                    self.next();
+                }
+                '^' => {
+                    self.push_token(Power, &mut tokens);
+                    self.next();
+                }
+                '+' => {
+                    self.push_token(Plus, &mut tokens);
+                    self.next();
+                }
+                '?' => {
+                    self.push_token(Help, &mut tokens);
+                    self.next();
+                }
+                '<' => {
+                    match self.lookahead() {
+                        Some('-') => {
+                            self.push_token(LAssign, &mut tokens);
+                            self.next();
+                        }
+                        Some('=') => {
+                            self.push_token(LowerEqual, &mut tokens);
+                            self.next();
+                        }
+                        Some('<') => {
+                            self.push_token(SuperAssign, &mut tokens);
+                            self.next();
+                            self.next();
+                        }
+                        _ => self.push_token(LowerThan, &mut tokens),
+                    }
+                    self.next();
+                }
+                '>' => {
+                    match self.lookahead() {
+                        Some('=') => {
+                            self.push_token(GreaterEqual, &mut tokens);
+                            self.next();
+                        }
+                        _ => {
+                            self.push_token(GreaterThan, &mut tokens);
+                        }
+                    }
+                    self.next();
+                }
+                '|' => {
+                    match self.lookahead() {
+                        Some('|') => {
+                            self.push_token(Or, &mut tokens);
+                            self.next();
+                        }
+                        Some('>') => {
+                            self.push_token(Pipe, &mut tokens);
+                            self.next();
+                        }
+                        _ => self.push_token(VectorizedOr, &mut tokens),
+                    }
+                    self.next();
+                }
+                '&' => {
+                    match self.lookahead() {
+                        Some('&') => {
+                            self.push_token(And, &mut tokens);
+                            self.next();
+                        }
+                        _ => self.push_token(VectorizedAnd, &mut tokens),
+                    }
+                    self.next();
+                }
+                '=' => {
+                    match self.lookahead() {
+                        Some('=') => {
+                            self.push_token(Equal, &mut tokens);
+                            self.next();
+                        }
+                        _ => self.push_token(OldAssign, &mut tokens),
+                    }
+                    self.next();
+                }
+                '$' => {
+                    self.push_token(Dollar, &mut tokens);
+                    self.next();
+                }
+                '-' => {
+                    match self.lookahead() {
+                        Some('>') => {
+                            self.push_token(RAssign, &mut tokens);
+                            self.next();
+                        }
+                        _ => self.push_token(Minus, &mut tokens),
+                    }
+                    self.next();
+                }
+                '!' => {
+                    self.next();
+                    match self.source[self.it..] {
+                        ['=', ..] => {
+                            self.push_token(NotEqual, &mut tokens);
+                            self.next();
+                        }
+                        _ => self.push_token(UnaryNot, &mut tokens),
+                    }
+                }
+                '.' => match self.lookahead() {
+                    Some('0'..='9') => {
+                        self.number_literal(&mut tokens);
+                    }
+                    _ => {
+                        self.identifier(&mut tokens);
+                    }
+                },
+                '`' => {
+                    self.quoted_identifier(&mut tokens)?;
+                }
+                // A lone underscore is the native pipe placeholder.
+                // R does not allow identifiers to start with an underscore.
+                '_' if !self.lookahead().is_some_and(is_identifier_char) => {
+                    self.push_token(Placeholder, &mut tokens);
+                    self.next();
+                }
+                '%' => match self.lookahead() {
+                    Some('%') => {
+                        self.push_token(Modulo, &mut tokens);
+                        self.next();
+                        self.next();
+                    }
+                    _ => {
+                        let custom_binary_start = self.byte;
+                        let (start_line, start_offset) = (self.line, self.offset);
+                        self.next();
+                        while self.it < self.source.len() && self.source[self.it] != '%' {
+                            if self.source[self.it] == '\n' {
+                                return Err(TokenizeError::new(
+                                    TokenizeErrorKind::UnterminatedSpecialOperator,
+                                    start_line,
+                                    start_offset,
+                                ));
+                            }
+                            self.next();
+                        }
+                        if self.it == self.source.len() {
+                            return Err(TokenizeError::new(
+                                TokenizeErrorKind::UnterminatedSpecialOperator,
+                                start_line,
+                                start_offset,
+                            ));
+                        }
+                        let custom_binary_end = self.byte;
+                        self.push_token(
+                            Special(&self.raw_source[custom_binary_start..=custom_binary_end]),
+                            &mut tokens,
+                        );
+                        self.next()
+                    }
+                },
+                'r' | 'R' if matches!(self.lookahead(), Some('"') | Some('\'')) => {
+                    self.raw_string_literal(&mut tokens)?;
+                    self.next();
+                }
+                c if c.is_alphabetic() => {
+                    self.identifier(&mut tokens);
+                }
+                '0'..='9' => {
+                    self.number_literal(&mut tokens);
+                }
+                '\\' => {
+                    self.push_token(Lambda, &mut tokens);
+                    self.next();
+                }
+                '#' => {
+                    self.comment(&mut tokens);
+                }
+                '~' => {
+                    self.push_token(Tilde, &mut tokens);
+                    self.next();
+                }
+                '@' => {
+                    self.push_token(Slot, &mut tokens);
+                    self.next();
+                }
+                ':' => {
+                    self.next();
+                    match self.source[self.it..] {
+                        [':', ':', ..] => {
+                            self.push_token(NsGetInt, &mut tokens);
+                            self.next();
+                            self.next();
+                        }
+                        [':', ..] => {
+                            self.push_token(NsGet, &mut tokens);
+                            self.next()
+                        }
+                        ['=', ..] => {
+                            self.push_token(ColonAssign, &mut tokens);
+                            self.next()
+                        }
+                        _ => self.push_token(Colon, &mut tokens),
+                    }
+                }
+                unexpected => {
+                    return Err(TokenizeError::new(
+                        TokenizeErrorKind::UnexpectedCharacter(unexpected),
+                        self.line,
+                        self.offset,
+                    ));
+                }
+            }
+            // Some of the branches above push the token before consuming
+            // its characters, so the positions are filled in here,
+            // once the whole token has been consumed.
+            for token in &mut tokens[pushed..] {
+                (token.line, token.offset) = (start.0, start.1);
+                (token.end_line, token.end_offset) = (self.line, self.offset);
+                token.span = start.2..self.byte;
+            }
+        }
+        tokens.push(CommentedToken::new(EOF, self.line, self.offset).with_end(
+            self.line,
+            self.offset,
+            self.byte..self.byte,
+        ));
+        Ok(tokens)
+    }
+
+    fn push_token(&mut self, token: Token<'a>, tokens: &mut Vec<CommentedToken<'a>>) {
+        tokens.push(CommentedToken::new(token, self.line, self.offset));
+    }
+
+    fn string_literal(
+        &mut self,
+        tokens: &mut Vec<CommentedToken<'a>>,
+    ) -> Result<(), TokenizeError> {
+        let delimiter = self.source[self.it];
+        let (start_line, start_offset) = (self.line, self.offset);
+        let start_byte = self.byte;
+        self.next();
+        loop {
+            match self.source.get(self.it) {
+                None => {
+                    return Err(TokenizeError::new(
+                        TokenizeErrorKind::UnterminatedString,
+                        start_line,
+                        start_offset,
+                    ));
+                }
+                Some('\\') => self.escape_sequence()?,
+                Some(c) if *c == delimiter => break,
+                Some('\n') => self.next_line(),
+                Some(_) => self.next(),
+            }
+        }
+        tokens.push(CommentedToken::new(
+            Literal(&self.raw_source[start_byte..=self.byte]),
+            start_line,
+            start_offset,
+        ));
+        Ok(())
+    }
+
+    /// Validates the escape sequence starting at the current backslash
+    /// and moves past it. The escapes recognized by R are:
+    /// * `\n`, `\r`, `\t`, `\b`, `\a`, `\f`, `\v`, `\\`, `\'`, `\"`, `` \` `` and an escaped newline,
+    /// * `\nnn` - one to three octal digits,
+    /// * `\xhh` - one or two hexadecimal digits,
+    /// * `\uhhhh` and `\u{hhhh}` - one to four hexadecimal digits,
+    /// * `\Uhhhhhhhh` and `\U{hhhhhhhh}` - one to eight hexadecimal digits.
+    fn escape_sequence(&mut self) -> Result<(), TokenizeError> {
+        let (start_line, start_offset) = (self.line, self.offset);
+        self.next();
+        let error = |kind| Err(TokenizeError::new(kind, start_line, start_offset));
+        match self.source.get(self.it).copied() {
+            // Reported as an unterminated string by the caller
+            None => {}
+            Some('\n') => self.next_line(),
+            Some('n' | 'r' | 't' | 'b' | 'a' | 'f' | 'v' | '\\' | '\'' | '"' | '`') => self.next(),
+            Some('0'..='7') => {
+                self.parse_digits(3, |c| c.is_digit(8));
+            }
+            Some(escape @ 'x') => {
+                self.next();
+                if self.parse_digits(2, |c| c.is_ascii_hexdigit()) == 0 {
+                    return error(TokenizeErrorKind::MalformedEscape(escape));
+                }
+            }
+            Some(escape @ ('u' | 'U')) => {
+                let max_digits = if escape == 'u' { 4 } else { 8 };
+                self.next();
+                let braced = self.source.get(self.it) == Some(&'{');
+                if braced {
+                    self.next();
+                }
+                if self.parse_digits(max_digits, |c| c.is_ascii_hexdigit()) == 0 {
+                    return error(TokenizeErrorKind::MalformedEscape(escape));
+                }
+                if braced {
+                    if self.source.get(self.it) != Some(&'}') {
+                        return error(TokenizeErrorKind::MalformedEscape(escape));
+                    }
+                    self.next();
+                }
+            }
+            Some(unrecognized) => {
+                return error(TokenizeErrorKind::UnrecognizedEscape(unrecognized));
+            }
+        }
+        Ok(())
+    }
+
+    /// Consumes at most `max` characters matching `is_digit`
+    /// and returns how many were consumed.
+    fn parse_digits(&mut self, max: usize, is_digit: impl Fn(char) -> bool) -> usize {
+        let mut count = 0;
+        while count < max && self.source.get(self.it).is_some_and(|c| is_digit(*c)) {
+            self.next();
+            count += 1;
+        }
+        count
+    }
+
+    /// Raw strings: r"(...)", R"[...]", r"---{...}---" and so on.
+    ///
+    /// The body ends at the first closing bracket matching the opening one,
+    /// followed by the same number of dashes and the opening quote.
+    fn raw_string_literal(
+        &mut self,
+        tokens: &mut Vec<CommentedToken<'a>>,
+    ) -> Result<(), TokenizeError> {
+        let (start_line, start_offset) = (self.line, self.offset);
+        let start_byte = self.byte;
+        self.next();
+        let quote = self.source[self.it];
+        self.next();
+        let mut dashes = 0;
+        while self.source.get(self.it) == Some(&'-') {
+            dashes += 1;
+            self.next();
+        }
+        let closing_bracket = match self.source.get(self.it) {
+            Some('(') => ')',
+            Some('[') => ']',
+            Some('{') => '}',
+            _ => {
+                return Err(TokenizeError::new(
+                    TokenizeErrorKind::MalformedRawString,
+                    start_line,
+                    start_offset,
+                ));
+            }
+        };
+        self.next();
+        loop {
+            match self.source.get(self.it) {
+                None => {
+                    return Err(TokenizeError::new(
+                        TokenizeErrorKind::UnterminatedRawString,
+                        start_line,
+                        start_offset,
+                    ));
+                }
+                Some('\n') => self.next_line(),
+                Some(c) if *c == closing_bracket && self.is_raw_string_end(quote, dashes) => break,
+                Some(_) => self.next(),
+            }
+        }
+        for _ in 0..=dashes {
+            self.next();
+        }
+        tokens.push(CommentedToken::new(
+            Literal(&self.raw_source[start_byte..=self.byte]),
+            start_line,
+            start_offset,
+        ));
+        Ok(())
+    }
+
+    fn is_raw_string_end(&self, quote: char, dashes: usize) -> bool {
+        let after_bracket = &self.source[self.it + 1..];
+        after_bracket.len() > dashes
+            && after_bracket[..dashes].iter().all(|c| *c == '-')
+            && after_bracket[dashes] == quote
+    }
+
+    fn parse_decimal(&mut self) {
+        while self.it < self.source.len() && self.source[self.it].is_ascii_digit() {
+            self.next();
+        }
+    }
+
+    fn parse_hexadecimal(&mut self) {
+        while self.it < self.source.len() && self.source[self.it].is_ascii_hexdigit() {
+            self.next();
+        }
+    }
+
+    /// Numeric constants follow R's grammar: decimal numbers with an optional
+    /// fraction and exponent (`1`, `1.`, `.5`, `1e-3`), hexadecimal numbers
+    /// with an optional fraction and binary exponent (`0xFF`, `0x1p10`, `0x1.8p3`),
+    /// and either of them with an `L` (integer) or `i` (complex) suffix.
+    fn number_literal(&mut self, tokens: &mut Vec<CommentedToken<'a>>) {
+        let start_byte = self.byte;
+        match self.source[self.it..] {
+            // Hexadecimal
+            ['0', 'x', ..] | ['0', 'X', ..] => {
+                self.next();
+                self.next();
+                self.parse_hexadecimal();
+                if let ['.', ..] = self.source[self.it..] {
+                    self.next();
+                    self.parse_hexadecimal();
+                }
+                self.parse_exponent('p');
+            }
+            // Decimal
+            _ => {
+                self.parse_decimal();
+                if let ['.', ..] = self.source[self.it..] {
+                    self.next();
+                    self.parse_decimal();
+                }
+                self.parse_exponent('e');
+            }
+        }
+        if let ['L', ..] | ['i', ..] = self.source[self.it..] {
+            self.next();
+        }
+        self.push_token(Literal(&self.raw_source[start_byte..self.byte]), tokens);
+    }
+
+    /// Consumes an exponent introduced by `marker` (in either case)
+    /// with an optional sign. The digits of the exponent are always decimal.
+    fn parse_exponent(&mut self, marker: char) {
+        match self.source[self.it..] {
+            [e, '+' | '-', '0'..='9', ..] if e.to_ascii_lowercase() == marker => {
+                self.next();
+                self.next();
+                self.parse_decimal();
+            }
+            [e, '0'..='9', ..] if e.to_ascii_lowercase() == marker => {
+                self.next();
+                self.parse_decimal();
+            }
+            _ => {}
+        }
+    }
+
+    /// Identifiers follow R's rules: they start with a letter or a dot
+    /// not followed by a digit, and continue with letters, digits, dots
+    /// and underscores. This also covers `...` and `..1`, `..2` and so on.
+    ///
+    /// Reserved words are recognized here as well.
+    fn identifier(&mut self, tokens: &mut Vec<CommentedToken<'a>>) {
+        let (start_line, start_offset) = (self.line, self.offset);
+        let start_byte = self.byte;
+        while self.it < self.source.len() && is_identifier_char(self.source[self.it]) {
+            self.next();
+        }
+
+        let token = match &self.raw_source[start_byte..self.byte] {
+            "continue" => Continue,
+            "break" => Break,
+            "for" => For,
+            "if" => If,
+            "else" => Else,
+            "in" => In,
+            "while" => While,
+            "repeat" => Repeat,
+            "function" => Function,
+            "TRUE" => Literal("TRUE"),
+            "FALSE" => Literal("FALSE"),
+            "NULL" => Null,
+            "NA" => Na,
+            "NA_integer_" => NaInteger,
+            "NA_real_" => NaReal,
+            "NA_character_" => NaCharacter,
+            "Inf" => Inf,
+            "NaN" => NaN,
+            "..." => Dots,
+            dot_dot_n
+                if dot_dot_n.len() > 2
+                    && dot_dot_n.starts_with("..")
+                    && dot_dot_n[2..].bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                DotDotN(dot_dot_n)
+            }
+            identifier => Symbol(identifier),
+        };
+        tokens.push(CommentedToken::new(token, start_line, start_offset));
+    }
+
+    /// Backtick-quoted symbols, e.g. `` `my var` ``.
+    fn quoted_identifier(
+        &mut self,
+        tokens: &mut Vec<CommentedToken<'a>>,
+    ) -> Result<(), TokenizeError> {
+        let (start_line, start_offset) = (self.line, self.offset);
+        let start_byte = self.byte;
+        self.next();
+        loop {
+            match self.source.get(self.it) {
+                None => {
+                    return Err(TokenizeError::new(
+                        TokenizeErrorKind::UnterminatedBacktick,
+                        start_line,
+                        start_offset,
+                    ))
+                }
+                Some('`') => break,
+                Some('\\') => {
+                    self.next();
+                    match self.source.get(self.it) {
+                        Some('\n') => self.next_line(),
+                        Some(_) => self.next(),
+                        None => {}
+                    }
+                }
+                Some('\n') => self.next_line(),
+                Some(_) => self.next(),
+            }
+        }
+        self.next();
+        tokens.push(CommentedToken::new(
+            Symbol(&self.raw_source[start_byte..self.byte]),
+            start_line,
+            start_offset,
+        ));
+        Ok(())
+    }
+
+    fn comment(&mut self, tokens: &mut Vec<CommentedToken<'a>>) {
+        let start_byte = self.byte;
+        while self.it < self.source.len() && !matches!(self.source[self.it], '\n' | '\r') {
+            self.next();
+        }
+
+        match tokens
+            .iter()
+            .rev()
+            .find(|token| !matches!(token.token, Whitespace(_)))
+        {
+            Some(CommentedToken { token: Newline, .. }) | None => self.push_token(
+                line_comment(&self.raw_source[start_byte..self.byte]),
+                tokens,
+            ),
+            Some(_) => self.push_token(
+                InlineComment(&self.raw_source[start_byte..self.byte]),
+                tokens,
+            ),
+        }
+    }
+
+    fn next(&mut self) {
+        self.byte += self.source.get(self.it).map_or(1, |c| c.len_utf8());
+        self.it += 1;
+        self.offset += 1;
+    }
+
+    fn next_line(&mut self) {
+        self.byte += 1;
+        self.it += 1;
+        self.line += 1;
+        self.offset = 0;
+    }
+
+    fn lookahead(&self) -> Option<char> {
+        if self.it + 1 < self.source.len() {
+            Some(self.source[self.it + 1])
+        } else {
+            None
+        }
+    }
+}
+
+/// Tells apart the kinds of comments occupying a whole line.
+fn line_comment(text: &str) -> Token<'_> {
+    match text.as_bytes() {
+        [b'#', b'\'', ..] => RoxygenComment(text),
+        [b'#', b'|', ..] => ChunkOptionComment(text),
+        [b'#', b'>', ..] => KnitrOutputComment(text),
+        _ if is_section_header(text) => SectionComment(text),
+        _ => Comment(text),
+    }
+}
+
+/// RStudio treats a comment ending with at least four `-`, `=` or `#`
+/// as a section header, e.g. `# Section ----` or `## Section ====`.
+fn is_section_header(text: &str) -> bool {
+    let text = text.trim_end();
+    let label = text.trim_start_matches('#');
+    ['-', '=', '#'].into_iter().any(|marker| {
+        let without_marker = label.trim_end_matches(marker);
+        label.len() - without_marker.len() >= 4 && !without_marker.trim().is_empty()
+    })
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '.' || c == '_'
+}
@@ -0,0 +1,72 @@
+mod char_tokenizer;
+
+use char_tokenizer::CharTokenizer;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use tergo_tokenizer::Tokenizer;
+
+const SNIPPET: &str = r#"#' Summarise a column
+#'
+#' @param df A data frame
+summarise_column <- function(df, column = "value", na.rm = TRUE, ...) {
+  # Section ----
+  values <- df[[column]]
+  if (is.null(values) || length(values) == 0L) {
+    stop(sprintf("Column '%s' is empty\n", column))
+  } else if (!is.numeric(values)) {
+    values <- as.numeric(values)
+  }
+  result <- list(
+    mean = mean(values, na.rm = na.rm), # inline comment
+    sd = sd(values, na.rm = na.rm),
+    range = range(values) %in% c(0x1F, 1e-3, 2.5i, NA_real_)
+  )
+  df |> dplyr::filter(.data$value > 0) |> head(n = 10)
+  lapply(seq_along(values), \(i) values[i]^2 / 3)
+  r"(raw \string)"
+}
+"#;
+
+/// Generates an R program with roughly `lines` lines.
+fn generate_program(lines: usize) -> String {
+    let snippet_lines = SNIPPET.lines().count();
+    SNIPPET.repeat(lines.div_ceil(snippet_lines))
+}
+
+fn tokenizer(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenizer");
+    for lines in [1_000, 20_000] {
+        let program = generate_program(lines);
+        assert_eq!(
+            Tokenizer::new(&program).tokenize().unwrap(),
+            CharTokenizer::new(&program).tokenize().unwrap()
+        );
+        group.throughput(Throughput::Bytes(program.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("char_tokenize", lines),
+            &program,
+            |b, program| b.iter(|| CharTokenizer::new(black_box(program)).tokenize().unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("tokenize", lines),
+            &program,
+            |b, program| b.iter(|| Tokenizer::new(black_box(program)).tokenize().unwrap()),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("iterate", lines),
+            &program,
+            |b, program| {
+                b.iter(|| {
+                    Tokenizer::new(black_box(program)).fold(0, |count, token| {
+                        black_box(token.unwrap());
+                        count + 1
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tokenizer);
+criterion_main!(benches);
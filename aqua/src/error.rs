@@ -0,0 +1,78 @@
+/// The reason why the tokenizer rejected the input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenizeErrorKind {
+    /// A string literal without the closing quote.
+    UnterminatedString,
+    /// An escape sequence that R does not recognize, e.g. `"\q"`.
+    UnrecognizedEscape(char),
+    /// A `\x`, `\u` or `\U` escape without hexadecimal digits
+    /// or with an unclosed brace, e.g. `"\x"` or `"\u{41"`.
+    MalformedEscape(char),
+    /// A raw string literal without the closing delimiter, e.g. `r"(abc`.
+    UnterminatedRawString,
+    /// A raw string literal with a delimiter other than `(`, `[` or `{`.
+    MalformedRawString,
+    /// A special operator without the closing `%`, e.g. `%in`.
+    UnterminatedSpecialOperator,
+    /// A backtick-quoted symbol without the closing backtick.
+    UnterminatedBacktick,
+    /// A character that cannot start any R token.
+    UnexpectedCharacter(char),
+}
+
+impl std::fmt::Display for TokenizeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenizeErrorKind::UnterminatedString => f.write_str("unterminated string literal"),
+            TokenizeErrorKind::UnrecognizedEscape(c) => {
+                write!(f, "'\\{c}' is an unrecognized escape in a string literal")
+            }
+            TokenizeErrorKind::MalformedEscape(c) => {
+                write!(f, "malformed '\\{c}' escape in a string literal")
+            }
+            TokenizeErrorKind::UnterminatedRawString => {
+                f.write_str("unterminated raw string literal")
+            }
+            TokenizeErrorKind::MalformedRawString => f.write_str(
+                "malformed raw string literal, expected one of '(', '[' or '{' after the quote",
+            ),
+            TokenizeErrorKind::UnterminatedSpecialOperator => {
+                f.write_str("unterminated special operator, expected a closing '%'")
+            }
+            TokenizeErrorKind::UnterminatedBacktick => {
+                f.write_str("unterminated backtick-quoted symbol")
+            }
+            TokenizeErrorKind::UnexpectedCharacter(c) => {
+                write!(f, "unexpected character {c:?}")
+            }
+        }
+    }
+}
+
+/// An error returned by [crate::Tokenizer::tokenize].
+///
+/// `line` and `offset` use the same convention as the tokens:
+/// both are counted from zero. The [std::fmt::Display] implementation
+/// prints them counted from one, the way editors show them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenizeError {
+    pub kind: TokenizeErrorKind,
+    /// The line where the offending token starts.
+    pub line: u32,
+    /// The column offset where the offending token starts.
+    pub offset: usize,
+}
+
+impl TokenizeError {
+    pub fn new(kind: TokenizeErrorKind, line: u32, offset: usize) -> Self {
+        Self { kind, line, offset }
+    }
+}
+
+impl std::fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line + 1, self.offset + 1, self.kind)
+    }
+}
+
+impl std::error::Error for TokenizeError {}
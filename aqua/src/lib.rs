@@ -1,5 +1,9 @@
+pub mod error;
+pub mod line_index;
 pub mod tokenizer;
 pub mod tokens;
+pub use error::TokenizeError;
+pub use line_index::LineIndex;
 pub use tokenizer::Tokenizer;
 pub use tokens::Token;
 pub mod tokens_buffer;
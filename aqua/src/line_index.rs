@@ -0,0 +1,161 @@
+/// Converts between byte offsets into a source and positions in it.
+///
+/// The positions are `(line, column)` pairs counted from zero, the same
+/// way the tokenizer counts them. The column is either a number of `char`s
+/// (like [crate::tokens::CommentedToken::offset]) or a number of UTF-16 code
+/// units, which is what the Language Server Protocol and VS Code use.
+///
+/// A line ends with `\n`, `\r\n` or a lone `\r`. The line terminator belongs
+/// to the line it ends.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    /// The byte offset of the start of every line.
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let bytes = source.as_bytes();
+        let mut line_starts = vec![0];
+        for (i, &byte) in bytes.iter().enumerate() {
+            match byte {
+                b'\n' => line_starts.push(i + 1),
+                b'\r' if bytes.get(i + 1) != Some(&b'\n') => line_starts.push(i + 1),
+                _ => {}
+            }
+        }
+        Self {
+            source,
+            line_starts,
+        }
+    }
+
+    /// The number of lines in the source.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Returns the line and the `char` column of the byte `offset`.
+    ///
+    /// Returns `None` if the offset is past the end of the source
+    /// or does not fall on a `char` boundary.
+    pub fn line_col(&self, offset: usize) -> Option<(u32, usize)> {
+        let (line, line_start) = self.line_of(offset)?;
+        Some((line, self.source[line_start..offset].chars().count()))
+    }
+
+    /// Returns the line and the UTF-16 column of the byte `offset`.
+    ///
+    /// Returns `None` if the offset is past the end of the source
+    /// or does not fall on a `char` boundary.
+    pub fn line_col_utf16(&self, offset: usize) -> Option<(u32, usize)> {
+        let (line, line_start) = self.line_of(offset)?;
+        Some((
+            line,
+            self.source[line_start..offset]
+                .chars()
+                .map(char::len_utf16)
+                .sum(),
+        ))
+    }
+
+    /// Returns the byte offset of the `char` column `col` on the line `line`.
+    ///
+    /// The column may point right after the last character of the line.
+    /// Returns `None` if the position is outside of the source.
+    pub fn offset(&self, line: u32, col: usize) -> Option<usize> {
+        self.offset_by(line, col, |_| 1)
+    }
+
+    /// Returns the byte offset of the UTF-16 column `col` on the line `line`.
+    ///
+    /// The column may point right after the last character of the line.
+    /// Returns `None` if the position is outside of the source or falls
+    /// in the middle of a surrogate pair.
+    pub fn offset_utf16(&self, line: u32, col: usize) -> Option<usize> {
+        self.offset_by(line, col, char::len_utf16)
+    }
+
+    fn line_of(&self, offset: usize) -> Option<(u32, usize)> {
+        if !self.source.is_char_boundary(offset) {
+            return None;
+        }
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        Some((line as u32, self.line_starts[line]))
+    }
+
+    fn line_content(&self, line: u32) -> Option<(usize, &'a str)> {
+        let line = line as usize;
+        let start = *self.line_starts.get(line)?;
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .copied()
+            .unwrap_or(self.source.len());
+        let content = self.source[start..end].trim_end_matches(['\n', '\r']);
+        Some((start, content))
+    }
+
+    fn offset_by(&self, line: u32, col: usize, col_len: fn(char) -> usize) -> Option<usize> {
+        let (line_start, content) = self.line_content(line)?;
+        let mut current_col = 0;
+        let mut current_byte = line_start;
+        for c in content.chars() {
+            if current_col >= col {
+                break;
+            }
+            current_col += col_len(c);
+            current_byte += c.len_utf8();
+        }
+        (current_col == col).then_some(current_byte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_offsets_to_positions() {
+        let index = LineIndex::new("a <- 1\nb <- \"ż😀\"\n");
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.line_col(0), Some((0, 0)));
+        assert_eq!(index.line_col(6), Some((0, 6)));
+        assert_eq!(index.line_col(7), Some((1, 0)));
+        // "ż" is two bytes long, "😀" is four
+        assert_eq!(index.line_col(13), Some((1, 6)));
+        assert_eq!(index.line_col(15), Some((1, 7)));
+        assert_eq!(index.line_col(19), Some((1, 8)));
+        assert_eq!(index.line_col_utf16(19), Some((1, 9)));
+        assert_eq!(index.line_col(21), Some((2, 0)));
+        assert_eq!(index.line_col(14), None);
+        assert_eq!(index.line_col(22), None);
+    }
+
+    #[test]
+    fn positions_to_byte_offsets() {
+        let index = LineIndex::new("a <- 1\nb <- \"ż😀\"\n");
+        assert_eq!(index.offset(0, 0), Some(0));
+        assert_eq!(index.offset(0, 6), Some(6));
+        assert_eq!(index.offset(0, 7), None);
+        assert_eq!(index.offset(1, 7), Some(15));
+        assert_eq!(index.offset(1, 8), Some(19));
+        assert_eq!(index.offset_utf16(1, 9), Some(19));
+        assert_eq!(index.offset_utf16(1, 8), None);
+        assert_eq!(index.offset(2, 0), Some(21));
+        assert_eq!(index.offset(3, 0), None);
+    }
+
+    #[test]
+    fn line_endings() {
+        let index = LineIndex::new("a\r\nb\rc\n");
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_col(1), Some((0, 1)));
+        assert_eq!(index.line_col(2), Some((0, 2)));
+        assert_eq!(index.line_col(3), Some((1, 0)));
+        assert_eq!(index.line_col(5), Some((2, 0)));
+        assert_eq!(index.offset(0, 2), None);
+        assert_eq!(index.offset(2, 1), Some(6));
+    }
+}
@@ -1,5 +1,6 @@
-use log::{debug, trace};
+use log::trace;
 
+use crate::error::{TokenizeError, TokenizeErrorKind};
 use crate::tokens::{
     CommentedToken,
     Token::{self, *},
@@ -8,18 +9,19 @@ use crate::tokens::{
 /// Tokenizer for an R program.
 ///
 /// Transforms an R program into an array of language tokens.
+/// The tokenizer scans the bytes of the input in place and can be used
+/// as an iterator to get the tokens one at a time.
 pub struct Tokenizer<'a> {
     line: u32,
     offset: usize,
-    it: usize,
-    source: Vec<char>,
+    byte: usize,
     raw_source: &'a str,
+    lossless: bool,
+    /// Whether only whitespace was seen since the last newline
+    at_line_start: bool,
+    finished: bool,
 }
 
-const SYMBOL_ENDING: [char; 29] = [
-    ' ', '(', ')', '{', '}', '#', ';', '\n', '\t', '\r', '+', '-', '/', '\\', '%', '*', '^', '!',
-    '&', '|', '<', '>', '=', ',', '[', ']', '$', '`', '"',
-];
 impl<'a> Tokenizer<'a> {
     /// Returns a new tokenizer from an R program.
     ///
@@ -45,14 +47,43 @@ impl<'a> Tokenizer<'a> {
         Self {
             line: 0,
             offset: 0,
-            it: 0,
-            source: input.chars().collect::<Vec<_>>(),
+            byte: 0,
             raw_source: input,
+            lossless: false,
+            at_line_start: true,
+            finished: false,
         }
     }
 
+    /// Makes the tokenizer emit the spaces and tabs between tokens
+    /// as [Token::Whitespace] tokens.
+    ///
+    /// In this mode no character of the input is dropped: joining the source text
+    /// of all tokens (see [CommentedToken::source_text]) gives back the input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tergo_tokenizer::tokenizer::Tokenizer;
+    ///
+    /// let r_program = "a  <- 7 # seven\n";
+    /// let tokens = Tokenizer::new(r_program).lossless().tokenize().unwrap();
+    /// let text = tokens
+    ///     .iter()
+    ///     .map(|token| token.source_text(r_program))
+    ///     .collect::<String>();
+    /// assert_eq!(text, r_program);
+    /// ```
+    pub fn lossless(mut self) -> Self {
+        self.lossless = true;
+        self
+    }
+
     /// Returns an array of tokens.
     ///
+    /// This collects the tokens produced by the [Iterator] implementation
+    /// of the tokenizer. The last token is always [Token::EOF].
+    ///
     /// # Examples
     ///
     /// ```
@@ -63,449 +94,590 @@ impl<'a> Tokenizer<'a> {
     /// print(a + 1)
     /// "#;
     /// let mut tokenizer = Tokenizer::new(r_program);
-    /// let tokens = tokenizer.tokenize();
+    /// let tokens = tokenizer.tokenize().unwrap();
     /// println!("{tokens:?}");
     /// ```
     ///
-    pub fn tokenize(&mut self) -> Vec<CommentedToken> {
+    /// # Errors
+    ///
+    /// Returns a [TokenizeError] pointing at the first piece of the input
+    /// that is not a valid R token, e.g. an unterminated string.
+    pub fn tokenize(&mut self) -> Result<Vec<CommentedToken<'a>>, TokenizeError> {
         let mut tokens = vec![];
-        while self.it < self.source.len() {
-            match self.source[self.it] {
-                ' ' | '\t' => {
-                    self.next();
-                }
-                '\r' => {
-                    self.next();
-                    self.push_token(Newline, &mut tokens);
-                    self.next_line();
-                }
-                '\n' => {
-                    self.push_token(Newline, &mut tokens);
-                    self.next_line();
-                }
-                ';' => {
-                    self.push_token(Semicolon, &mut tokens);
-                    self.next();
-                }
-                ',' => {
-                    self.push_token(Comma, &mut tokens);
-                    self.next();
-                }
-                '(' => {
-                    self.push_token(LParen, &mut tokens);
-                    self.next();
-                }
-                ')' => {
-                    self.push_token(RParen, &mut tokens);
-                    self.next();
-                }
-                '{' => {
-                    self.push_token(LBrace, &mut tokens);
-                    self.next();
-                }
-                '}' => {
-                    self.push_token(RBrace, &mut tokens);
-                    self.next();
-                }
-                '[' => {
-                    self.push_token(LBracket, &mut tokens);
-                    self.next();
-                }
-                ']' => {
-                    self.push_token(RBracket, &mut tokens);
-                    self.next();
-                }
-                '\'' | '\"' => {
-                    self.string_literal(&mut tokens);
-                    self.next();
-                }
-                '*' => {
-                    let next_char = self.lookahead().expect("Script does not end on '*'");
-                    match next_char {
-                        // That's undocumented, but it actually works...
-                        '*' => {
-                            self.push_token(Power, &mut tokens);
-                            self.next();
-                        }
-                        _ => self.push_token(Multiply, &mut tokens),
-                    }
-                    self.next();
-                }
-                '/' => {
-                    self.push_token(Divide, &mut tokens);
-                    self.next();
-                }
-                '^' => {
-                    self.push_token(Power, &mut tokens);
-                    self.next();
-                }
-                '+' => {
-                    self.push_token(Plus, &mut tokens);
-                    self.next();
-                }
-                '?' => {
-                    self.push_token(Help, &mut tokens);
-                    self.next();
-                }
-                '<' => {
-                    let next_char = self.lookahead().expect("Script does not end on '<'");
-                    match next_char {
-                        '-' => {
-                            self.push_token(LAssign, &mut tokens);
-                            self.next();
-                        }
-                        '=' => {
-                            self.push_token(LowerEqual, &mut tokens);
-                            self.next();
-                        }
-                        '<' => {
-                            self.push_token(SuperAssign, &mut tokens);
-                            self.next();
-                            self.next();
-                        }
-                        _ => self.push_token(LowerThan, &mut tokens),
-                    }
-                    self.next();
-                }
-                '>' => {
-                    let next_char = self.lookahead().expect("Script does not end on '>'");
-                    match next_char {
-                        '=' => {
-                            self.push_token(GreaterEqual, &mut tokens);
-                            self.next();
-                        }
-                        _ => {
-                            self.push_token(GreaterThan, &mut tokens);
-                        }
-                    }
-                    self.next();
-                }
-                '|' => {
-                    let next_char = self.lookahead().expect("Script does not end on '|'");
-                    match next_char {
-                        '|' => {
-                            self.push_token(Or, &mut tokens);
-                            self.next();
-                        }
-                        '>' => {
-                            self.push_token(Pipe, &mut tokens);
-                            self.next();
-                        }
-                        _ => self.push_token(VectorizedOr, &mut tokens),
-                    }
-                    self.next();
-                }
-                '&' => {
-                    let next_char = self.lookahead().expect("Script does not end on '&'");
-                    match next_char {
-                        '&' => {
-                            self.push_token(And, &mut tokens);
-                            self.next();
-                        }
-                        _ => self.push_token(VectorizedAnd, &mut tokens),
-                    }
-                    self.next();
-                }
-                '=' => {
-                    let next_char = self.lookahead().expect("Script does not end on '='");
-                    match next_char {
-                        '=' => {
-                            self.push_token(Equal, &mut tokens);
-                            self.next();
-                        }
-                        _ => self.push_token(OldAssign, &mut tokens),
-                    }
-                    self.next();
-                }
-                '$' => {
-                    self.push_token(Dollar, &mut tokens);
-                    self.next();
-                }
-                '-' => {
-                    let next_char = self.lookahead().expect("Script does not end on '-'");
-                    match next_char {
-                        '>' => {
-                            self.push_token(RAssign, &mut tokens);
-                            self.next();
-                        }
-                        _ => self.push_token(Minus, &mut tokens),
-                    }
-                    self.next();
-                }
-                '!' => {
-                    self.next();
-                    match self.source[self.it..] {
-                        ['=', ..] => {
-                            self.push_token(NotEqual, &mut tokens);
-                            self.next();
-                        }
-                        _ => self.push_token(UnaryNot, &mut tokens),
-                    }
-                }
-                '.' => {
-                    let next_char = self.lookahead().expect("Script does not end on '.'");
-                    match next_char {
-                        'a'..='z' | 'A'..='Z' => {
-                            self.identifier(&mut tokens);
-                        }
-                        '0'..='9' => {
-                            self.number_literal(&mut tokens);
-                        }
-                        _ => {
-                            debug!(
-                                "Found not alphabetic and non-numeric character after a dot. \
-                                 Treating it as an identifier."
-                            );
-                            self.identifier(&mut tokens);
-                        }
-                    }
-                }
-                '`' | '_' => {
-                    self.identifier(&mut tokens);
-                }
-                '%' => {
-                    let next_char = self.lookahead().expect("Script does not end on '%'");
-                    match next_char {
-                        '%' => {
-                            self.push_token(Modulo, &mut tokens);
-                            self.next();
-                            self.next();
-                        }
-                        _ => {
-                            let custom_binary_start = self.it;
-                            self.next();
-                            while self.source[self.it] != '%' {
-                                self.next();
-                            }
-                            let custom_binary_end = self.it;
-                            self.push_token(
-                                Special(&self.raw_source[custom_binary_start..=custom_binary_end]),
-                                &mut tokens,
-                            );
-                            self.next()
-                        }
-                    }
-                }
-                'a'..='z' | 'A'..='Z' => {
-                    self.identifier_or_reserved(&mut tokens);
-                }
-                '0'..='9' => {
-                    self.number_literal(&mut tokens);
-                }
-                '\\' => {
-                    self.push_token(Lambda, &mut tokens);
-                    self.next();
-                }
-                '#' => {
-                    self.comment(&mut tokens);
-                }
-                '~' => {
-                    self.push_token(Tilde, &mut tokens);
-                    self.next();
-                }
-                '@' => {
-                    self.push_token(Slot, &mut tokens);
-                    self.next();
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
+        }
+        tokens.push(self.eof());
+        trace!("Tokenized {} tokens", tokens.len());
+        Ok(tokens)
+    }
+
+    /// Returns the next token or `None` at the end of the input.
+    #[inline(always)]
+    fn next_token(&mut self) -> Result<Option<CommentedToken<'a>>, TokenizeError> {
+        while self.byte < self.raw_source.len() {
+            let start = (self.line, self.offset, self.byte);
+            if let Some(token) = self.scan()? {
+                if !matches!(token, Whitespace(_)) {
+                    self.at_line_start = matches!(token, Newline);
+                }
+                return Ok(Some(self.commented_token(token, start)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn eof(&mut self) -> CommentedToken<'a> {
+        self.finished = true;
+        self.commented_token(EOF, (self.line, self.offset, self.byte))
+    }
+
+    /// Scans a single token starting at the current position.
+    ///
+    /// Returns `None` for the whitespace skipped outside of the lossless mode.
+    #[inline(always)]
+    fn scan(&mut self) -> Result<Option<Token<'a>>, TokenizeError> {
+        let start_byte = self.byte;
+        let token = match self.rest() {
+            [b' ' | b'\t', ..] => {
+                self.advance_while(|c| c == ' ' || c == '\t');
+                if !self.lossless {
+                    return Ok(None);
+                }
+                Whitespace(&self.raw_source[start_byte..self.byte])
+            }
+            // CRLF is a single line break, a lone CR is a line break too
+            [b'\r', b'\n', ..] => {
+                self.bump();
+                self.next_line();
+                Newline
+            }
+            [b'\r' | b'\n', ..] => {
+                self.next_line();
+                Newline
+            }
+            [b';', ..] => self.single(Semicolon),
+            [b',', ..] => self.single(Comma),
+            [b'(', ..] => self.single(LParen),
+            [b')', ..] => self.single(RParen),
+            [b'{', ..] => self.single(LBrace),
+            [b'}', ..] => self.single(RBrace),
+            [b'[', ..] => self.single(LBracket),
+            [b']', ..] => self.single(RBracket),
+            [b'\'' | b'"', ..] => self.string_literal()?,
+            // That's undocumented, but it actually works...
+            [b'*', b'*', ..] => self.double(Power),
+            [b'*', ..] => self.single(Multiply),
+            [b'/', ..] => self.single(Divide),
+            [b'^', ..] => self.single(Power),
+            [b'+', ..] => self.single(Plus),
+            [b'?', ..] => self.single(Help),
+            [b'<', b'<', b'-', ..] => self.triple(SuperAssign),
+            [b'<', b'-', ..] => self.double(LAssign),
+            [b'<', b'=', ..] => self.double(LowerEqual),
+            [b'<', ..] => self.single(LowerThan),
+            [b'>', b'=', ..] => self.double(GreaterEqual),
+            [b'>', ..] => self.single(GreaterThan),
+            [b'|', b'|', ..] => self.double(Or),
+            [b'|', b'>', ..] => self.double(Pipe),
+            [b'|', ..] => self.single(VectorizedOr),
+            [b'&', b'&', ..] => self.double(And),
+            [b'&', ..] => self.single(VectorizedAnd),
+            [b'=', b'=', ..] => self.double(Equal),
+            [b'=', ..] => self.single(OldAssign),
+            [b'$', ..] => self.single(Dollar),
+            [b'-', b'>', b'>', ..] => self.triple(SuperRAssign),
+            [b'-', b'>', ..] => self.double(RAssign),
+            [b'-', ..] => self.single(Minus),
+            [b'!', b'=', ..] => self.double(NotEqual),
+            [b'!', ..] => self.single(UnaryNot),
+            [b'.', b'0'..=b'9', ..] => self.number_literal(),
+            [b'.', ..] => self.identifier(),
+            [b'`', ..] => self.quoted_identifier()?,
+            // A lone underscore is the native pipe placeholder.
+            // R does not allow identifiers to start with an underscore.
+            [b'_', ..] => match self.lookahead() {
+                Some(c) if is_identifier_char(c) => return Err(self.unexpected_character()),
+                _ => self.single(Placeholder),
+            },
+            [b'%', b'%', ..] => self.double(Modulo),
+            [b'%', ..] => self.special_operator()?,
+            [b'r' | b'R', b'"' | b'\'', ..] => self.raw_string_literal()?,
+            [b'0'..=b'9', ..] => self.number_literal(),
+            [b'\\', ..] => self.single(Lambda),
+            [b'#', ..] => self.comment(),
+            [b'~', ..] => self.single(Tilde),
+            [b'@', ..] => self.single(Slot),
+            [b':', b':', b':', ..] => self.triple(NsGetInt),
+            [b':', b':', ..] => self.double(NsGet),
+            [b':', b'=', ..] => self.double(ColonAssign),
+            [b':', ..] => self.single(Colon),
+            [b'a'..=b'z' | b'A'..=b'Z', ..] => self.identifier(),
+            _ => match self.current() {
+                Some(c) if c.is_alphabetic() => self.identifier(),
+                _ => return Err(self.unexpected_character()),
+            },
+        };
+        Ok(Some(token))
+    }
+
+    fn unexpected_character(&self) -> TokenizeError {
+        TokenizeError::new(
+            TokenizeErrorKind::UnexpectedCharacter(self.current().unwrap_or_default()),
+            self.line,
+            self.offset,
+        )
+    }
+
+    fn single(&mut self, token: Token<'a>) -> Token<'a> {
+        self.skip_ascii(1);
+        token
+    }
+
+    fn double(&mut self, token: Token<'a>) -> Token<'a> {
+        self.skip_ascii(2);
+        token
+    }
+
+    fn triple(&mut self, token: Token<'a>) -> Token<'a> {
+        self.skip_ascii(3);
+        token
+    }
+
+    fn special_operator(&mut self) -> Result<Token<'a>, TokenizeError> {
+        let (start_line, start_offset) = (self.line, self.offset);
+        let start_byte = self.byte;
+        self.bump();
+        loop {
+            match self.rest() {
+                [b'%', ..] => break,
+                [] | [b'\n' | b'\r', ..] => {
+                    return Err(TokenizeError::new(
+                        TokenizeErrorKind::UnterminatedSpecialOperator,
+                        start_line,
+                        start_offset,
+                    ));
+                }
+                _ => self.bump(),
+            }
+        }
+        self.bump();
+        Ok(Special(&self.raw_source[start_byte..self.byte]))
+    }
+
+    fn string_literal(&mut self) -> Result<Token<'a>, TokenizeError> {
+        let delimiter = self.rest()[0];
+        let (start_line, start_offset) = (self.line, self.offset);
+        let start_byte = self.byte;
+        self.bump();
+        loop {
+            match self.rest() {
+                [] => {
+                    return Err(TokenizeError::new(
+                        TokenizeErrorKind::UnterminatedString,
+                        start_line,
+                        start_offset,
+                    ));
+                }
+                [b'\\', ..] => self.escape_sequence()?,
+                [c, ..] if *c == delimiter => break,
+                [b'\n', ..] => self.next_line(),
+                _ => self.bump(),
+            }
+        }
+        self.bump();
+        Ok(Literal(&self.raw_source[start_byte..self.byte]))
+    }
+
+    /// Validates the escape sequence starting at the current backslash
+    /// and moves past it. The escapes recognized by R are:
+    /// * `\n`, `\r`, `\t`, `\b`, `\a`, `\f`, `\v`, `\\`, `\'`, `\"`, `` \` `` and an escaped newline,
+    /// * `\nnn` - one to three octal digits,
+    /// * `\xhh` - one or two hexadecimal digits,
+    /// * `\uhhhh` and `\u{hhhh}` - one to four hexadecimal digits,
+    /// * `\Uhhhhhhhh` and `\U{hhhhhhhh}` - one to eight hexadecimal digits.
+    fn escape_sequence(&mut self) -> Result<(), TokenizeError> {
+        let (start_line, start_offset) = (self.line, self.offset);
+        self.bump();
+        let error = |kind| Err(TokenizeError::new(kind, start_line, start_offset));
+        match self.rest() {
+            // Reported as an unterminated string by the caller
+            [] => {}
+            [b'\n', ..] => self.next_line(),
+            [b'n' | b'r' | b't' | b'b' | b'a' | b'f' | b'v' | b'\\' | b'\'' | b'"' | b'`', ..] => {
+                self.bump()
+            }
+            [b'0'..=b'7', ..] => {
+                self.parse_digits(3, |c| matches!(c, b'0'..=b'7'));
+            }
+            [b'x', ..] => {
+                self.bump();
+                if self.parse_digits(2, |c| c.is_ascii_hexdigit()) == 0 {
+                    return error(TokenizeErrorKind::MalformedEscape('x'));
                 }
-                ':' => {
-                    self.next();
-                    match self.source[self.it..] {
-                        [':', ':', ..] => {
-                            self.push_token(NsGetInt, &mut tokens);
-                            self.next();
-                            self.next();
-                        }
-                        [':', ..] => {
-                            self.push_token(NsGet, &mut tokens);
-                            self.next()
-                        }
-                        ['=', ..] => {
-                            self.push_token(ColonAssign, &mut tokens);
-                            self.next()
-                        }
-                        _ => self.push_token(Colon, &mut tokens),
+            }
+            [escape @ (b'u' | b'U'), ..] => {
+                let escape = *escape as char;
+                let max_digits = if escape == 'u' { 4 } else { 8 };
+                self.bump();
+                let braced = matches!(self.rest(), [b'{', ..]);
+                if braced {
+                    self.bump();
+                }
+                if self.parse_digits(max_digits, |c| c.is_ascii_hexdigit()) == 0 {
+                    return error(TokenizeErrorKind::MalformedEscape(escape));
+                }
+                if braced {
+                    if !matches!(self.rest(), [b'}', ..]) {
+                        return error(TokenizeErrorKind::MalformedEscape(escape));
                     }
+                    self.bump();
                 }
-                _ => unreachable!(),
+            }
+            _ => {
+                let unrecognized = self.current().unwrap_or_default();
+                return error(TokenizeErrorKind::UnrecognizedEscape(unrecognized));
             }
         }
-        tokens.push(CommentedToken::new(EOF, self.line, self.offset));
-        trace!("Tokenized: {:?}", tokens);
-        tokens
+        Ok(())
     }
 
-    fn push_token(&mut self, token: Token<'a>, tokens: &mut Vec<CommentedToken<'a>>) {
-        tokens.push(CommentedToken::new(token, self.line, self.offset));
+    /// Consumes at most `max` ASCII characters matching `is_digit`
+    /// and returns how many were consumed.
+    fn parse_digits(&mut self, max: usize, is_digit: impl Fn(u8) -> bool) -> usize {
+        let mut count = 0;
+        while count < max && self.rest().first().is_some_and(|c| is_digit(*c)) {
+            self.bump();
+            count += 1;
+        }
+        count
     }
 
-    fn string_literal(&mut self, tokens: &mut Vec<CommentedToken<'a>>) {
-        let delimiter = self.source[self.it];
+    /// Raw strings: r"(...)", R"[...]", r"---{...}---" and so on.
+    ///
+    /// The body ends at the first closing bracket matching the opening one,
+    /// followed by the same number of dashes and the opening quote.
+    fn raw_string_literal(&mut self) -> Result<Token<'a>, TokenizeError> {
         let (start_line, start_offset) = (self.line, self.offset);
-        let start_it = self.it;
-        self.next();
-        while self.source[self.it] != delimiter || self.source[self.it - 1] == '\\' {
-            if self.source[self.it] == '\n' {
-                self.next_line();
-            } else {
-                self.next();
+        let start_byte = self.byte;
+        self.bump();
+        let quote = self.rest()[0];
+        self.bump();
+        let mut dashes = 0;
+        while matches!(self.rest(), [b'-', ..]) {
+            dashes += 1;
+            self.bump();
+        }
+        let closing_bracket = match self.rest() {
+            [b'(', ..] => b')',
+            [b'[', ..] => b']',
+            [b'{', ..] => b'}',
+            _ => {
+                return Err(TokenizeError::new(
+                    TokenizeErrorKind::MalformedRawString,
+                    start_line,
+                    start_offset,
+                ));
+            }
+        };
+        self.bump();
+        loop {
+            match self.rest() {
+                [] => {
+                    return Err(TokenizeError::new(
+                        TokenizeErrorKind::UnterminatedRawString,
+                        start_line,
+                        start_offset,
+                    ));
+                }
+                [b'\n', ..] => self.next_line(),
+                [c, after_bracket @ ..]
+                    if *c == closing_bracket
+                        && after_bracket.len() > dashes
+                        && after_bracket[..dashes].iter().all(|c| *c == b'-')
+                        && after_bracket[dashes] == quote =>
+                {
+                    break
+                }
+                _ => self.bump(),
             }
         }
-        tokens.push(CommentedToken::new(
-            Literal(&self.raw_source[start_it..=self.it]),
-            start_line,
-            start_offset,
-        ));
+        for _ in 0..dashes + 2 {
+            self.bump();
+        }
+        Ok(Literal(&self.raw_source[start_byte..self.byte]))
     }
 
     fn parse_decimal(&mut self) {
-        while self.it < self.source.len() && self.source[self.it].is_ascii_digit() {
-            self.next();
+        while self.rest().first().is_some_and(u8::is_ascii_digit) {
+            self.bump();
         }
     }
 
     fn parse_hexadecimal(&mut self) {
-        while self.it < self.source.len() && self.source[self.it].is_ascii_hexdigit() {
-            self.next();
+        while self.rest().first().is_some_and(u8::is_ascii_hexdigit) {
+            self.bump();
         }
     }
 
-    fn number_literal(&mut self, tokens: &mut Vec<CommentedToken<'a>>) {
-        let start_it = self.it;
-        match self.source[self.it..] {
+    /// Numeric constants follow R's grammar: decimal numbers with an optional
+    /// fraction and exponent (`1`, `1.`, `.5`, `1e-3`), hexadecimal numbers
+    /// with an optional fraction and binary exponent (`0xFF`, `0x1p10`, `0x1.8p3`),
+    /// and either of them with an `L` (integer) or `i` (complex) suffix.
+    fn number_literal(&mut self) -> Token<'a> {
+        let start_byte = self.byte;
+        match self.rest() {
             // Hexadecimal
-            ['0', 'x', ..] | ['0', 'X', ..] => {
-                self.next();
-                self.next();
+            [b'0', b'x' | b'X', ..] => {
+                self.bump();
+                self.bump();
                 self.parse_hexadecimal();
-                if let ['.', ..] = self.source[self.it..] {
-                    self.next();
+                if let [b'.', ..] = self.rest() {
+                    self.bump();
                     self.parse_hexadecimal();
-                    match self.source[self.it..] {
-                        ['p', ..] | ['P', ..] => {
-                            self.next();
-                            self.parse_hexadecimal();
-                        }
-                        _ => {}
-                    }
                 }
+                self.parse_exponent(b'p');
             }
             // Decimal
             _ => {
                 self.parse_decimal();
-                match self.source[self.it..] {
-                    ['.', ..] => {
-                        self.next();
-                        self.parse_decimal();
-                        match self.source[self.it..] {
-                            ['e', '+', ..] | ['E', '+', ..] | ['e', '-', ..] | ['E', '-', ..] => {
-                                self.next();
-                                self.next();
-                                self.parse_decimal();
-                            }
-                            ['e', ..] | ['E', ..] => {
-                                self.next();
-                                self.parse_decimal();
-                            }
-                            _ => {}
-                        }
-                    }
-                    ['e', '+', ..] | ['E', '+', ..] | ['e', '-', ..] | ['E', '-', ..] => {
-                        self.next();
-                        self.next();
-                        self.parse_decimal();
-                    }
-                    ['e', ..] | ['E', ..] => {
-                        self.next();
-                        self.parse_decimal();
-                    }
-                    ['L', ..] => {
-                        self.next();
-                    }
-                    _ => {}
+                if let [b'.', ..] = self.rest() {
+                    self.bump();
+                    self.parse_decimal();
                 }
+                self.parse_exponent(b'e');
             }
         }
-        self.push_token(Literal(&self.raw_source[start_it..self.it]), tokens);
-    }
-
-    fn identifier(&mut self, tokens: &mut Vec<CommentedToken<'a>>) {
-        let start_it = self.it;
-        let mut in_backticks = false;
-        while self.it < self.source.len() && in_backticks
-            || self.source[self.it].is_alphabetic()
-            || self.source[self.it] == '.'
-            || self.source[self.it] == '_'
-            || self.source[self.it] == '`'
-        {
-            if self.source[self.it] == '`' {
-                in_backticks = !in_backticks;
+        if let [b'L' | b'i', ..] = self.rest() {
+            self.bump();
+        }
+        Literal(&self.raw_source[start_byte..self.byte])
+    }
+
+    /// Consumes an exponent introduced by `marker` (in either case)
+    /// with an optional sign. The digits of the exponent are always decimal.
+    fn parse_exponent(&mut self, marker: u8) {
+        match self.rest() {
+            [e, b'+' | b'-', b'0'..=b'9', ..] if e.to_ascii_lowercase() == marker => {
+                self.bump();
+                self.bump();
+                self.parse_decimal();
+            }
+            [e, b'0'..=b'9', ..] if e.to_ascii_lowercase() == marker => {
+                self.bump();
+                self.parse_decimal();
             }
-            self.next();
+            _ => {}
         }
-        match &self.raw_source[start_it..self.it] {
-            "TRUE" | "T" => self.push_token(Literal("TRUE"), tokens),
-            "FALSE" | "F" => self.push_token(Literal("FALSE"), tokens),
-            _ => self.push_token(Symbol(&self.raw_source[start_it..self.it]), tokens),
+    }
+
+    /// Identifiers follow R's rules: they start with a letter or a dot
+    /// not followed by a digit, and continue with letters, digits, dots
+    /// and underscores. This also covers `...` and `..1`, `..2` and so on.
+    ///
+    /// Reserved words are recognized here as well.
+    fn identifier(&mut self) -> Token<'a> {
+        let start_byte = self.byte;
+        self.advance_while(is_identifier_char);
+
+        match &self.raw_source[start_byte..self.byte] {
+            "continue" => Continue,
+            "break" => Break,
+            "for" => For,
+            "if" => If,
+            "else" => Else,
+            "in" => In,
+            "while" => While,
+            "repeat" => Repeat,
+            "function" => Function,
+            "TRUE" => Literal("TRUE"),
+            "FALSE" => Literal("FALSE"),
+            "NULL" => Null,
+            "NA" => Na,
+            "NA_integer_" => NaInteger,
+            "NA_real_" => NaReal,
+            "NA_character_" => NaCharacter,
+            "Inf" => Inf,
+            "NaN" => NaN,
+            "..." => Dots,
+            dot_dot_n
+                if dot_dot_n.len() > 2
+                    && dot_dot_n.starts_with("..")
+                    && dot_dot_n[2..].bytes().all(|b| b.is_ascii_digit()) =>
+            {
+                DotDotN(dot_dot_n)
+            }
+            identifier => Symbol(identifier),
         }
     }
 
-    fn identifier_or_reserved(&mut self, tokens: &mut Vec<CommentedToken<'a>>) {
-        let start_it = self.it;
-        while self.it < self.source.len() && !SYMBOL_ENDING.contains(&self.source[self.it]) {
-            self.next();
+    /// Backtick-quoted symbols, e.g. `` `my var` ``.
+    fn quoted_identifier(&mut self) -> Result<Token<'a>, TokenizeError> {
+        let (start_line, start_offset) = (self.line, self.offset);
+        let start_byte = self.byte;
+        self.bump();
+        loop {
+            match self.rest() {
+                [] => {
+                    return Err(TokenizeError::new(
+                        TokenizeErrorKind::UnterminatedBacktick,
+                        start_line,
+                        start_offset,
+                    ))
+                }
+                [b'`', ..] => break,
+                [b'\\', b'\n', ..] => {
+                    self.bump();
+                    self.next_line();
+                }
+                [b'\\', _, ..] => {
+                    self.bump();
+                    self.bump();
+                }
+                [b'\n', ..] => self.next_line(),
+                _ => self.bump(),
+            }
         }
+        self.bump();
+        Ok(Symbol(&self.raw_source[start_byte..self.byte]))
+    }
 
-        match &self.raw_source[start_it..self.it] {
-            "continue" => self.push_token(Continue, tokens),
-            "break" => self.push_token(Break, tokens),
-            "for" => self.push_token(For, tokens),
-            "if" => self.push_token(If, tokens),
-            "else" => self.push_token(Else, tokens),
-            "in" => self.push_token(In, tokens),
-            "while" => self.push_token(While, tokens),
-            "repeat" => self.push_token(Repeat, tokens),
-            "function" => self.push_token(Function, tokens),
-            "TRUE" | "T" => self.push_token(Literal("TRUE"), tokens),
-            "FALSE" | "F" => self.push_token(Literal("FALSE"), tokens),
-            _ => self.push_token(Symbol(&self.raw_source[start_it..self.it]), tokens),
+    fn comment(&mut self) -> Token<'a> {
+        let start_byte = self.byte;
+        self.advance_while(|c| c != '\n' && c != '\r');
+        let text = &self.raw_source[start_byte..self.byte];
+        if self.at_line_start {
+            line_comment(text)
+        } else {
+            InlineComment(text)
         }
     }
 
-    fn comment(&mut self, tokens: &mut Vec<CommentedToken<'a>>) {
-        let start_it = self.it;
-        while self.it < self.source.len() && self.source[self.it] != '\n' {
-            self.next();
+    /// Returns the token spanning from `start` (line, offset, byte) to the current position.
+    fn commented_token(&self, token: Token<'a>, start: (u32, usize, usize)) -> CommentedToken<'a> {
+        CommentedToken {
+            token,
+            line: start.0,
+            offset: start.1,
+            end_line: self.line,
+            end_offset: self.offset,
+            span: start.2..self.byte,
+            leading_comments: None,
+            inline_comment: None,
+            comments_span: None,
         }
+    }
+
+    /// The part of the input that has not been tokenized yet.
+    fn rest(&self) -> &'a [u8] {
+        &self.raw_source.as_bytes()[self.byte..]
+    }
 
-        match tokens.last() {
-            Some(CommentedToken {
-                token: Newline,
-                line: _,
-                offset: _,
-                leading_comments: _,
-                inline_comment: _,
-            }) => self.push_token(Comment(&self.raw_source[start_it..self.it]), tokens),
-            Some(_) => self.push_token(InlineComment(&self.raw_source[start_it..self.it]), tokens),
-            None => self.push_token(Comment(&self.raw_source[start_it..self.it]), tokens),
+    fn current(&self) -> Option<char> {
+        match self.rest() {
+            [] => None,
+            [b, ..] if b.is_ascii() => Some(*b as char),
+            _ => self.raw_source[self.byte..].chars().next(),
         }
     }
 
-    fn next(&mut self) {
-        self.it += 1;
+    /// Moves past the current character.
+    fn bump(&mut self) {
+        // The length of a UTF-8 encoded character can be read from its first byte
+        self.byte += match self.rest().first() {
+            Some(0xF0..) => 4,
+            Some(0xE0..) => 3,
+            Some(0xC0..) => 2,
+            _ => 1,
+        };
         self.offset += 1;
     }
 
+    /// Moves past `count` ASCII characters.
+    fn skip_ascii(&mut self, count: usize) {
+        self.byte += count;
+        self.offset += count;
+    }
+
+    /// Moves past the characters matching `predicate`. They must not be line breaks.
+    fn advance_while(&mut self, predicate: impl Fn(char) -> bool) {
+        loop {
+            match self.rest() {
+                [b, ..] if b.is_ascii() => {
+                    if !predicate(*b as char) {
+                        break;
+                    }
+                    self.byte += 1;
+                    self.offset += 1;
+                }
+                [] => break,
+                _ => match self.current() {
+                    Some(c) if predicate(c) => {
+                        self.byte += c.len_utf8();
+                        self.offset += 1;
+                    }
+                    _ => break,
+                },
+            }
+        }
+    }
+
     fn next_line(&mut self) {
-        self.it += 1;
+        self.byte += 1;
         self.line += 1;
         self.offset = 0;
     }
 
     fn lookahead(&self) -> Option<char> {
-        if self.it + 1 < self.source.len() {
-            Some(self.source[self.it + 1])
-        } else {
-            None
+        self.raw_source[self.byte..].chars().nth(1)
+    }
+}
+
+/// Yields the tokens one by one, finishing with [Token::EOF].
+///
+/// After an error the iterator is exhausted.
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<CommentedToken<'a>, TokenizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
         }
+        match self.next_token() {
+            Ok(Some(token)) => Some(Ok(token)),
+            Ok(None) => Some(Ok(self.eof())),
+            Err(error) => {
+                self.finished = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+impl std::iter::FusedIterator for Tokenizer<'_> {}
+
+/// Tells apart the kinds of comments occupying a whole line.
+fn line_comment(text: &str) -> Token<'_> {
+    match text.as_bytes() {
+        [b'#', b'\'', ..] => RoxygenComment(text),
+        [b'#', b'|', ..] => ChunkOptionComment(text),
+        [b'#', b'>', ..] => KnitrOutputComment(text),
+        _ if is_section_header(text) => SectionComment(text),
+        _ => Comment(text),
     }
 }
+
+/// RStudio treats a comment ending with at least four `-`, `=` or `#`
+/// as a section header, e.g. `# Section ----` or `## Section ====`.
+fn is_section_header(text: &str) -> bool {
+    let text = text.trim_end();
+    let label = text.trim_start_matches('#');
+    ['-', '=', '#'].into_iter().any(|marker| {
+        let without_marker = label.trim_end_matches(marker);
+        label.len() - without_marker.len() >= 4 && !without_marker.trim().is_empty()
+    })
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '.' || c == '_'
+}
@@ -3,18 +3,66 @@
 /// This represents a single token in an R program along with the line on which it occurs
 /// and the column offset. Additionally, it stores the comments that are associated
 /// with the token.
-#[derive(Debug, Clone)]
+///
+/// Lines and column offsets are counted from zero. Column offsets count `char`s,
+/// the byte positions are stored in `span`. See [crate::line_index::LineIndex]
+/// for conversions between the two and UTF-16 columns.
+///
+/// Serializes to an object holding the fields of the [Token] next to the
+/// fields of this struct, e.g. the symbol `x` on the first line is
+/// `{"kind":"Symbol","text":"x","line":0,"offset":0,"end_line":0,"end_offset":1,
+/// "span":{"start":0,"end":1},"leading_comments":null,"inline_comment":null,
+/// "comments_span":null}`.
+#[derive(Debug, Clone, Serialize)]
 pub struct CommentedToken<'a> {
     /// The actual token stored in this struct.
+    #[serde(flatten)]
     pub token: Token<'a>,
     /// The line of the start of this token.
     pub line: u32,
     /// The column offset of the start of this token.
     pub offset: usize,
+    /// The line of the end of this token.
+    pub end_line: u32,
+    /// The column offset right after the last character of this token.
+    pub end_offset: usize,
+    /// The byte range of this token in the source.
+    pub span: Range<usize>,
     /// Preceding comments.
     pub leading_comments: Option<Vec<&'a str>>,
     /// Trailing inline comment.
     pub inline_comment: Option<&'a str>,
+    /// The span of this token together with its leading and inline comments.
+    /// Set by the parser when it attaches the comments, `None` without comments.
+    /// Boxed to keep the tokens small, most of them have no comments.
+    pub comments_span: Option<Box<Span>>,
+}
+
+/// A part of the source, from a start position to an end position.
+///
+/// The positions follow the convention of [CommentedToken]: lines and
+/// column offsets are counted from zero and the end is exclusive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct Span {
+    pub start_line: u32,
+    pub start_offset: usize,
+    pub end_line: u32,
+    pub end_offset: usize,
+    /// The byte range in the source.
+    pub bytes: Range<usize>,
+}
+
+impl Span {
+    /// Returns the span from the start of `self` to the end of `end`.
+    pub fn to(&self, end: &Span) -> Span {
+        Span {
+            start_line: self.start_line,
+            start_offset: self.start_offset,
+            end_line: end.end_line,
+            end_offset: end.end_offset,
+            bytes: self.bytes.start..end.bytes.end,
+        }
+    }
 }
 
 impl<'a> Deref for CommentedToken<'a> {
@@ -31,8 +79,12 @@ impl<'a> CommentedToken<'a> {
             token,
             line,
             offset,
+            end_line: line,
+            end_offset: offset,
+            span: 0..0,
             leading_comments: None,
             inline_comment: None,
+            comments_span: None,
         }
     }
 
@@ -47,14 +99,50 @@ impl<'a> CommentedToken<'a> {
             token,
             line,
             offset,
+            end_line: line,
+            end_offset: offset,
+            span: 0..0,
             leading_comments,
             inline_comment,
+            comments_span: None,
         }
     }
+
+    /// Returns the text of this token in the source it was tokenized from.
+    pub fn source_text<'s>(&self, source: &'s str) -> &'s str {
+        &source[self.span.clone()]
+    }
+
+    /// Returns the span of this token without its comments.
+    pub fn token_span(&self) -> Span {
+        Span {
+            start_line: self.line,
+            start_offset: self.offset,
+            end_line: self.end_line,
+            end_offset: self.end_offset,
+            bytes: self.span.clone(),
+        }
+    }
+
+    /// Returns the span of this token including the comments attached to it.
+    pub fn full_span(&self) -> Span {
+        self.comments_span
+            .as_deref()
+            .cloned()
+            .unwrap_or_else(|| self.token_span())
+    }
+
+    /// Sets the end position and the byte range of this token.
+    pub fn with_end(mut self, end_line: u32, end_offset: usize, span: Range<usize>) -> Self {
+        self.end_line = end_line;
+        self.end_offset = end_offset;
+        self.span = span;
+        self
+    }
 }
 
 /// When comparing two tokens, only the token itself is compared.
-/// The line and offset are ignored.
+/// The positions are ignored.
 impl<'a> PartialEq for CommentedToken<'a> {
     fn eq(&self, other: &Self) -> bool {
         self.token == other.token
@@ -69,7 +157,12 @@ impl<'a> std::fmt::Display for CommentedToken<'a> {
 
 /// This represents all the different token types encountered
 /// in an R program.
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Serializes to an object with the name of the variant in `kind` and,
+/// for the variants holding source text, that text in `text`, e.g.
+/// `{"kind":"Symbol","text":"x"}` and `{"kind":"LAssign"}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "text")]
 pub enum Token<'a> {
     Symbol(&'a str),
     Literal(&'a str),
@@ -83,6 +176,22 @@ pub enum Token<'a> {
     RBracket,
     Comma,
 
+    // Constants
+    Null,
+    Na,
+    NaInteger,
+    NaReal,
+    NaCharacter,
+    Inf,
+    NaN,
+
+    // Dots: `...` and `..1`, `..2` and so on
+    Dots,
+    DotDotN(&'a str),
+
+    // The native pipe placeholder `_`
+    Placeholder,
+
     // Reserved
     Continue,
     Break,
@@ -103,6 +212,7 @@ pub enum Token<'a> {
     SuperAssign,
     ColonAssign,
     RAssign,
+    SuperRAssign,
     OldAssign,
     Equal,
     NotEqual,
@@ -136,11 +246,114 @@ pub enum Token<'a> {
     // Comments
     InlineComment(&'a str),
     Comment(&'a str),
+    /// `#' ...`
+    RoxygenComment(&'a str),
+    /// RStudio section headers, e.g. `# Section ----`
+    SectionComment(&'a str),
+    /// Quarto chunk options, e.g. `#| echo: false`
+    ChunkOptionComment(&'a str),
+    /// knitr output, e.g. `#> [1] 1`
+    KnitrOutputComment(&'a str),
+
+    // Spaces and tabs, emitted only by the lossless tokenizer
+    Whitespace(&'a str),
 
     // EOF
     EOF,
 }
 
+impl<'a> Token<'a> {
+    /// Returns the text of a comment occupying a whole line, whatever its kind.
+    /// Returns `None` for inline comments and other tokens.
+    pub fn line_comment(&self) -> Option<&'a str> {
+        match self {
+            Token::Comment(text)
+            | Token::RoxygenComment(text)
+            | Token::SectionComment(text)
+            | Token::ChunkOptionComment(text)
+            | Token::KnitrOutputComment(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns the R source of this token. Newlines are printed as `\n`.
+    pub fn text(&self) -> &'a str {
+        match self {
+            Token::Symbol(s) | Token::Literal(s) => s,
+            Token::Semicolon => ";",
+            Token::Newline => "\n",
+            Token::LParen => "(",
+            Token::RParen => ")",
+            Token::LBrace => "{",
+            Token::RBrace => "}",
+            Token::LBracket => "[",
+            Token::RBracket => "]",
+            Token::Comma => ",",
+            Token::Null => "NULL",
+            Token::Na => "NA",
+            Token::NaInteger => "NA_integer_",
+            Token::NaReal => "NA_real_",
+            Token::NaCharacter => "NA_character_",
+            Token::Inf => "Inf",
+            Token::NaN => "NaN",
+            Token::Dots => "...",
+            Token::DotDotN(s) => s,
+            Token::Placeholder => "_",
+            Token::Continue => "continue",
+            Token::Break => "break",
+            Token::Stop => "stop",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::While => "while",
+            Token::For => "for",
+            Token::Repeat => "repeat",
+            Token::In => "in",
+            Token::Function => "function",
+            Token::Lambda => "\\",
+            Token::LAssign => "<-",
+            Token::SuperAssign => "<<-",
+            Token::ColonAssign => ":=",
+            Token::RAssign => "->",
+            Token::SuperRAssign => "->>",
+            Token::OldAssign => "=",
+            Token::Equal => "==",
+            Token::NotEqual => "!=",
+            Token::LowerThan => "<",
+            Token::GreaterThan => ">",
+            Token::LowerEqual => "<=",
+            Token::GreaterEqual => ">=",
+            Token::Power => "^",
+            Token::Divide => "/",
+            Token::Multiply => "*",
+            Token::Minus => "-",
+            Token::Plus => "+",
+            Token::Help => "?",
+            Token::And => "&&",
+            Token::VectorizedAnd => "&",
+            Token::Or => "||",
+            Token::VectorizedOr => "|",
+            Token::Dollar => "$",
+            Token::Pipe => "|>",
+            Token::Modulo => "%%",
+            Token::NsGet => "::",
+            Token::NsGetInt => ":::",
+            Token::Tilde => "~",
+            Token::Colon => ":",
+            Token::Slot => "@",
+            Token::Special(s) => s,
+            Token::UnaryNot => "!",
+            Token::InlineComment(s) => s,
+            Token::Comment(s)
+            | Token::RoxygenComment(s)
+            | Token::SectionComment(s)
+            | Token::ChunkOptionComment(s)
+            | Token::KnitrOutputComment(s) => s,
+            Token::Whitespace(s) => s,
+            Token::EOF => "",
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! commented_tokens {
     ($($args:expr),*) => {{
@@ -151,7 +364,8 @@ macro_rules! commented_tokens {
         ]
     }}
 }
-use std::ops::Deref;
+use serde::Serialize;
+use std::ops::{Deref, Range};
 
 pub use commented_tokens;
 
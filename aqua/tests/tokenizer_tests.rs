@@ -20,7 +20,7 @@ fn symbols() {
     ];
     for (example, expected_tokens) in examples {
         let mut tokenizer = Tokenizer::new(example);
-        let tokens = tokenizer.tokenize();
+        let tokens = tokenizer.tokenize().unwrap();
         let tokens = tokens
             .into_iter()
             .map(|token| token.token)
@@ -47,7 +47,7 @@ fn comments() {
     ];
     for (example, expected) in examples {
         let mut tokenizer = Tokenizer::new(example);
-        let tokens = tokenizer.tokenize();
+        let tokens = tokenizer.tokenize().unwrap();
         let tokens = tokens
             .into_iter()
             .map(|token| token.token)
@@ -73,7 +73,7 @@ fn ifs() {
     )];
     for (example, expected) in examples {
         let mut tokenizer = Tokenizer::new(example);
-        let tokens = tokenizer.tokenize();
+        let tokens = tokenizer.tokenize().unwrap();
         let tokens = tokens
             .into_iter()
             .map(|token| token.token)
@@ -96,10 +96,28 @@ fn number_literals() {
             "0xabcdef.1P28",
             vec![Token::Literal("0xabcdef.1P28"), Token::EOF],
         ),
+        ("1.", vec![Token::Literal("1."), Token::EOF]),
+        ("10L", vec![Token::Literal("10L"), Token::EOF]),
+        ("1e5L", vec![Token::Literal("1e5L"), Token::EOF]),
+        ("0xFFL", vec![Token::Literal("0xFFL"), Token::EOF]),
+        ("0x1p10", vec![Token::Literal("0x1p10"), Token::EOF]),
+        ("0x1P-2", vec![Token::Literal("0x1P-2"), Token::EOF]),
+        ("3i", vec![Token::Literal("3i"), Token::EOF]),
+        ("1e-3i", vec![Token::Literal("1e-3i"), Token::EOF]),
+        ("0x10i", vec![Token::Literal("0x10i"), Token::EOF]),
+        (
+            "1L+2i",
+            vec![
+                Token::Literal("1L"),
+                Token::Plus,
+                Token::Literal("2i"),
+                Token::EOF,
+            ],
+        ),
     ];
     for (example, expected) in examples {
         let mut tokenizer = Tokenizer::new(example);
-        let tokens = tokenizer.tokenize();
+        let tokens = tokenizer.tokenize().unwrap();
         let tokens = tokens
             .into_iter()
             .map(|token| token.token)
@@ -175,10 +193,21 @@ fn binary_ops() {
                 Token::EOF,
             ],
         ),
+        (
+            "a->b->>c",
+            vec![
+                Token::Symbol("a"),
+                Token::RAssign,
+                Token::Symbol("b"),
+                Token::SuperRAssign,
+                Token::Symbol("c"),
+                Token::EOF,
+            ],
+        ),
     ];
     for (example, expected) in examples {
         let mut tokenizer = Tokenizer::new(example);
-        let tokens = tokenizer.tokenize();
+        let tokens = tokenizer.tokenize().unwrap();
         let tokens = tokens
             .into_iter()
             .map(|token| token.token)
@@ -250,7 +279,7 @@ fn function_definitions() {
                 Token::Comma,
                 Token::Symbol("z"),
                 Token::Comma,
-                Token::Symbol("..."),
+                Token::Dots,
                 Token::RParen,
                 Token::Literal("TRUE"),
                 Token::EOF,
@@ -260,7 +289,7 @@ fn function_definitions() {
 
     for (example, expected) in examples {
         let mut tokenizer = Tokenizer::new(example);
-        let tokens = tokenizer.tokenize();
+        let tokens = tokenizer.tokenize().unwrap();
         let tokens = tokens
             .into_iter()
             .map(|token| token.token)
@@ -308,6 +337,7 @@ fn function_definitions() {
         let mut tokenizer = Tokenizer::new(example);
         let tokens = tokenizer
             .tokenize()
+            .unwrap()
             .into_iter()
             .map(|token| token.token)
             .collect::<Vec<_>>();
@@ -322,6 +352,578 @@ fn custom_binary_operators() {
 
     for example in examples {
         let mut tokenizer = Tokenizer::new(example);
-        let _ = tokenizer.tokenize();
+        let _ = tokenizer.tokenize().unwrap();
+    }
+}
+
+#[test]
+fn raw_string_literals() {
+    let examples = [
+        r#"r"(C:\path\)""#,
+        r#"R"(a)""#,
+        r#"r'(a)'"#,
+        r#"r"[brackets]""#,
+        r#"R"{braces}""#,
+        r#"r"-(dashes)-""#,
+        r#"r"---(a )" )-" )--")---""#,
+        "r\"(multi\nline)\"",
+    ];
+    for example in examples {
+        let mut tokenizer = Tokenizer::new(example);
+        let tokens = tokenizer
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.token)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, vec![Token::Literal(example), Token::EOF]);
+    }
+
+    let mut tokenizer = Tokenizer::new(r#"paste0(r"(\d+)", r)"#);
+    let tokens = tokenizer
+        .tokenize()
+        .unwrap()
+        .into_iter()
+        .map(|token| token.token)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Symbol("paste0"),
+            Token::LParen,
+            Token::Literal(r#"r"(\d+)""#),
+            Token::Comma,
+            Token::Symbol("r"),
+            Token::RParen,
+            Token::EOF,
+        ]
+    );
+}
+
+#[test]
+fn tokenize_errors() {
+    use tergo_tokenizer::error::TokenizeErrorKind;
+
+    let examples = [
+        ("\"abc", TokenizeErrorKind::UnterminatedString, 0, 0),
+        ("a <- 'abc\n", TokenizeErrorKind::UnterminatedString, 0, 5),
+        (
+            "x <- r\"(abc",
+            TokenizeErrorKind::UnterminatedRawString,
+            0,
+            5,
+        ),
+        ("r\"abc\"", TokenizeErrorKind::MalformedRawString, 0, 0),
+        (
+            "a %in b",
+            TokenizeErrorKind::UnterminatedSpecialOperator,
+            0,
+            2,
+        ),
+        (
+            "a %in\n%b",
+            TokenizeErrorKind::UnterminatedSpecialOperator,
+            0,
+            2,
+        ),
+        ("`abc", TokenizeErrorKind::UnterminatedBacktick, 0, 0),
+        (
+            "a\nb £ c",
+            TokenizeErrorKind::UnexpectedCharacter('£'),
+            1,
+            2,
+        ),
+        (
+            "a <-\u{a0}1",
+            TokenizeErrorKind::UnexpectedCharacter('\u{a0}'),
+            0,
+            4,
+        ),
+        (
+            "x <- \"\\q\"",
+            TokenizeErrorKind::UnrecognizedEscape('q'),
+            0,
+            6,
+        ),
+        ("'a\n\\ '", TokenizeErrorKind::UnrecognizedEscape(' '), 1, 0),
+        ("\"\\xg\"", TokenizeErrorKind::MalformedEscape('x'), 0, 1),
+        ("\"\\u{41\"", TokenizeErrorKind::MalformedEscape('u'), 0, 1),
+        ("\"\\U{}\"", TokenizeErrorKind::MalformedEscape('U'), 0, 1),
+        ("\"C:\\\\\\\"", TokenizeErrorKind::UnterminatedString, 0, 0),
+    ];
+    for (example, kind, line, offset) in examples {
+        let mut tokenizer = Tokenizer::new(example);
+        let error = tokenizer.tokenize().unwrap_err();
+        assert_eq!(error.kind, kind, "{example:?}");
+        assert_eq!((error.line, error.offset), (line, offset), "{example:?}");
+    }
+}
+
+#[test]
+fn operators_at_the_end_of_input() {
+    let examples = [
+        ("a *", Token::Multiply),
+        ("a <", Token::LowerThan),
+        ("a >", Token::GreaterThan),
+        ("a |", Token::VectorizedOr),
+        ("a &", Token::VectorizedAnd),
+        ("a =", Token::OldAssign),
+        ("a -", Token::Minus),
+    ];
+    for (example, operator) in examples {
+        let mut tokenizer = Tokenizer::new(example);
+        let tokens = tokenizer
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.token)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, vec![Token::Symbol("a"), operator, Token::EOF]);
+    }
+}
+
+#[test]
+fn identifiers() {
+    let examples = [
+        "a",
+        "abc",
+        "x1",
+        "snake_case",
+        "dotted.name",
+        ".x1",
+        ".data2",
+        ".",
+        "..",
+        "._",
+        "a._1",
+        "`my var`",
+        "`a\\`b`",
+        "élan",
+        "zmienna_ąę",
+        "変数",
+        "Ωmega2",
+    ];
+    for example in examples {
+        let mut tokenizer = Tokenizer::new(example);
+        let tokens = tokenizer
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.token)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![Token::Symbol(example), Token::EOF],
+            "{example}"
+        );
+    }
+}
+
+#[test]
+fn identifiers_followed_by_operators() {
+    let examples = [
+        (
+            "pkg::fun",
+            vec![Token::Symbol("pkg"), Token::NsGet, Token::Symbol("fun")],
+        ),
+        (
+            "obj@slot",
+            vec![Token::Symbol("obj"), Token::Slot, Token::Symbol("slot")],
+        ),
+        (
+            ".x1:.x2",
+            vec![Token::Symbol(".x1"), Token::Colon, Token::Symbol(".x2")],
+        ),
+        (
+            "a~b",
+            vec![Token::Symbol("a"), Token::Tilde, Token::Symbol("b")],
+        ),
+        (
+            "a?b",
+            vec![Token::Symbol("a"), Token::Help, Token::Symbol("b")],
+        ),
+        (".5", vec![Token::Literal(".5")]),
+        (
+            "..1 + 2",
+            vec![Token::DotDotN("..1"), Token::Plus, Token::Literal("2")],
+        ),
+    ];
+    for (example, mut expected) in examples {
+        expected.push(Token::EOF);
+        let mut tokenizer = Tokenizer::new(example);
+        let tokens = tokenizer
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.token)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, expected, "{example}");
+    }
+}
+
+#[test]
+fn pipe_placeholder() {
+    let mut tokenizer = Tokenizer::new("x |> f(y = _)$z |> _$a");
+    let tokens = tokenizer
+        .tokenize()
+        .unwrap()
+        .into_iter()
+        .map(|token| token.token)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Symbol("x"),
+            Token::Pipe,
+            Token::Symbol("f"),
+            Token::LParen,
+            Token::Symbol("y"),
+            Token::OldAssign,
+            Token::Placeholder,
+            Token::RParen,
+            Token::Dollar,
+            Token::Symbol("z"),
+            Token::Pipe,
+            Token::Placeholder,
+            Token::Dollar,
+            Token::Symbol("a"),
+            Token::EOF,
+        ]
+    );
+}
+
+#[test]
+fn identifiers_cannot_start_with_an_underscore() {
+    use tergo_tokenizer::error::TokenizeErrorKind;
+
+    let mut tokenizer = Tokenizer::new("_abc");
+    let error = tokenizer.tokenize().unwrap_err();
+    assert_eq!(error.kind, TokenizeErrorKind::UnexpectedCharacter('_'));
+}
+
+#[test]
+fn reserved_words() {
+    let examples = [
+        ("if", Token::If),
+        ("else", Token::Else),
+        ("for", Token::For),
+        ("in", Token::In),
+        ("while", Token::While),
+        ("repeat", Token::Repeat),
+        ("function", Token::Function),
+        ("break", Token::Break),
+        ("continue", Token::Continue),
+        ("iffy", Token::Symbol("iffy")),
+        ("in2", Token::Symbol("in2")),
+        (".function", Token::Symbol(".function")),
+        ("TRUE", Token::Literal("TRUE")),
+        ("FALSE", Token::Literal("FALSE")),
+        ("T", Token::Symbol("T")),
+        ("F", Token::Symbol("F")),
+        ("NULL", Token::Null),
+        ("NA", Token::Na),
+        ("NA_integer_", Token::NaInteger),
+        ("NA_real_", Token::NaReal),
+        ("NA_character_", Token::NaCharacter),
+        ("Inf", Token::Inf),
+        ("NaN", Token::NaN),
+        ("...", Token::Dots),
+        ("..1", Token::DotDotN("..1")),
+        ("..10", Token::DotDotN("..10")),
+        ("....", Token::Symbol("....")),
+        ("..1a", Token::Symbol("..1a")),
+        ("NA_", Token::Symbol("NA_")),
+        ("Null", Token::Symbol("Null")),
+    ];
+    for (example, expected) in examples {
+        let mut tokenizer = Tokenizer::new(example);
+        let tokens = tokenizer
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.token)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, vec![expected, Token::EOF], "{example}");
+    }
+}
+
+#[test]
+fn token_positions() {
+    let source = "x <- \"ą\"\nf(`a b`, 0x1F) # ok\n";
+    let mut tokenizer = Tokenizer::new(source);
+    let tokens = tokenizer.tokenize().unwrap();
+    let positions = tokens
+        .iter()
+        .map(|token| {
+            (
+                &source[token.span.clone()],
+                (token.line, token.offset),
+                (token.end_line, token.end_offset),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        positions,
+        vec![
+            ("x", (0, 0), (0, 1)),
+            ("<-", (0, 2), (0, 4)),
+            ("\"ą\"", (0, 5), (0, 8)),
+            ("\n", (0, 8), (1, 0)),
+            ("f", (1, 0), (1, 1)),
+            ("(", (1, 1), (1, 2)),
+            ("`a b`", (1, 2), (1, 7)),
+            (",", (1, 7), (1, 8)),
+            ("0x1F", (1, 9), (1, 13)),
+            (")", (1, 13), (1, 14)),
+            ("# ok", (1, 15), (1, 19)),
+            ("\n", (1, 19), (2, 0)),
+            ("", (2, 0), (2, 0)),
+        ]
+    );
+}
+
+#[test]
+fn multiline_token_positions() {
+    let source = "'a\nbc' r\"(\n)\"";
+    let mut tokenizer = Tokenizer::new(source);
+    let tokens = tokenizer.tokenize().unwrap();
+    assert_eq!(tokens[0].span, 0..6);
+    assert_eq!((tokens[0].end_line, tokens[0].end_offset), (1, 3));
+    assert_eq!(tokens[1].span, 7..13);
+    assert_eq!((tokens[1].line, tokens[1].offset), (1, 4));
+    assert_eq!((tokens[1].end_line, tokens[1].end_offset), (2, 2));
+}
+
+#[test]
+fn line_endings() {
+    let examples = [
+        (
+            "a\r\nb",
+            vec![
+                Token::Symbol("a"),
+                Token::Newline,
+                Token::Symbol("b"),
+                Token::EOF,
+            ],
+        ),
+        (
+            "a\rb",
+            vec![
+                Token::Symbol("a"),
+                Token::Newline,
+                Token::Symbol("b"),
+                Token::EOF,
+            ],
+        ),
+        (
+            "# Comment\r\n\r\n",
+            vec![
+                Token::Comment("# Comment"),
+                Token::Newline,
+                Token::Newline,
+                Token::EOF,
+            ],
+        ),
+    ];
+    for (example, expected_tokens) in examples {
+        let mut tokenizer = Tokenizer::new(example);
+        let tokens = tokenizer.tokenize().unwrap();
+        let tokens = tokens
+            .into_iter()
+            .map(|token| token.token)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, expected_tokens);
+    }
+
+    let mut tokenizer = Tokenizer::new("a\r\nb");
+    let tokens = tokenizer.tokenize().unwrap();
+    assert_eq!(tokens[1].span, 1..3);
+    assert_eq!((tokens[2].line, tokens[2].offset), (1, 0));
+}
+
+#[test]
+fn string_escapes() {
+    let examples = [
+        r#""C:\\""#,
+        r#"'it\'s'"#,
+        r#""say \"hi\"""#,
+        r#""\n\r\t\b\a\f\v\`""#,
+        r#""\0\12\123\1234""#,
+        r#""\x4\x41\x411""#,
+        r#""\u41\u0041\u{41}\u{0041}""#,
+        r#""\U1F600\U{1F600}\U0001F6001""#,
+        "\"a\\\nb\"",
+    ];
+    for example in examples {
+        let mut tokenizer = Tokenizer::new(example);
+        let tokens = tokenizer
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.token)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, vec![Token::Literal(example), Token::EOF]);
+    }
+
+    let mut tokenizer = Tokenizer::new(r#"f("C:\\", 1)"#);
+    let tokens = tokenizer
+        .tokenize()
+        .unwrap()
+        .into_iter()
+        .map(|token| token.token)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Symbol("f"),
+            Token::LParen,
+            Token::Literal(r#""C:\\""#),
+            Token::Comma,
+            Token::Literal("1"),
+            Token::RParen,
+            Token::EOF
+        ]
+    );
+}
+
+#[test]
+fn lossless_tokenization() {
+    let examples = [
+        "",
+        "a <- 1\n",
+        "  \tf(x,  y) # comment\n\n  # leading comment\r\nb\t<-  'ż\\n'  \n",
+        "if (a)   {\n  b\n} else {\r  c }",
+        "x |>\n  f(y = _) ;  z <- r\"(raw  )\"  ",
+        "`odd  name` %in%  c(0x1p10, 1e-3i)\t",
+    ];
+    for example in examples {
+        let tokens = Tokenizer::new(example).lossless().tokenize().unwrap();
+        let text = tokens
+            .iter()
+            .map(|token| token.source_text(example))
+            .collect::<String>();
+        assert_eq!(text, example);
+        for window in tokens.windows(2) {
+            assert_eq!(window[0].span.end, window[1].span.start, "{example:?}");
+        }
     }
+
+    let tokens = Tokenizer::new("a  <-\tb")
+        .lossless()
+        .tokenize()
+        .unwrap()
+        .into_iter()
+        .map(|token| token.token)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Symbol("a"),
+            Token::Whitespace("  "),
+            Token::LAssign,
+            Token::Whitespace("\t"),
+            Token::Symbol("b"),
+            Token::EOF,
+        ]
+    );
+
+    let tokens = Tokenizer::new("a # inline\n  # leading\n")
+        .lossless()
+        .tokenize()
+        .unwrap()
+        .into_iter()
+        .map(|token| token.token)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Symbol("a"),
+            Token::Whitespace(" "),
+            Token::InlineComment("# inline"),
+            Token::Newline,
+            Token::Whitespace("  "),
+            Token::Comment("# leading"),
+            Token::Newline,
+            Token::EOF,
+        ]
+    );
+}
+
+#[test]
+fn comment_kinds() {
+    let examples = [
+        ("# Comment", Token::Comment("# Comment")),
+        ("#' @param x", Token::RoxygenComment("#' @param x")),
+        ("# Section ----", Token::SectionComment("# Section ----")),
+        (
+            "## Section ====  ",
+            Token::SectionComment("## Section ====  "),
+        ),
+        (
+            "#### Section ####",
+            Token::SectionComment("#### Section ####"),
+        ),
+        ("# Not a section ---", Token::Comment("# Not a section ---")),
+        ("# ----", Token::Comment("# ----")),
+        ("########", Token::Comment("########")),
+        (
+            "#| echo: false",
+            Token::ChunkOptionComment("#| echo: false"),
+        ),
+        ("#> [1] 1", Token::KnitrOutputComment("#> [1] 1")),
+    ];
+    for (example, expected) in examples {
+        let mut tokenizer = Tokenizer::new(example);
+        let tokens = tokenizer
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.token)
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, vec![expected, Token::EOF], "{example}");
+    }
+
+    let mut tokenizer = Tokenizer::new("f(x) #' not roxygen\n  #' @export\n");
+    let tokens = tokenizer
+        .tokenize()
+        .unwrap()
+        .into_iter()
+        .map(|token| token.token)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Symbol("f"),
+            Token::LParen,
+            Token::Symbol("x"),
+            Token::RParen,
+            Token::InlineComment("#' not roxygen"),
+            Token::Newline,
+            Token::RoxygenComment("#' @export"),
+            Token::Newline,
+            Token::EOF,
+        ]
+    );
+}
+
+#[test]
+fn iterating_over_tokens() {
+    let tokens = Tokenizer::new("a <- 1")
+        .map(|token| token.unwrap().token)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Symbol("a"),
+            Token::LAssign,
+            Token::Literal("1"),
+            Token::EOF,
+        ]
+    );
+
+    let mut tokenizer = Tokenizer::new("a <- \"b");
+    assert_eq!(tokenizer.next().unwrap().unwrap().token, Token::Symbol("a"));
+    assert_eq!(tokenizer.next().unwrap().unwrap().token, Token::LAssign);
+    assert!(tokenizer.next().unwrap().is_err());
+    assert!(tokenizer.next().is_none());
 }
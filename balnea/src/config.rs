@@ -1,4 +1,5 @@
-use formatter::config::{FormattingConfig, FunctionLineBreaks};
+use formatter::config::FormattingConfig;
+pub use formatter::config::{FunctionLineBreaks, LineEnding};
 use serde::Deserialize;
 
 #[derive(Debug, Clone, Copy, Deserialize)]
@@ -49,6 +50,25 @@ pub struct Config {
     ///   }
     /// )
     pub insert_newline_in_quote_call: bool,
+    /// The line endings of the formatted code. One of:
+    /// lf, crlf, auto (keep the line endings of the input)
+    /// or native (crlf on Windows, lf elsewhere)
+    #[serde(default)]
+    pub line_ending: LineEnding,
+    /// Whether to rewrite T and F to TRUE and FALSE.
+    /// T and F are ordinary variables in R, so they are
+    /// left untouched unless this is turned on
+    #[serde(default)]
+    pub expand_logical_abbreviations: bool,
+    /// Whether to put the statements separated by semicolons
+    /// on separate lines. Example:
+    /// a <- 1; b <- 2
+    /// becomes
+    /// a <- 1
+    /// b <- 2
+    /// Turned on by default. Turn it off to keep the semicolons
+    #[serde(default = "split_semicolon_statements_default")]
+    pub split_semicolon_statements: bool,
 }
 
 impl FormattingConfig for Config {
@@ -83,6 +103,18 @@ impl FormattingConfig for Config {
     fn insert_newline_in_quote_call(&self) -> bool {
         self.insert_newline_in_quote_call
     }
+
+    fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    fn expand_logical_abbreviations(&self) -> bool {
+        self.expand_logical_abbreviations
+    }
+
+    fn split_semicolon_statements(&self) -> bool {
+        self.split_semicolon_statements
+    }
 }
 
 impl Default for Config {
@@ -96,10 +128,17 @@ impl Default for Config {
             strip_suffix_whitespace_in_function_defs: true,
             function_line_breaks: FunctionLineBreaks::Hanging,
             insert_newline_in_quote_call: true,
+            line_ending: LineEnding::Auto,
+            expand_logical_abbreviations: false,
+            split_semicolon_statements: true,
         }
     }
 }
 
+fn split_semicolon_statements_default() -> bool {
+    Config::default().split_semicolon_statements
+}
+
 impl std::fmt::Display for Config {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
@@ -120,6 +159,9 @@ impl Config {
         strip_suffix_whitespace_in_function_defs: bool,
         function_line_breaks: FunctionLineBreaks,
         insert_newline_in_quote_call: bool,
+        line_ending: LineEnding,
+        expand_logical_abbreviations: bool,
+        split_semicolon_statements: bool,
     ) -> Self {
         Self {
             indent,
@@ -130,6 +172,9 @@ impl Config {
             strip_suffix_whitespace_in_function_defs,
             function_line_breaks,
             insert_newline_in_quote_call,
+            line_ending,
+            expand_logical_abbreviations,
+            split_semicolon_statements,
         }
     }
 }
@@ -0,0 +1,34 @@
+use parser::ParseError;
+use tokenizer::TokenizeError;
+
+/// Errors returned by [crate::tergo_format] and the other entry points.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormattingError {
+    /// The input could not be split into R tokens.
+    Tokenizer(TokenizeError),
+    /// The tokens could not be parsed into an R program.
+    Parser(ParseError),
+}
+
+impl std::fmt::Display for FormattingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormattingError::Tokenizer(err) => write!(f, "{err}"),
+            FormattingError::Parser(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for FormattingError {}
+
+impl From<TokenizeError> for FormattingError {
+    fn from(err: TokenizeError) -> Self {
+        FormattingError::Tokenizer(err)
+    }
+}
+
+impl From<ParseError> for FormattingError {
+    fn from(err: ParseError) -> Self {
+        FormattingError::Parser(err)
+    }
+}
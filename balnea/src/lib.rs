@@ -1,28 +1,92 @@
 pub mod config;
+pub mod error;
 use config::Config;
+use error::FormattingError;
 use formatter::format_code;
 use log::trace;
 use parser::{
     ast::{Expression, TermExpr},
-    parse, pre_parse,
+    parse, parse_data, parse_data_to_csv, parse_with_recovery, pre_parse, ParseError,
 };
 use tokenizer::{tokens_buffer::TokensBuffer, Tokenizer};
 
-pub fn tergo_format(input: &str, config: Option<&Config>) -> Result<String, String> {
+pub fn tergo_format(input: &str, config: Option<&Config>) -> Result<String, FormattingError> {
+    format(input, config, false).map(|(formatted, _)| formatted)
+}
+
+/// Formats the input like [tergo_format], but does not give up on syntax errors.
+///
+/// The statements that fail to parse are printed as they are in the input,
+/// everything else is formatted. The syntax errors are returned along with
+/// the formatted code.
+pub fn tergo_format_with_recovery(
+    input: &str,
+    config: Option<&Config>,
+) -> Result<(String, Vec<ParseError>), FormattingError> {
+    format(input, config, true)
+}
+
+/// Returns the tokens of the input as a JSON array.
+///
+/// The comments are tokens of their own. See [tokenizer::tokens::CommentedToken]
+/// for the shape of a token.
+pub fn tergo_tokens(input: &str) -> Result<String, FormattingError> {
+    let commented_tokens = Tokenizer::new(input).tokenize()?;
+    Ok(serde_json::to_string(&commented_tokens).expect("tokens serialize to JSON"))
+}
+
+/// Returns the statements of the input as a JSON array.
+///
+/// The comments are attached to the tokens, as the formatter sees them.
+/// See [Expression] for the shape of a statement.
+pub fn tergo_parse(input: &str) -> Result<String, FormattingError> {
+    let mut commented_tokens = Tokenizer::new(input).tokenize()?;
+    let tokens_without_comments = pre_parse(&mut commented_tokens);
+    let cst = parse(&tokens_without_comments).map_err(|err| err.with_source(input))?;
+    Ok(serde_json::to_string(&cst).expect("the syntax tree serializes to JSON"))
+}
+
+/// Returns the parse data of the input as CSV, in the format of R's
+/// `utils::getParseData`. See [parser::parse_data] for how it differs from R's.
+pub fn tergo_parse_data(input: &str) -> Result<String, FormattingError> {
+    let mut commented_tokens = Tokenizer::new(input).tokenize()?;
+    let tokens_without_comments = pre_parse(&mut commented_tokens);
+    let cst = parse(&tokens_without_comments).map_err(|err| err.with_source(input))?;
+    Ok(parse_data_to_csv(&parse_data(&cst, input)))
+}
+
+fn format(
+    input: &str,
+    config: Option<&Config>,
+    recover: bool,
+) -> Result<(String, Vec<ParseError>), FormattingError> {
     let default_config = Config::default();
     let config = config.unwrap_or(&default_config);
+    let config = &Config {
+        line_ending: config.line_ending.resolve(input),
+        ..*config
+    };
     trace!("Formatting with config: {config}");
     let mut tokenizer = Tokenizer::new(input);
     trace!("Tokenizer created");
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize()?;
     trace!("Tokens with comments: {commented_tokens:?}",);
     let tokens_without_comments = pre_parse(&mut commented_tokens);
     trace!(
         "Tokens without comments: {}",
         TokensBuffer(&tokens_without_comments)
     );
-    let cst = parse(&tokens_without_comments)?;
+    let (cst, errors) = if recover {
+        parse_with_recovery(&tokens_without_comments)
+    } else {
+        let cst = parse(&tokens_without_comments).map_err(|err| err.with_source(input))?;
+        (cst, vec![])
+    };
+    let errors = errors
+        .into_iter()
+        .map(|err| err.with_source(input))
+        .collect();
     let top_node = Expression::Term(Box::new(TermExpr::new(None, cst, None)));
     trace!("CST: {:?}", top_node);
-    Ok(format_code(top_node, config))
+    Ok((format_code(top_node, input, config), errors))
 }
@@ -243,6 +243,7 @@ comparison_test!(
 );
 comparison_test!(comment_in_parentheses, "084", Config::default());
 comparison_test!(in_parentheses_term_does_not_fit, "085", Config::default());
+comparison_test!(raw_strings_are_printed_verbatim, "086", Config::default());
 
 // Tidyverse styleguide examples
 comparison_test!(tidyverse_commas, "tidyverse_style_guide_001");
@@ -390,3 +391,135 @@ comparison_test!(
     Config::default()
 );
 comparison_test!(rle_tmc, "real_life_004", Config::default());
+
+#[test]
+fn tokenizer_errors_are_returned() {
+    use tergo_lib::error::FormattingError;
+
+    let result = tergo_format("a <- \"unterminated", Some(&Config::default()));
+    match result {
+        Err(FormattingError::Tokenizer(err)) => {
+            assert_eq!((err.line, err.offset), (0, 5));
+        }
+        other => panic!("Expected a tokenizer error, got {other:?}"),
+    }
+}
+
+#[test]
+fn parser_errors_are_returned() {
+    use tergo_lib::error::FormattingError;
+
+    let result = tergo_format("f(a,\n  b <- )\n", Some(&Config::default()));
+    match result {
+        Err(FormattingError::Parser(err)) => {
+            assert_eq!((err.line, err.offset), (1, 7));
+            assert_eq!(
+                err.to_string(),
+                "2:8: expected an expression, found `)`\n  b <- )\n       ^"
+            );
+        }
+        other => panic!("Expected a parser error, got {other:?}"),
+    }
+}
+
+#[test]
+fn syntax_errors_are_printed_verbatim() {
+    use tergo_lib::tergo_format_with_recovery;
+
+    let input = "f <- function(x) {\n  a<-1\n  b <- c(1 2,\n         3)  # why\n  z<-3\n}\n\ng<-function( ) {\n    # call h\n    h(\n}\n";
+    let expected = "f <- function(x) {\n  a <- 1\n  b <- c(1 2,\n         3)  # why\n  z <- 3\n}\n\ng <- function() {\n  # call h\n    h(\n}\n";
+    let (formatted, errors) =
+        tergo_format_with_recovery(input, Some(&Config::default())).unwrap();
+    assert_eq!(formatted, expected);
+    assert_eq!(errors.len(), 2);
+    assert_eq!((errors[0].line, errors[0].offset), (2, 11));
+    assert!(tergo_format(input, Some(&Config::default())).is_err());
+
+    let input = "y<-2\nx   <-   (1 +\t\t2 #  c\n";
+    let (formatted, errors) =
+        tergo_format_with_recovery(input, Some(&Config::default())).unwrap();
+    assert_eq!(formatted, "y <- 2\nx   <-   (1 +\t\t2 #  c\n");
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn line_endings_follow_the_config() {
+    use tergo_lib::config::LineEnding;
+
+    let examples = [
+        ("a <- 1\r\n\r\nb<-2\r\n", LineEnding::Auto, "a <- 1\r\n\r\nb <- 2\r\n"),
+        ("a <- 1\n\nb<-2\n", LineEnding::Auto, "a <- 1\n\nb <- 2\n"),
+        ("a <- 1\r\nb<-2\n", LineEnding::Lf, "a <- 1\nb <- 2\n"),
+        ("a <- 1\nb<-2\n", LineEnding::Crlf, "a <- 1\r\nb <- 2\r\n"),
+    ];
+    for (input, line_ending, expected) in examples {
+        let mut config = Config::default();
+        config.line_ending = line_ending;
+        assert_eq!(tergo_format(input, Some(&config)).unwrap(), expected);
+    }
+}
+
+#[test]
+fn line_endings_inside_string_literals_are_kept() {
+    use tergo_lib::config::LineEnding;
+
+    let examples = [
+        (
+            "x <- \"a  \nb\"\ny <- r\"(c\nd)\"\n",
+            LineEnding::Crlf,
+            "x <- \"a  \nb\"\r\ny <- r\"(c\nd)\"\r\n",
+        ),
+        (
+            "x <- \"a  \r\nb\"\r\ny <- r\"(c\r\nd)\"\r\n",
+            LineEnding::Lf,
+            "x <- \"a  \r\nb\"\ny <- r\"(c\r\nd)\"\n",
+        ),
+    ];
+    for (input, line_ending, expected) in examples {
+        let mut config = Config::default();
+        config.line_ending = line_ending;
+        assert_eq!(tergo_format(input, Some(&config)).unwrap(), expected);
+    }
+}
+
+#[test]
+fn numeric_literals_are_printed_unchanged() {
+    let literals = [
+        "1", "123.0", "1.", ".5", "1e10", "1e-10", "1E+10", ".42e42", "10L", "1e5L", "0x10L",
+        "0xFFL", "0xabcdef", "0xabcdef.1P28", "0x1p10", "0x1P-2", "3i", "2.5i", "1e-3i",
+        "0x10i",
+    ];
+    for literal in literals {
+        let input = format!("x <- {literal}\n");
+        assert_eq!(tergo_format(&input, None).unwrap(), input);
+    }
+}
+
+comparison_test!(t_and_f_are_kept_by_default, "087", Config::default());
+comparison_test!(t_and_f_are_expanded_when_configured, "088", {
+    let mut config = Config::default();
+    config.expand_logical_abbreviations = true;
+    config
+});
+comparison_test!(constants_and_dots, "089", Config::default());
+comparison_test!(special_comments, "090", Config::default());
+comparison_test!(operator_precedence, "091", Config::default());
+comparison_test!(newlines_in_brackets_and_before_else, "092", Config::default());
+comparison_test!(named_arguments_and_defaults, "093", Config::default());
+comparison_test!(semicolons_are_kept_when_configured, "094", {
+    let mut config = Config::default();
+    config.split_semicolon_statements = false;
+    config
+});
+comparison_test!(semicolon_statements_are_split_by_default, "095", Config::default());
+comparison_test!(pipe_placeholder, "096", Config::default());
+comparison_test!(t_and_f_bound_in_functions_are_kept, "097", {
+    let mut config = Config::default();
+    config.expand_logical_abbreviations = true;
+    config
+});
+comparison_test!(t_and_f_bound_in_the_file_are_kept, "098", {
+    let mut config = Config::default();
+    config.expand_logical_abbreviations = true;
+    config
+});
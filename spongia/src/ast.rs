@@ -1,9 +1,35 @@
-use tokenizer::{tokens::CommentedToken, tokens_buffer::TokensBuffer};
-
+use serde::Serialize;
+use tokenizer::{
+    tokens::{CommentedToken, Span},
+    tokens_buffer::TokensBuffer,
+};
+
+mod arena;
+mod serialize;
+mod visit;
+pub use arena::*;
+pub use visit::*;
+
+/// An R expression.
+///
+/// Serializes to a JSON object with the variant name in `type`, e.g. `x <- 1`
+/// is `{"type":"Bop","operator":{..},"lhs":{"type":"Symbol","token":{..}},
+/// "rhs":{"type":"Literal","token":{..}}}`. The variants holding a single
+/// token put it in `token`, [Expression::Whitespace] and [Expression::Error]
+/// put their tokens in `tokens`, and the variants holding a node, like
+/// [Expression::FunctionCall], have the fields of that node next to `type`.
+/// Tokens serialize as described in [CommentedToken].
+///
+/// The nodes serialize to objects with the names of their fields. Missing
+/// optional fields are `null` and pairs are arrays of two elements. [Arg] is
+/// an object with the `argument` and the `comma` after it, and [Delimiter] is
+/// an object with its variant name in `type` and its tokens in `tokens`.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression<'a> {
     Symbol(&'a CommentedToken<'a>),
     Literal(&'a CommentedToken<'a>),
+    /// The native pipe placeholder `_`, as in `x |> f(y = _)`.
+    Placeholder(&'a CommentedToken<'a>),
     Comment(&'a CommentedToken<'a>),
     Term(Box<TermExpr<'a>>),
     Unary(&'a CommentedToken<'a>, Box<Expression<'a>>),
@@ -14,6 +40,9 @@ pub enum Expression<'a> {
     ),
     Formula(&'a CommentedToken<'a>, Box<Expression<'a>>),
     Newline(&'a CommentedToken<'a>),
+    /// The `;` ending the statement before it. Kept in the list of statements
+    /// after that statement, so that the comments attached to it are not lost.
+    Semicolon(&'a CommentedToken<'a>),
     Whitespace(&'a [&'a CommentedToken<'a>]),
     EOF(&'a CommentedToken<'a>),
     FunctionDef(FunctionDefinition<'a>),
@@ -26,6 +55,9 @@ pub enum Expression<'a> {
     ForLoopExpression(ForLoop<'a>),
     Break(&'a CommentedToken<'a>),
     Continue(&'a CommentedToken<'a>),
+    /// A statement that failed to parse, kept as the raw tokens.
+    /// Only produced by [crate::parse_with_recovery].
+    Error(&'a [&'a CommentedToken<'a>]),
 }
 
 impl std::fmt::Display for Expression<'_> {
@@ -33,6 +65,9 @@ impl std::fmt::Display for Expression<'_> {
         match self {
             Expression::Symbol(token) => f.write_fmt(format_args!("{}", TokensBuffer(&[token]))),
             Expression::Literal(token) => f.write_fmt(format_args!("{}", TokensBuffer(&[token]))),
+            Expression::Placeholder(token) => {
+                f.write_fmt(format_args!("{}", TokensBuffer(&[token])))
+            }
             Expression::Comment(token) => f.write_fmt(format_args!("{}", TokensBuffer(&[token]))),
             Expression::Term(term) => f.write_fmt(format_args!("{}", term)),
             Expression::Unary(op, expr) => f.write_fmt(format_args!("{}{}", op, expr)),
@@ -41,6 +76,7 @@ impl std::fmt::Display for Expression<'_> {
             }
             Expression::Formula(tilde, term) => write!(f, "{} {}", tilde, term),
             Expression::Newline(token) => f.write_fmt(format_args!("{}", TokensBuffer(&[token]))),
+            Expression::Semicolon(token) => f.write_fmt(format_args!("{}", TokensBuffer(&[token]))),
             Expression::Whitespace(tokens) => f.write_fmt(format_args!("{}", TokensBuffer(tokens))),
             Expression::EOF(token) => f.write_fmt(format_args!("{}", TokensBuffer(&[token]))),
             Expression::FunctionDef(func_def) => f.write_fmt(format_args!("{}", func_def)),
@@ -64,6 +100,7 @@ impl std::fmt::Display for Expression<'_> {
                 f.write_fmt(format_args!("{}", TokensBuffer(&[token])))
             }
             Expression::LambdaFunction(lambda) => f.write_fmt(format_args!("{}", lambda)),
+            Expression::Error(tokens) => write!(f, "(Error: {})", TokensBuffer(tokens)),
         }
     }
 }
@@ -86,7 +123,7 @@ impl std::fmt::Display for ExpressionsBuffer<'_> {
 }
 
 // Term
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct TermExpr<'a> {
     pub pre_delimiters: Option<&'a CommentedToken<'a>>,
     pub term: Vec<Expression<'a>>,
@@ -141,9 +178,9 @@ impl std::fmt::Display for TermExpr<'_> {
 // The formatter needs comments and some of them might end up squeezed into
 // the comma token
 #[derive(Debug, Clone, PartialEq)]
-pub struct Arg<'a>(pub Option<Expression<'a>>, pub Option<Expression<'a>>); // Argument, comma
+pub struct Arg<'a, T = CallArgument<'a>>(pub Option<T>, pub Option<Expression<'a>>); // Argument, comma
 
-impl std::fmt::Display for Arg<'_> {
+impl<T: std::fmt::Display> std::fmt::Display for Arg<'_, T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let Some(ref xpr) = self.0 {
             f.write_fmt(format_args!("{}", xpr))?;
@@ -173,17 +210,59 @@ impl std::fmt::Display for Delimiter<'_> {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Args<'a> {
+/// An argument of a call or a subset: `x`, `n = 1` or `n = `.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CallArgument<'a> {
+    /// The name and the `=` after it.
+    pub name: Option<(&'a CommentedToken<'a>, &'a CommentedToken<'a>)>,
+    pub value: Option<Expression<'a>>,
+}
+
+impl std::fmt::Display for CallArgument<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((name, equal_sign)) = self.name {
+            write!(f, "{} {} ", name, equal_sign)?;
+        }
+        if let Some(value) = &self.value {
+            write!(f, "{}", value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A parameter of a `function` or `\` definition: `x` or `x = 1`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FormalParameter<'a> {
+    pub name: &'a CommentedToken<'a>,
+    /// The `=` and the default value.
+    pub default: Option<(&'a CommentedToken<'a>, Expression<'a>)>,
+}
+
+impl std::fmt::Display for FormalParameter<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some((equal_sign, default)) = &self.default {
+            write!(f, " {} {}", equal_sign, default)?;
+        }
+        Ok(())
+    }
+}
+
+/// The delimited, comma separated arguments of a call or a subset,
+/// or the parameters of a function definition.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Args<'a, T = CallArgument<'a>> {
+    #[serde(rename = "left_delimiter")]
     pub left_delimeter: Delimiter<'a>,
-    pub args: Vec<Arg<'a>>,
+    pub args: Vec<Arg<'a, T>>,
+    #[serde(rename = "right_delimiter")]
     pub right_delimeter: Delimiter<'a>,
 }
 
-impl<'a> Args<'a> {
+impl<'a, T> Args<'a, T> {
     pub fn new(
         left_delimeter: Delimiter<'a>,
-        args: Vec<Arg<'a>>,
+        args: Vec<Arg<'a, T>>,
         right_delimeter: Delimiter<'a>,
     ) -> Self {
         Self {
@@ -194,7 +273,7 @@ impl<'a> Args<'a> {
     }
 }
 
-impl std::fmt::Display for Args<'_> {
+impl<T: std::fmt::Display> std::fmt::Display for Args<'_, T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
             "(Args: {} {} {})",
@@ -209,17 +288,17 @@ impl std::fmt::Display for Args<'_> {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct FunctionDefinition<'a> {
     pub keyword: &'a CommentedToken<'a>,
-    pub arguments: Args<'a>,
+    pub arguments: Args<'a, FormalParameter<'a>>,
     pub body: Box<Expression<'a>>,
 }
 
 impl<'a> FunctionDefinition<'a> {
     pub fn new(
         keyword: &'a CommentedToken<'a>,
-        arguments: Args<'a>,
+        arguments: Args<'a, FormalParameter<'a>>,
         body: Box<Expression<'a>>,
     ) -> Self {
         Self {
@@ -240,7 +319,7 @@ impl std::fmt::Display for FunctionDefinition<'_> {
 }
 
 // If expression
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct IfConditional<'a> {
     pub keyword: &'a CommentedToken<'a>,
     pub left_delimiter: &'a CommentedToken<'a>,
@@ -249,19 +328,19 @@ pub struct IfConditional<'a> {
     pub body: Box<Expression<'a>>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ElseIfConditional<'a> {
     pub else_keyword: &'a CommentedToken<'a>,
     pub if_conditional: IfConditional<'a>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct TrailingElse<'a> {
     pub else_keyword: &'a CommentedToken<'a>,
     pub body: Box<Expression<'a>>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct IfExpression<'a> {
     pub if_conditional: IfConditional<'a>,
     pub else_ifs: Vec<ElseIfConditional<'a>>,
@@ -306,7 +385,7 @@ impl std::fmt::Display for IfExpression<'_> {
 }
 
 // While expression
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct WhileExpression<'a> {
     pub while_keyword: &'a CommentedToken<'a>,
     pub condition: Box<Expression<'a>>,
@@ -323,7 +402,7 @@ impl std::fmt::Display for WhileExpression<'_> {
 }
 
 // Repeat expresssion
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct RepeatExpression<'a> {
     pub repeat_keyword: &'a CommentedToken<'a>,
     pub body: Box<Expression<'a>>,
@@ -336,7 +415,7 @@ impl std::fmt::Display for RepeatExpression<'_> {
 }
 
 // Function call
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct FunctionCall<'a> {
     pub function_ref: Box<Expression<'a>>,
     pub args: Args<'a>,
@@ -349,7 +428,7 @@ impl std::fmt::Display for FunctionCall<'_> {
 }
 
 // Subset expression
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct SubsetExpression<'a> {
     pub object_ref: Box<Expression<'a>>,
     pub args: Args<'a>,
@@ -362,7 +441,7 @@ impl std::fmt::Display for SubsetExpression<'_> {
 }
 
 // For loop
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ForLoop<'a> {
     pub keyword: &'a CommentedToken<'a>,
     pub left_delim: Delimiter<'a>,
@@ -389,10 +468,10 @@ impl std::fmt::Display for ForLoop<'_> {
 }
 
 // Lambda
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Lambda<'a> {
     pub keyword: &'a CommentedToken<'a>,
-    pub args: Args<'a>,
+    pub args: Args<'a, FormalParameter<'a>>,
     pub body: Box<Expression<'a>>,
 }
 
@@ -401,3 +480,213 @@ impl std::fmt::Display for Lambda<'_> {
         f.write_fmt(format_args!("{} {} {}", self.keyword, self.args, self.body))
     }
 }
+
+// Spans
+//
+// A node spans from the start of its first token to the end of its last one,
+// including the comments attached to them.
+
+/// Returns the span from the start of the first span to the end of the last one.
+fn join(spans: impl IntoIterator<Item = Option<Span>>) -> Option<Span> {
+    let mut spans = spans.into_iter().flatten();
+    let first = spans.next()?;
+    Some(match spans.last() {
+        Some(last) => first.to(&last),
+        None => first,
+    })
+}
+
+fn tokens_span(tokens: &[&CommentedToken]) -> Option<Span> {
+    join([tokens.first(), tokens.last()].map(|token| token.map(|token| token.full_span())))
+}
+
+impl Expression<'_> {
+    /// Returns the part of the source this expression was parsed from.
+    ///
+    /// Returns `None` only for a term without any tokens.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Expression::Symbol(token)
+            | Expression::Literal(token)
+            | Expression::Placeholder(token)
+            | Expression::Comment(token)
+            | Expression::Newline(token)
+            | Expression::Semicolon(token)
+            | Expression::EOF(token)
+            | Expression::Break(token)
+            | Expression::Continue(token) => Some(token.full_span()),
+            Expression::Term(term) => term.span(),
+            Expression::Unary(op, expr) | Expression::Formula(op, expr) => {
+                join([Some(op.full_span()), expr.span()])
+            }
+            Expression::Bop(op, left, right) => {
+                join([left.span(), Some(op.full_span()), right.span()])
+            }
+            Expression::Whitespace(tokens) | Expression::Error(tokens) => tokens_span(tokens),
+            Expression::FunctionDef(function_def) => Some(function_def.span()),
+            Expression::LambdaFunction(lambda) => Some(lambda.span()),
+            Expression::IfExpression(if_expression) => Some(if_expression.span()),
+            Expression::WhileExpression(while_expression) => Some(while_expression.span()),
+            Expression::RepeatExpression(repeat_expression) => Some(repeat_expression.span()),
+            Expression::FunctionCall(function_call) => Some(function_call.span()),
+            Expression::SubsetExpression(subset_expression) => Some(subset_expression.span()),
+            Expression::ForLoopExpression(for_loop) => Some(for_loop.span()),
+        }
+    }
+}
+
+impl TermExpr<'_> {
+    /// Returns `None` for a term without delimiters and expressions.
+    pub fn span(&self) -> Option<Span> {
+        join(
+            [self.pre_delimiters.map(CommentedToken::full_span)]
+                .into_iter()
+                .chain(self.term.iter().map(Expression::span))
+                .chain([self.post_delimiters.map(CommentedToken::full_span)]),
+        )
+    }
+}
+
+impl Arg<'_> {
+    /// Returns `None` for the empty argument after a trailing comma.
+    pub fn span(&self) -> Option<Span> {
+        join([
+            self.0.as_ref().and_then(CallArgument::span),
+            self.1.as_ref().and_then(Expression::span),
+        ])
+    }
+}
+
+impl Arg<'_, FormalParameter<'_>> {
+    /// Returns `None` for the empty parameter after a trailing comma.
+    pub fn span(&self) -> Option<Span> {
+        join([
+            self.0.as_ref().map(FormalParameter::span),
+            self.1.as_ref().and_then(Expression::span),
+        ])
+    }
+}
+
+impl CallArgument<'_> {
+    /// Returns `None` only for an argument without a name and a value.
+    pub fn span(&self) -> Option<Span> {
+        join([
+            self.name.map(|(name, _)| name.full_span()),
+            self.name.map(|(_, equal_sign)| equal_sign.full_span()),
+            self.value.as_ref().and_then(Expression::span),
+        ])
+    }
+}
+
+impl FormalParameter<'_> {
+    pub fn span(&self) -> Span {
+        up_to(
+            self.name.full_span(),
+            self.default
+                .as_ref()
+                .and_then(|(_, default)| default.span()),
+        )
+    }
+}
+
+impl Delimiter<'_> {
+    pub fn span(&self) -> Span {
+        match self {
+            Delimiter::Paren(token) | Delimiter::SingleBracket(token) => token.full_span(),
+            Delimiter::DoubleBracket((b1, b2)) => b1.full_span().to(&b2.full_span()),
+        }
+    }
+}
+
+impl<T> Args<'_, T> {
+    pub fn span(&self) -> Span {
+        self.left_delimeter.span().to(&self.right_delimeter.span())
+    }
+}
+
+/// Returns the span from the start of `start` to the end of `end`, if there is one.
+fn up_to(start: Span, end: Option<Span>) -> Span {
+    match end {
+        Some(end) => start.to(&end),
+        None => start,
+    }
+}
+
+impl FunctionDefinition<'_> {
+    pub fn span(&self) -> Span {
+        up_to(self.keyword.full_span(), self.body.span())
+    }
+}
+
+impl IfConditional<'_> {
+    pub fn span(&self) -> Span {
+        up_to(self.keyword.full_span(), self.body.span())
+    }
+}
+
+impl ElseIfConditional<'_> {
+    pub fn span(&self) -> Span {
+        self.else_keyword
+            .full_span()
+            .to(&self.if_conditional.span())
+    }
+}
+
+impl TrailingElse<'_> {
+    pub fn span(&self) -> Span {
+        up_to(self.else_keyword.full_span(), self.body.span())
+    }
+}
+
+impl IfExpression<'_> {
+    pub fn span(&self) -> Span {
+        let end = match (&self.trailing_else, self.else_ifs.last()) {
+            (Some(trailing_else), _) => trailing_else.span(),
+            (None, Some(else_if)) => else_if.span(),
+            (None, None) => return self.if_conditional.span(),
+        };
+        self.if_conditional.span().to(&end)
+    }
+}
+
+impl WhileExpression<'_> {
+    pub fn span(&self) -> Span {
+        up_to(self.while_keyword.full_span(), self.body.span())
+    }
+}
+
+impl RepeatExpression<'_> {
+    pub fn span(&self) -> Span {
+        up_to(self.repeat_keyword.full_span(), self.body.span())
+    }
+}
+
+impl FunctionCall<'_> {
+    pub fn span(&self) -> Span {
+        match self.function_ref.span() {
+            Some(start) => start.to(&self.args.span()),
+            None => self.args.span(),
+        }
+    }
+}
+
+impl SubsetExpression<'_> {
+    pub fn span(&self) -> Span {
+        match self.object_ref.span() {
+            Some(start) => start.to(&self.args.span()),
+            None => self.args.span(),
+        }
+    }
+}
+
+impl ForLoop<'_> {
+    pub fn span(&self) -> Span {
+        up_to(self.keyword.full_span(), self.body.span())
+    }
+}
+
+impl Lambda<'_> {
+    pub fn span(&self) -> Span {
+        up_to(self.keyword.full_span(), self.body.span())
+    }
+}
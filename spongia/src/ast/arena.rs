@@ -0,0 +1,54 @@
+use tokenizer::{tokens::CommentedToken, Token};
+use typed_arena::Arena;
+
+/// Owns the tokens and the text created by the passes rewriting the AST.
+///
+/// The nodes of the AST refer to the tokens of the parsed program. A pass
+/// adding a token, e.g. turning `=` into `<-` or `T` into `TRUE`, allocates
+/// it here and puts the returned reference into the tree. The arena has to
+/// outlive the tree:
+///
+/// ```
+/// use tergo_parser::ast::{Expression, TokenArena};
+/// use tergo_parser::{parse, pre_parse};
+/// use tokenizer::{Token, Tokenizer};
+///
+/// let mut commented_tokens = Tokenizer::new("x = 1\n").tokenize().unwrap();
+/// let tokens = pre_parse(&mut commented_tokens);
+/// let arena = TokenArena::new();
+/// let mut program = parse(&tokens).unwrap();
+/// if let Expression::Bop(op, _, _) = &mut program[0] {
+///     *op = arena.replace(op, Token::LAssign);
+/// }
+/// assert!(matches!(program[0], Expression::Bop(op, ..) if op.token == Token::LAssign));
+/// ```
+#[derive(Default)]
+pub struct TokenArena<'a> {
+    tokens: Arena<CommentedToken<'a>>,
+    text: Arena<u8>,
+}
+
+impl<'a> TokenArena<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves `token` into the arena.
+    pub fn alloc(&self, token: CommentedToken<'a>) -> &CommentedToken<'a> {
+        self.tokens.alloc(token)
+    }
+
+    /// Returns a copy of `original` holding `token`. The copy keeps
+    /// the position and the comments of `original`.
+    pub fn replace(&self, original: &CommentedToken<'a>, token: Token<'a>) -> &CommentedToken<'a> {
+        self.alloc(CommentedToken {
+            token,
+            ..original.clone()
+        })
+    }
+
+    /// Copies `text` into the arena, e.g. for the name of a new symbol.
+    pub fn alloc_str(&self, text: &str) -> &str {
+        self.text.alloc_str(text)
+    }
+}
@@ -0,0 +1,143 @@
+//! The JSON shape of the syntax tree, see [Expression].
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use tokenizer::tokens::CommentedToken;
+
+use super::{
+    Arg, Delimiter, Expression, ForLoop, FunctionCall, FunctionDefinition, IfExpression, Lambda,
+    RepeatExpression, SubsetExpression, TermExpr, WhileExpression,
+};
+
+/// [Expression] with named fields, so that it can be tagged with `type`.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Serialize)]
+#[serde(tag = "type")]
+enum Node<'e, 'a> {
+    Symbol {
+        token: &'a CommentedToken<'a>,
+    },
+    Literal {
+        token: &'a CommentedToken<'a>,
+    },
+    Placeholder {
+        token: &'a CommentedToken<'a>,
+    },
+    Comment {
+        token: &'a CommentedToken<'a>,
+    },
+    Term(&'e TermExpr<'a>),
+    Unary {
+        operator: &'a CommentedToken<'a>,
+        operand: &'e Expression<'a>,
+    },
+    Bop {
+        operator: &'a CommentedToken<'a>,
+        lhs: &'e Expression<'a>,
+        rhs: &'e Expression<'a>,
+    },
+    Formula {
+        tilde: &'a CommentedToken<'a>,
+        operand: &'e Expression<'a>,
+    },
+    Newline {
+        token: &'a CommentedToken<'a>,
+    },
+    Semicolon {
+        token: &'a CommentedToken<'a>,
+    },
+    Whitespace {
+        tokens: &'a [&'a CommentedToken<'a>],
+    },
+    EOF {
+        token: &'a CommentedToken<'a>,
+    },
+    FunctionDef(&'e FunctionDefinition<'a>),
+    LambdaFunction(&'e Lambda<'a>),
+    IfExpression(&'e IfExpression<'a>),
+    WhileExpression(&'e WhileExpression<'a>),
+    RepeatExpression(&'e RepeatExpression<'a>),
+    FunctionCall(&'e FunctionCall<'a>),
+    SubsetExpression(&'e SubsetExpression<'a>),
+    ForLoopExpression(&'e ForLoop<'a>),
+    Break {
+        token: &'a CommentedToken<'a>,
+    },
+    Continue {
+        token: &'a CommentedToken<'a>,
+    },
+    Error {
+        tokens: &'a [&'a CommentedToken<'a>],
+    },
+}
+
+impl<'e, 'a> From<&'e Expression<'a>> for Node<'e, 'a> {
+    fn from(expression: &'e Expression<'a>) -> Self {
+        match expression {
+            Expression::Symbol(token) => Node::Symbol { token },
+            Expression::Literal(token) => Node::Literal { token },
+            Expression::Placeholder(token) => Node::Placeholder { token },
+            Expression::Comment(token) => Node::Comment { token },
+            Expression::Term(term) => Node::Term(term),
+            Expression::Unary(operator, operand) => Node::Unary { operator, operand },
+            Expression::Bop(operator, lhs, rhs) => Node::Bop { operator, lhs, rhs },
+            Expression::Formula(tilde, operand) => Node::Formula { tilde, operand },
+            Expression::Newline(token) => Node::Newline { token },
+            Expression::Semicolon(token) => Node::Semicolon { token },
+            Expression::Whitespace(tokens) => Node::Whitespace { tokens },
+            Expression::EOF(token) => Node::EOF { token },
+            Expression::FunctionDef(function_def) => Node::FunctionDef(function_def),
+            Expression::LambdaFunction(lambda) => Node::LambdaFunction(lambda),
+            Expression::IfExpression(if_expression) => Node::IfExpression(if_expression),
+            Expression::WhileExpression(while_expression) => {
+                Node::WhileExpression(while_expression)
+            }
+            Expression::RepeatExpression(repeat_expression) => {
+                Node::RepeatExpression(repeat_expression)
+            }
+            Expression::FunctionCall(function_call) => Node::FunctionCall(function_call),
+            Expression::SubsetExpression(subset_expression) => {
+                Node::SubsetExpression(subset_expression)
+            }
+            Expression::ForLoopExpression(for_loop) => Node::ForLoopExpression(for_loop),
+            Expression::Break(token) => Node::Break { token },
+            Expression::Continue(token) => Node::Continue { token },
+            Expression::Error(tokens) => Node::Error { tokens },
+        }
+    }
+}
+
+impl Serialize for Expression<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Node::from(self).serialize(serializer)
+    }
+}
+
+impl<T: Serialize> Serialize for Arg<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut arg = serializer.serialize_struct("Arg", 2)?;
+        arg.serialize_field("argument", &self.0)?;
+        arg.serialize_field("comma", &self.1)?;
+        arg.end()
+    }
+}
+
+impl Serialize for Delimiter<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut delimiter = serializer.serialize_struct("Delimiter", 2)?;
+        match self {
+            Delimiter::Paren(token) => {
+                delimiter.serialize_field("type", "Paren")?;
+                delimiter.serialize_field("tokens", &[token])?;
+            }
+            Delimiter::SingleBracket(token) => {
+                delimiter.serialize_field("type", "SingleBracket")?;
+                delimiter.serialize_field("tokens", &[token])?;
+            }
+            Delimiter::DoubleBracket((first, second)) => {
+                delimiter.serialize_field("type", "DoubleBracket")?;
+                delimiter.serialize_field("tokens", &[first, second])?;
+            }
+        }
+        delimiter.end()
+    }
+}
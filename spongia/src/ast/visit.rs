@@ -0,0 +1,626 @@
+use tokenizer::tokens::CommentedToken;
+
+use super::{
+    Arg, Args, CallArgument, Delimiter, ElseIfConditional, Expression, ForLoop, FormalParameter,
+    FunctionCall, FunctionDefinition, IfConditional, IfExpression, Lambda, RepeatExpression,
+    SubsetExpression, TermExpr, TrailingElse, WhileExpression,
+};
+
+/// Traverses the AST in the source order.
+///
+/// Every method visits one kind of node. By default it calls the matching
+/// `walk_*` function, which visits the children of the node. Override the
+/// methods of the nodes you are interested in and call the `walk_*` function
+/// from them to keep descending into the children:
+///
+/// ```
+/// use tergo_parser::ast::{walk_function_call, FunctionCall, Visitor};
+///
+/// #[derive(Default)]
+/// struct CallCounter(usize);
+///
+/// impl<'a> Visitor<'a> for CallCounter {
+///     fn visit_function_call(&mut self, call: &FunctionCall<'a>) {
+///         self.0 += 1;
+///         walk_function_call(self, call);
+///     }
+/// }
+/// ```
+pub trait Visitor<'a> {
+    fn visit_expression(&mut self, expression: &Expression<'a>) {
+        walk_expression(self, expression)
+    }
+
+    fn visit_term(&mut self, term: &TermExpr<'a>) {
+        walk_term(self, term)
+    }
+
+    fn visit_delimiter(&mut self, delimiter: &Delimiter<'a>) {
+        walk_delimiter(self, delimiter)
+    }
+
+    fn visit_args(&mut self, args: &Args<'a>) {
+        walk_args(self, args)
+    }
+
+    fn visit_arg(&mut self, arg: &Arg<'a>) {
+        walk_arg(self, arg)
+    }
+
+    fn visit_call_argument(&mut self, argument: &CallArgument<'a>) {
+        walk_call_argument(self, argument)
+    }
+
+    fn visit_parameters(&mut self, parameters: &Args<'a, FormalParameter<'a>>) {
+        walk_parameters(self, parameters)
+    }
+
+    fn visit_formal_parameter(&mut self, parameter: &FormalParameter<'a>) {
+        walk_formal_parameter(self, parameter)
+    }
+
+    fn visit_function_def(&mut self, function_def: &FunctionDefinition<'a>) {
+        walk_function_def(self, function_def)
+    }
+
+    fn visit_lambda(&mut self, lambda: &Lambda<'a>) {
+        walk_lambda(self, lambda)
+    }
+
+    fn visit_if_expression(&mut self, if_expression: &IfExpression<'a>) {
+        walk_if_expression(self, if_expression)
+    }
+
+    fn visit_if_conditional(&mut self, if_conditional: &IfConditional<'a>) {
+        walk_if_conditional(self, if_conditional)
+    }
+
+    fn visit_else_if(&mut self, else_if: &ElseIfConditional<'a>) {
+        walk_else_if(self, else_if)
+    }
+
+    fn visit_trailing_else(&mut self, trailing_else: &TrailingElse<'a>) {
+        walk_trailing_else(self, trailing_else)
+    }
+
+    fn visit_while_expression(&mut self, while_expression: &WhileExpression<'a>) {
+        walk_while_expression(self, while_expression)
+    }
+
+    fn visit_repeat_expression(&mut self, repeat_expression: &RepeatExpression<'a>) {
+        walk_repeat_expression(self, repeat_expression)
+    }
+
+    fn visit_function_call(&mut self, call: &FunctionCall<'a>) {
+        walk_function_call(self, call)
+    }
+
+    fn visit_subset_expression(&mut self, subset: &SubsetExpression<'a>) {
+        walk_subset_expression(self, subset)
+    }
+
+    fn visit_for_loop(&mut self, for_loop: &ForLoop<'a>) {
+        walk_for_loop(self, for_loop)
+    }
+
+    /// Called for every token of the tree. Does nothing by default.
+    fn visit_token(&mut self, _token: &'a CommentedToken<'a>) {}
+}
+
+pub fn walk_expression<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, expression: &Expression<'a>) {
+    match expression {
+        Expression::Symbol(token)
+        | Expression::Literal(token)
+        | Expression::Placeholder(token)
+        | Expression::Comment(token)
+        | Expression::Newline(token)
+        | Expression::Semicolon(token)
+        | Expression::EOF(token)
+        | Expression::Break(token)
+        | Expression::Continue(token) => visitor.visit_token(token),
+        Expression::Term(term) => visitor.visit_term(term),
+        Expression::Unary(op, expression) | Expression::Formula(op, expression) => {
+            visitor.visit_token(op);
+            visitor.visit_expression(expression);
+        }
+        Expression::Bop(op, lhs, rhs) => {
+            visitor.visit_expression(lhs);
+            visitor.visit_token(op);
+            visitor.visit_expression(rhs);
+        }
+        Expression::Whitespace(tokens) | Expression::Error(tokens) => {
+            tokens.iter().for_each(|token| visitor.visit_token(token))
+        }
+        Expression::FunctionDef(function_def) => visitor.visit_function_def(function_def),
+        Expression::LambdaFunction(lambda) => visitor.visit_lambda(lambda),
+        Expression::IfExpression(if_expression) => visitor.visit_if_expression(if_expression),
+        Expression::WhileExpression(while_expression) => {
+            visitor.visit_while_expression(while_expression)
+        }
+        Expression::RepeatExpression(repeat_expression) => {
+            visitor.visit_repeat_expression(repeat_expression)
+        }
+        Expression::FunctionCall(call) => visitor.visit_function_call(call),
+        Expression::SubsetExpression(subset) => visitor.visit_subset_expression(subset),
+        Expression::ForLoopExpression(for_loop) => visitor.visit_for_loop(for_loop),
+    }
+}
+
+pub fn walk_term<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, term: &TermExpr<'a>) {
+    if let Some(token) = term.pre_delimiters {
+        visitor.visit_token(token);
+    }
+    term.term
+        .iter()
+        .for_each(|expression| visitor.visit_expression(expression));
+    if let Some(token) = term.post_delimiters {
+        visitor.visit_token(token);
+    }
+}
+
+pub fn walk_delimiter<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, delimiter: &Delimiter<'a>) {
+    match delimiter {
+        Delimiter::Paren(token) | Delimiter::SingleBracket(token) => visitor.visit_token(token),
+        Delimiter::DoubleBracket((b1, b2)) => {
+            visitor.visit_token(b1);
+            visitor.visit_token(b2);
+        }
+    }
+}
+
+pub fn walk_args<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, args: &Args<'a>) {
+    visitor.visit_delimiter(&args.left_delimeter);
+    args.args.iter().for_each(|arg| visitor.visit_arg(arg));
+    visitor.visit_delimiter(&args.right_delimeter);
+}
+
+pub fn walk_arg<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, arg: &Arg<'a>) {
+    if let Some(argument) = &arg.0 {
+        visitor.visit_call_argument(argument);
+    }
+    if let Some(comma) = &arg.1 {
+        visitor.visit_expression(comma);
+    }
+}
+
+pub fn walk_call_argument<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    argument: &CallArgument<'a>,
+) {
+    if let Some((name, equal_sign)) = argument.name {
+        visitor.visit_token(name);
+        visitor.visit_token(equal_sign);
+    }
+    if let Some(value) = &argument.value {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_parameters<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    parameters: &Args<'a, FormalParameter<'a>>,
+) {
+    visitor.visit_delimiter(&parameters.left_delimeter);
+    for arg in parameters.args.iter() {
+        if let Some(parameter) = &arg.0 {
+            visitor.visit_formal_parameter(parameter);
+        }
+        if let Some(comma) = &arg.1 {
+            visitor.visit_expression(comma);
+        }
+    }
+    visitor.visit_delimiter(&parameters.right_delimeter);
+}
+
+pub fn walk_formal_parameter<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    parameter: &FormalParameter<'a>,
+) {
+    visitor.visit_token(parameter.name);
+    if let Some((equal_sign, default)) = &parameter.default {
+        visitor.visit_token(equal_sign);
+        visitor.visit_expression(default);
+    }
+}
+
+pub fn walk_function_def<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    function_def: &FunctionDefinition<'a>,
+) {
+    visitor.visit_token(function_def.keyword);
+    visitor.visit_parameters(&function_def.arguments);
+    visitor.visit_expression(&function_def.body);
+}
+
+pub fn walk_lambda<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, lambda: &Lambda<'a>) {
+    visitor.visit_token(lambda.keyword);
+    visitor.visit_parameters(&lambda.args);
+    visitor.visit_expression(&lambda.body);
+}
+
+pub fn walk_if_expression<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    if_expression: &IfExpression<'a>,
+) {
+    visitor.visit_if_conditional(&if_expression.if_conditional);
+    if_expression
+        .else_ifs
+        .iter()
+        .for_each(|else_if| visitor.visit_else_if(else_if));
+    if let Some(trailing_else) = &if_expression.trailing_else {
+        visitor.visit_trailing_else(trailing_else);
+    }
+}
+
+pub fn walk_if_conditional<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    if_conditional: &IfConditional<'a>,
+) {
+    visitor.visit_token(if_conditional.keyword);
+    visitor.visit_token(if_conditional.left_delimiter);
+    visitor.visit_expression(&if_conditional.condition);
+    visitor.visit_token(if_conditional.right_delimiter);
+    visitor.visit_expression(&if_conditional.body);
+}
+
+pub fn walk_else_if<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, else_if: &ElseIfConditional<'a>) {
+    visitor.visit_token(else_if.else_keyword);
+    visitor.visit_if_conditional(&else_if.if_conditional);
+}
+
+pub fn walk_trailing_else<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    trailing_else: &TrailingElse<'a>,
+) {
+    visitor.visit_token(trailing_else.else_keyword);
+    visitor.visit_expression(&trailing_else.body);
+}
+
+pub fn walk_while_expression<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    while_expression: &WhileExpression<'a>,
+) {
+    visitor.visit_token(while_expression.while_keyword);
+    visitor.visit_expression(&while_expression.condition);
+    visitor.visit_expression(&while_expression.body);
+}
+
+pub fn walk_repeat_expression<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    repeat_expression: &RepeatExpression<'a>,
+) {
+    visitor.visit_token(repeat_expression.repeat_keyword);
+    visitor.visit_expression(&repeat_expression.body);
+}
+
+pub fn walk_function_call<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, call: &FunctionCall<'a>) {
+    visitor.visit_expression(&call.function_ref);
+    visitor.visit_args(&call.args);
+}
+
+pub fn walk_subset_expression<'a, V: Visitor<'a> + ?Sized>(
+    visitor: &mut V,
+    subset: &SubsetExpression<'a>,
+) {
+    visitor.visit_expression(&subset.object_ref);
+    visitor.visit_args(&subset.args);
+}
+
+pub fn walk_for_loop<'a, V: Visitor<'a> + ?Sized>(visitor: &mut V, for_loop: &ForLoop<'a>) {
+    visitor.visit_token(for_loop.keyword);
+    visitor.visit_delimiter(&for_loop.left_delim);
+    visitor.visit_expression(&for_loop.identifier);
+    visitor.visit_token(for_loop.in_keyword);
+    visitor.visit_expression(&for_loop.collection);
+    visitor.visit_delimiter(&for_loop.right_delim);
+    visitor.visit_expression(&for_loop.body);
+}
+
+/// Traverses the AST in the source order and allows changing it.
+///
+/// Works like [Visitor]. The tokens are shared, so [VisitorMut::visit_token]
+/// cannot change them; replace the expression holding a token instead.
+pub trait VisitorMut<'a> {
+    fn visit_expression(&mut self, expression: &mut Expression<'a>) {
+        walk_expression_mut(self, expression)
+    }
+
+    fn visit_term(&mut self, term: &mut TermExpr<'a>) {
+        walk_term_mut(self, term)
+    }
+
+    fn visit_delimiter(&mut self, delimiter: &mut Delimiter<'a>) {
+        walk_delimiter_mut(self, delimiter)
+    }
+
+    fn visit_args(&mut self, args: &mut Args<'a>) {
+        walk_args_mut(self, args)
+    }
+
+    fn visit_arg(&mut self, arg: &mut Arg<'a>) {
+        walk_arg_mut(self, arg)
+    }
+
+    fn visit_call_argument(&mut self, argument: &mut CallArgument<'a>) {
+        walk_call_argument_mut(self, argument)
+    }
+
+    fn visit_parameters(&mut self, parameters: &mut Args<'a, FormalParameter<'a>>) {
+        walk_parameters_mut(self, parameters)
+    }
+
+    fn visit_formal_parameter(&mut self, parameter: &mut FormalParameter<'a>) {
+        walk_formal_parameter_mut(self, parameter)
+    }
+
+    fn visit_function_def(&mut self, function_def: &mut FunctionDefinition<'a>) {
+        walk_function_def_mut(self, function_def)
+    }
+
+    fn visit_lambda(&mut self, lambda: &mut Lambda<'a>) {
+        walk_lambda_mut(self, lambda)
+    }
+
+    fn visit_if_expression(&mut self, if_expression: &mut IfExpression<'a>) {
+        walk_if_expression_mut(self, if_expression)
+    }
+
+    fn visit_if_conditional(&mut self, if_conditional: &mut IfConditional<'a>) {
+        walk_if_conditional_mut(self, if_conditional)
+    }
+
+    fn visit_else_if(&mut self, else_if: &mut ElseIfConditional<'a>) {
+        walk_else_if_mut(self, else_if)
+    }
+
+    fn visit_trailing_else(&mut self, trailing_else: &mut TrailingElse<'a>) {
+        walk_trailing_else_mut(self, trailing_else)
+    }
+
+    fn visit_while_expression(&mut self, while_expression: &mut WhileExpression<'a>) {
+        walk_while_expression_mut(self, while_expression)
+    }
+
+    fn visit_repeat_expression(&mut self, repeat_expression: &mut RepeatExpression<'a>) {
+        walk_repeat_expression_mut(self, repeat_expression)
+    }
+
+    fn visit_function_call(&mut self, call: &mut FunctionCall<'a>) {
+        walk_function_call_mut(self, call)
+    }
+
+    fn visit_subset_expression(&mut self, subset: &mut SubsetExpression<'a>) {
+        walk_subset_expression_mut(self, subset)
+    }
+
+    fn visit_for_loop(&mut self, for_loop: &mut ForLoop<'a>) {
+        walk_for_loop_mut(self, for_loop)
+    }
+
+    /// Called for every token of the tree. Does nothing by default.
+    fn visit_token(&mut self, _token: &'a CommentedToken<'a>) {}
+}
+
+pub fn walk_expression_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    expression: &mut Expression<'a>,
+) {
+    match expression {
+        Expression::Symbol(token)
+        | Expression::Literal(token)
+        | Expression::Placeholder(token)
+        | Expression::Comment(token)
+        | Expression::Newline(token)
+        | Expression::Semicolon(token)
+        | Expression::EOF(token)
+        | Expression::Break(token)
+        | Expression::Continue(token) => visitor.visit_token(token),
+        Expression::Term(term) => visitor.visit_term(term),
+        Expression::Unary(op, expression) | Expression::Formula(op, expression) => {
+            visitor.visit_token(op);
+            visitor.visit_expression(expression);
+        }
+        Expression::Bop(op, lhs, rhs) => {
+            visitor.visit_expression(lhs);
+            visitor.visit_token(op);
+            visitor.visit_expression(rhs);
+        }
+        Expression::Whitespace(tokens) | Expression::Error(tokens) => {
+            tokens.iter().for_each(|token| visitor.visit_token(token))
+        }
+        Expression::FunctionDef(function_def) => visitor.visit_function_def(function_def),
+        Expression::LambdaFunction(lambda) => visitor.visit_lambda(lambda),
+        Expression::IfExpression(if_expression) => visitor.visit_if_expression(if_expression),
+        Expression::WhileExpression(while_expression) => {
+            visitor.visit_while_expression(while_expression)
+        }
+        Expression::RepeatExpression(repeat_expression) => {
+            visitor.visit_repeat_expression(repeat_expression)
+        }
+        Expression::FunctionCall(call) => visitor.visit_function_call(call),
+        Expression::SubsetExpression(subset) => visitor.visit_subset_expression(subset),
+        Expression::ForLoopExpression(for_loop) => visitor.visit_for_loop(for_loop),
+    }
+}
+
+pub fn walk_term_mut<'a, V: VisitorMut<'a> + ?Sized>(visitor: &mut V, term: &mut TermExpr<'a>) {
+    if let Some(token) = term.pre_delimiters {
+        visitor.visit_token(token);
+    }
+    term.term
+        .iter_mut()
+        .for_each(|expression| visitor.visit_expression(expression));
+    if let Some(token) = term.post_delimiters {
+        visitor.visit_token(token);
+    }
+}
+
+pub fn walk_delimiter_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    delimiter: &mut Delimiter<'a>,
+) {
+    match delimiter {
+        Delimiter::Paren(token) | Delimiter::SingleBracket(token) => visitor.visit_token(token),
+        Delimiter::DoubleBracket((b1, b2)) => {
+            visitor.visit_token(b1);
+            visitor.visit_token(b2);
+        }
+    }
+}
+
+pub fn walk_args_mut<'a, V: VisitorMut<'a> + ?Sized>(visitor: &mut V, args: &mut Args<'a>) {
+    visitor.visit_delimiter(&mut args.left_delimeter);
+    args.args.iter_mut().for_each(|arg| visitor.visit_arg(arg));
+    visitor.visit_delimiter(&mut args.right_delimeter);
+}
+
+pub fn walk_arg_mut<'a, V: VisitorMut<'a> + ?Sized>(visitor: &mut V, arg: &mut Arg<'a>) {
+    if let Some(argument) = &mut arg.0 {
+        visitor.visit_call_argument(argument);
+    }
+    if let Some(comma) = &mut arg.1 {
+        visitor.visit_expression(comma);
+    }
+}
+
+pub fn walk_call_argument_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    argument: &mut CallArgument<'a>,
+) {
+    if let Some((name, equal_sign)) = argument.name {
+        visitor.visit_token(name);
+        visitor.visit_token(equal_sign);
+    }
+    if let Some(value) = &mut argument.value {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_parameters_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    parameters: &mut Args<'a, FormalParameter<'a>>,
+) {
+    visitor.visit_delimiter(&mut parameters.left_delimeter);
+    for arg in parameters.args.iter_mut() {
+        if let Some(parameter) = &mut arg.0 {
+            visitor.visit_formal_parameter(parameter);
+        }
+        if let Some(comma) = &mut arg.1 {
+            visitor.visit_expression(comma);
+        }
+    }
+    visitor.visit_delimiter(&mut parameters.right_delimeter);
+}
+
+pub fn walk_formal_parameter_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    parameter: &mut FormalParameter<'a>,
+) {
+    visitor.visit_token(parameter.name);
+    if let Some((equal_sign, default)) = &mut parameter.default {
+        visitor.visit_token(equal_sign);
+        visitor.visit_expression(default);
+    }
+}
+
+pub fn walk_function_def_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    function_def: &mut FunctionDefinition<'a>,
+) {
+    visitor.visit_token(function_def.keyword);
+    visitor.visit_parameters(&mut function_def.arguments);
+    visitor.visit_expression(&mut function_def.body);
+}
+
+pub fn walk_lambda_mut<'a, V: VisitorMut<'a> + ?Sized>(visitor: &mut V, lambda: &mut Lambda<'a>) {
+    visitor.visit_token(lambda.keyword);
+    visitor.visit_parameters(&mut lambda.args);
+    visitor.visit_expression(&mut lambda.body);
+}
+
+pub fn walk_if_expression_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    if_expression: &mut IfExpression<'a>,
+) {
+    visitor.visit_if_conditional(&mut if_expression.if_conditional);
+    if_expression
+        .else_ifs
+        .iter_mut()
+        .for_each(|else_if| visitor.visit_else_if(else_if));
+    if let Some(trailing_else) = &mut if_expression.trailing_else {
+        visitor.visit_trailing_else(trailing_else);
+    }
+}
+
+pub fn walk_if_conditional_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    if_conditional: &mut IfConditional<'a>,
+) {
+    visitor.visit_token(if_conditional.keyword);
+    visitor.visit_token(if_conditional.left_delimiter);
+    visitor.visit_expression(&mut if_conditional.condition);
+    visitor.visit_token(if_conditional.right_delimiter);
+    visitor.visit_expression(&mut if_conditional.body);
+}
+
+pub fn walk_else_if_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    else_if: &mut ElseIfConditional<'a>,
+) {
+    visitor.visit_token(else_if.else_keyword);
+    visitor.visit_if_conditional(&mut else_if.if_conditional);
+}
+
+pub fn walk_trailing_else_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    trailing_else: &mut TrailingElse<'a>,
+) {
+    visitor.visit_token(trailing_else.else_keyword);
+    visitor.visit_expression(&mut trailing_else.body);
+}
+
+pub fn walk_while_expression_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    while_expression: &mut WhileExpression<'a>,
+) {
+    visitor.visit_token(while_expression.while_keyword);
+    visitor.visit_expression(&mut while_expression.condition);
+    visitor.visit_expression(&mut while_expression.body);
+}
+
+pub fn walk_repeat_expression_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    repeat_expression: &mut RepeatExpression<'a>,
+) {
+    visitor.visit_token(repeat_expression.repeat_keyword);
+    visitor.visit_expression(&mut repeat_expression.body);
+}
+
+pub fn walk_function_call_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    call: &mut FunctionCall<'a>,
+) {
+    visitor.visit_expression(&mut call.function_ref);
+    visitor.visit_args(&mut call.args);
+}
+
+pub fn walk_subset_expression_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    subset: &mut SubsetExpression<'a>,
+) {
+    visitor.visit_expression(&mut subset.object_ref);
+    visitor.visit_args(&mut subset.args);
+}
+
+pub fn walk_for_loop_mut<'a, V: VisitorMut<'a> + ?Sized>(
+    visitor: &mut V,
+    for_loop: &mut ForLoop<'a>,
+) {
+    visitor.visit_token(for_loop.keyword);
+    visitor.visit_delimiter(&mut for_loop.left_delim);
+    visitor.visit_expression(&mut for_loop.identifier);
+    visitor.visit_token(for_loop.in_keyword);
+    visitor.visit_expression(&mut for_loop.collection);
+    visitor.visit_delimiter(&mut for_loop.right_delim);
+    visitor.visit_expression(&mut for_loop.body);
+}
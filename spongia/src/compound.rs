@@ -1,239 +1,300 @@
 use log::trace;
 use nom::{
-    combinator::{map, opt},
-    error::Error,
+    branch::alt,
+    combinator::{flat_map, map, opt, success, verify},
     multi::many0,
     sequence::tuple,
-    IResult, Parser,
+    Parser,
 };
 
 use crate::{
     ast::{
-        Arg, Args, Delimiter, ElseIfConditional, Expression, ForLoop, FunctionDefinition,
-        IfConditional, IfExpression, Lambda, RepeatExpression, TrailingElse, WhileExpression,
+        Arg, Args, CallArgument, Delimiter, ElseIfConditional, Expression, ForLoop,
+        FormalParameter, FunctionDefinition, IfConditional, IfExpression, Lambda, RepeatExpression,
+        TrailingElse, WhileExpression,
     },
+    error::{closing, commit, SyntaxError},
     expressions::expr,
-    program::statement_or_expr,
+    program::{statement_or_expr, ParseState},
     token_parsers::*,
-    Input,
+    IResult, Input,
 };
+use tokenizer::{tokens::CommentedToken, Token};
 
 // Function definition
-pub(crate) fn function_def<'a, 'b: 'a>(
-    tokens: Input<'a, 'b>,
-) -> IResult<Input<'a, 'b>, Expression<'a>> {
-    map(
-        tuple((
-            function,
-            many0(newline),
-            delimited_comma_sep_exprs(map(lparen, Delimiter::Paren), map(rparen, Delimiter::Paren)),
-            many0(newline),
-            expr,
-        )),
-        |(keyword, _, args, _, body)| {
-            Expression::FunctionDef(FunctionDefinition::new(keyword, args, Box::new(body)))
-        },
-    )(tokens)
+pub(crate) fn function_def<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Expression<'a>> + '_ {
+    move |tokens| {
+        map(
+            tuple((
+                function,
+                delimited_comma_sep_exprs(
+                    map(lparen, Delimiter::Paren),
+                    map(rparen, Delimiter::Paren),
+                    formal_parameter(state),
+                    "parameter list",
+                ),
+                expr(state),
+            )),
+            |(keyword, args, body)| {
+                Expression::FunctionDef(FunctionDefinition::new(keyword, args, Box::new(body)))
+            },
+        )(tokens)
+    }
 }
 
-pub(crate) fn delimited_comma_sep_exprs<'a, P1, P2>(
-    left_delimiter: P1,
-    right_delimiter: P2,
-) -> impl Parser<Input<'a, 'a>, Args<'a>, Error<Input<'a, 'a>>>
+pub(crate) fn delimited_comma_sep_exprs<'a, T, P1, P2, P3>(
+    mut left_delimiter: P1,
+    mut right_delimiter: P2,
+    mut item: P3,
+    construct: &'static str,
+) -> impl Parser<Input<'a, 'a>, Args<'a, T>, SyntaxError<Input<'a, 'a>>>
 where
-    P1: Parser<Input<'a, 'a>, Delimiter<'a>, Error<Input<'a, 'a>>>,
-    P2: Parser<Input<'a, 'a>, Delimiter<'a>, Error<Input<'a, 'a>>>,
+    T: std::fmt::Debug + PartialEq,
+    P1: Parser<Input<'a, 'a>, Delimiter<'a>, SyntaxError<Input<'a, 'a>>>,
+    P2: Parser<Input<'a, 'a>, Delimiter<'a>, SyntaxError<Input<'a, 'a>>>,
+    P3: Parser<Input<'a, 'a>, T, SyntaxError<Input<'a, 'a>>>,
 {
-    map(
-        tuple((
-            left_delimiter,
-            many0(newline),
-            opt(expr),
-            many0(tuple((
-                tuple((comma, many0(newline))),
-                tuple((opt(expr), many0(newline))),
-            ))),
-            many0(newline),
-            right_delimiter,
-        )),
-        |(ldelim, _, first_arg, comma_delimited_args, _, rdelim)| {
-            let comma_delimited_args = comma_delimited_args
-                .into_iter()
-                .flat_map(|((sep, _), (xpr, _))| [Some(Expression::Literal(sep)), xpr]);
-            let mut args = vec![];
-            let mut comma_delimited_args = std::iter::once(first_arg).chain(comma_delimited_args);
-            while let Some(potential_arg) = comma_delimited_args.next() {
-                if let Some(potential_delim) = comma_delimited_args.next() {
-                    args.push(Arg(potential_arg, potential_delim));
-                } else {
-                    args.push(Arg(potential_arg, None));
-                }
-            }
-            if !args.is_empty() && args[0] == Arg(None, None) {
-                args = vec![];
-            }
-            trace!("delimited_comma_sep_exprs: parsed args {args:?}");
-            Args::new(ldelim, args, rdelim)
-        },
-    )
+    move |tokens| {
+        let (tokens, ldelim) = left_delimiter.parse(tokens)?;
+        let (opening, closing_delimiter) = match ldelim {
+            Delimiter::Paren(token) => (token, "`)`"),
+            Delimiter::SingleBracket(token) => (token, "`]`"),
+            Delimiter::DoubleBracket((token, _)) => (token, "`]]`"),
+        };
+        let (tokens, first_arg) = opt(commit(|tokens| item.parse(tokens)))(tokens)?;
+        let (tokens, comma_delimited_args) =
+            many0(tuple((comma, opt(commit(|tokens| item.parse(tokens))))))(tokens)?;
+        let (tokens, rdelim) = closing(opening, closing_delimiter, construct, |tokens| {
+            right_delimiter.parse(tokens)
+        })(tokens)?;
+        let mut args = vec![];
+        let mut current = first_arg;
+        for (sep, item) in comma_delimited_args {
+            args.push(Arg(current, Some(Expression::Literal(sep))));
+            current = item;
+        }
+        if !args.is_empty() || current.is_some() {
+            args.push(Arg(current, None));
+        }
+        trace!("delimited_comma_sep_exprs: parsed args {args:?}");
+        Ok((tokens, Args::new(ldelim, args, rdelim)))
+    }
 }
 
-// If expression
-pub(crate) fn if_expression<'a, 'b: 'a>(
-    tokens: Input<'a, 'b>,
-) -> IResult<Input<'a, 'b>, Expression<'a>> {
-    map(
-        tuple((if_conditional, many0(else_if), opt(trailing_else))),
-        |(if_conditional, else_ifs, trailing_else)| {
-            Expression::IfExpression(IfExpression {
-                if_conditional,
-                else_ifs,
-                trailing_else,
-            })
-        },
-    )(tokens)
+/// Parses a formal parameter of a function definition: a name with an optional
+/// default value.
+pub(crate) fn formal_parameter<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, FormalParameter<'a>> + '_ {
+    move |tokens| {
+        map(
+            tuple((symbol, opt(commit(tuple((old_assign, expr(state))))))),
+            |(name, default)| FormalParameter { name, default },
+        )(tokens)
+    }
 }
 
-fn if_conditional<'a, 'b: 'a>(tokens: Input<'a, 'b>) -> IResult<Input<'a, 'b>, IfConditional<'a>> {
-    map(
-        tuple((
-            if_token,
-            lparen,
-            many0(newline),
-            expr,
-            many0(newline),
-            rparen,
-            many0(newline),
-            expr,
-        )),
-        |(keyword, left_delimiter, _, condition, _, right_delimiter, _, body)| IfConditional {
-            keyword,
-            left_delimiter,
-            condition: Box::new(condition),
-            right_delimiter,
-            body: Box::new(body),
-        },
-    )(tokens)
+/// Parses an argument of a call or a subset. The argument is named if it starts
+/// with a symbol, `NULL` or a string followed by `=`, and the value of a named
+/// argument may be missing, as in `alist(x = )`.
+pub(crate) fn call_argument<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, CallArgument<'a>> + '_ {
+    move |tokens| {
+        alt((
+            map(
+                tuple((argument_name, old_assign, opt(commit(expr(state))))),
+                |(name, equal_sign, value)| CallArgument {
+                    name: Some((name, equal_sign)),
+                    value,
+                },
+            ),
+            map(expr(state), |value| CallArgument {
+                name: None,
+                value: Some(value),
+            }),
+        ))(tokens)
+    }
 }
 
-fn else_if<'a, 'b: 'a>(tokens: Input<'a, 'b>) -> IResult<Input<'a, 'b>, ElseIfConditional<'a>> {
-    map(
-        tuple((else_token, if_conditional)),
-        |(else_keyword, if_conditional)| ElseIfConditional {
-            else_keyword,
-            if_conditional,
-        },
-    )(tokens)
+fn argument_name<'a, 'b: 'a>(
+    tokens: Input<'a, 'b>,
+) -> IResult<Input<'a, 'b>, &'b CommentedToken<'a>> {
+    alt((
+        symbol,
+        verify(literal, |token: &CommentedToken| match token.token {
+            Token::Null => true,
+            Token::Literal(text) => text.starts_with(['"', '\'', 'r', 'R']),
+            _ => false,
+        }),
+    ))(tokens)
 }
 
-fn trailing_else<'a, 'b: 'a>(tokens: Input<'a, 'b>) -> IResult<Input<'a, 'b>, TrailingElse<'a>> {
-    map(tuple((else_token, expr)), |(else_keyword, body)| {
-        TrailingElse {
-            else_keyword,
-            body: Box::new(body),
-        }
-    })(tokens)
+// If expression
+pub(crate) fn if_expression<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Expression<'a>> + '_ {
+    move |tokens| {
+        map(
+            tuple((
+                if_conditional(state),
+                many0(else_if(state)),
+                opt(trailing_else(state)),
+            )),
+            |(if_conditional, else_ifs, trailing_else)| {
+                Expression::IfExpression(IfExpression {
+                    if_conditional,
+                    else_ifs,
+                    trailing_else,
+                })
+            },
+        )(tokens)
+    }
 }
 
-// While expression
-pub(crate) fn while_expression<'a, 'b: 'a>(
-    tokens: Input<'a, 'b>,
-) -> IResult<Input<'a, 'b>, Expression<'a>> {
-    map(
-        tuple((while_token, many0(newline), expr, many0(newline), expr)),
-        |(while_keyword, _, condition, _, body)| {
-            Expression::WhileExpression(WhileExpression {
-                while_keyword,
+fn if_conditional<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, IfConditional<'a>> + '_ {
+    move |tokens| {
+        map(
+            tuple((
+                if_token,
+                flat_map(lparen, |lparen| {
+                    tuple((
+                        success(lparen),
+                        expr(state),
+                        closing(lparen, "`)`", "condition", rparen),
+                    ))
+                }),
+                expr(state),
+            )),
+            |(keyword, (left_delimiter, condition, right_delimiter), body)| IfConditional {
+                keyword,
+                left_delimiter,
                 condition: Box::new(condition),
+                right_delimiter,
                 body: Box::new(body),
-            })
-        },
-    )(tokens)
+            },
+        )(tokens)
+    }
+}
+
+fn else_if<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, ElseIfConditional<'a>> + '_ {
+    move |tokens| {
+        map(
+            tuple((else_token, if_conditional(state))),
+            |(else_keyword, if_conditional)| ElseIfConditional {
+                else_keyword,
+                if_conditional,
+            },
+        )(tokens)
+    }
+}
+
+fn trailing_else<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, TrailingElse<'a>> + '_ {
+    move |tokens| {
+        map(tuple((else_token, expr(state))), |(else_keyword, body)| {
+            TrailingElse {
+                else_keyword,
+                body: Box::new(body),
+            }
+        })(tokens)
+    }
+}
+
+// While expression
+pub(crate) fn while_expression<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Expression<'a>> + '_ {
+    move |tokens| {
+        map(
+            tuple((while_token, expr(state), expr(state))),
+            |(while_keyword, condition, body)| {
+                Expression::WhileExpression(WhileExpression {
+                    while_keyword,
+                    condition: Box::new(condition),
+                    body: Box::new(body),
+                })
+            },
+        )(tokens)
+    }
 }
 
 // Repeat expression
-pub(crate) fn repeat_expression<'a, 'b: 'a>(
-    tokens: Input<'a, 'b>,
-) -> IResult<Input<'a, 'b>, Expression<'a>> {
-    map(
-        tuple((repeat, many0(newline), expr)),
-        |(repeat_keyword, _, body)| {
+pub(crate) fn repeat_expression<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Expression<'a>> + '_ {
+    move |tokens| {
+        map(tuple((repeat, expr(state))), |(repeat_keyword, body)| {
             Expression::RepeatExpression(RepeatExpression {
                 repeat_keyword,
                 body: Box::new(body),
             })
-        },
-    )(tokens)
+        })(tokens)
+    }
 }
 
 // For loops
-pub(crate) fn for_loop_expression<'a, 'b: 'a>(
-    tokens: Input<'a, 'b>,
-) -> IResult<Input<'a, 'b>, Expression<'a>> {
-    map(
-        tuple((
-            for_token,
-            many0(newline),
-            map(lparen, Delimiter::Paren),
-            many0(newline),
-            expr,
-            many0(newline),
-            in_token,
-            many0(newline),
-            expr,
-            many0(newline),
-            map(rparen, Delimiter::Paren),
-            many0(newline),
-            expr,
-        )),
-        |(
-            keyword,
-            _,
-            left_delim,
-            _,
-            identifier,
-            _,
-            in_keyword,
-            _,
-            collection,
-            _,
-            right_delim,
-            _,
-            body,
-        )| {
-            Expression::ForLoopExpression(ForLoop {
-                keyword,
-                left_delim,
-                identifier: Box::new(identifier),
-                in_keyword,
-                collection: Box::new(collection),
-                right_delim,
-                body: Box::new(body),
-            })
-        },
-    )(tokens)
+pub(crate) fn for_loop_expression<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Expression<'a>> + '_ {
+    move |tokens| {
+        map(
+            tuple((
+                for_token,
+                map(lparen, Delimiter::Paren),
+                expr(state),
+                in_token,
+                expr(state),
+                map(rparen, Delimiter::Paren),
+                expr(state),
+            )),
+            |(keyword, left_delim, identifier, in_keyword, collection, right_delim, body)| {
+                Expression::ForLoopExpression(ForLoop {
+                    keyword,
+                    left_delim,
+                    identifier: Box::new(identifier),
+                    in_keyword,
+                    collection: Box::new(collection),
+                    right_delim,
+                    body: Box::new(body),
+                })
+            },
+        )(tokens)
+    }
 }
 
 // Lambda
-pub(crate) fn lambda_function<'a, 'b: 'a>(
-    tokens: Input<'a, 'b>,
-) -> IResult<Input<'a, 'b>, Expression<'a>> {
-    map(
-        tuple((
-            lambda,
-            many0(newline),
-            delimited_comma_sep_exprs(map(lparen, Delimiter::Paren), map(rparen, Delimiter::Paren)),
-            many0(newline),
-            statement_or_expr,
-        )),
-        |(keyword, _, args, _, body)| {
-            Expression::LambdaFunction(Lambda {
-                keyword,
-                args,
-                body: Box::new(body),
-            })
-        },
-    )(tokens)
+pub(crate) fn lambda_function<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Expression<'a>> + '_ {
+    move |tokens| {
+        map(
+            tuple((
+                lambda,
+                delimited_comma_sep_exprs(
+                    map(lparen, Delimiter::Paren),
+                    map(rparen, Delimiter::Paren),
+                    formal_parameter(state),
+                    "parameter list",
+                ),
+                statement_or_expr(state),
+            )),
+            |(keyword, args, body)| {
+                Expression::LambdaFunction(Lambda {
+                    keyword,
+                    args,
+                    body: Box::new(body),
+                })
+            },
+        )(tokens)
+    }
 }
 
 #[cfg(test)]
@@ -255,7 +316,7 @@ mod tests {
         log_init();
         let tokens_ = commented_tokens![Function, LParen, RParen, LBrace, RBrace, EOF];
         let tokens: Vec<_> = tokens_.iter().collect();
-        let parsed = expr(&tokens).unwrap();
+        let parsed = expr(&ParseState::default())(&tokens).unwrap();
         let res = parsed.1;
         assert_eq!(
             res,
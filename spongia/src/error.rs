@@ -0,0 +1,300 @@
+use std::ops::Range;
+
+use nom::error::ErrorKind;
+use nom::Parser;
+use tokenizer::tokens::CommentedToken;
+use tokenizer::Token;
+
+use crate::{IResult, Input};
+
+/// An error returned by [crate::parse].
+///
+/// `line` and `offset` point at the offending token and use the same
+/// convention as the tokens: both are counted from zero. The
+/// [std::fmt::Display] implementation prints them counted from one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// The line of the offending token.
+    pub line: u32,
+    /// The column offset of the offending token.
+    pub offset: usize,
+    /// The byte range of the offending token in the source.
+    pub span: Range<usize>,
+    /// What the parser expected instead, e.g. "`)` to close call started at 12:4".
+    pub expected: Option<String>,
+    /// The offending token, e.g. "`}`" or "end of input".
+    pub found: String,
+    /// The source line of the offending token with a caret under it.
+    /// Set by [ParseError::with_source].
+    pub snippet: Option<String>,
+    /// Set when the offending token is a pipe placeholder `_` used where R
+    /// does not allow it. `expected` is `None` then.
+    pub placeholder_misuse: Option<PlaceholderMisuse>,
+}
+
+/// A use of the pipe placeholder `_` that R rejects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceholderMisuse {
+    /// The placeholder is not on the right-hand side of `|>`, e.g. `f(x = _)`.
+    OutsideOfPipe,
+    /// The placeholder is not a named argument of the call on the right-hand
+    /// side of `|>`, e.g. `x |> f(_)`.
+    NotNamed,
+    /// The call on the right-hand side of `|>` takes the placeholder more
+    /// than once, e.g. `x |> f(y = _, z = _)`.
+    Repeated,
+}
+
+impl std::fmt::Display for PlaceholderMisuse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            PlaceholderMisuse::OutsideOfPipe => "invalid use of pipe placeholder",
+            PlaceholderMisuse::NotNamed => "pipe placeholder can only be used as a named argument",
+            PlaceholderMisuse::Repeated => "pipe placeholder may only appear once",
+        })
+    }
+}
+
+impl ParseError {
+    /// Adds the snippet of `source`, the program that was parsed, pointing at the error.
+    pub fn with_source(mut self, source: &str) -> Self {
+        let start = self.span.start.min(source.len());
+        let line_start = source[..start]
+            .rfind(['\n', '\r'])
+            .map(|position| position + 1)
+            .unwrap_or(0);
+        let line_end = source[start..]
+            .find(['\n', '\r'])
+            .map(|position| start + position)
+            .unwrap_or(source.len());
+        let line = &source[line_start..line_end];
+        // Keep the tabs, so the caret lines up however wide they are
+        let padding: String = source[line_start..start]
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = source[start..self.span.end.clamp(start, line_end)]
+            .chars()
+            .count()
+            .max(1);
+        self.snippet = Some(format!("{line}\n{padding}{}", "^".repeat(width)));
+        self
+    }
+
+    pub(crate) fn new(token: Option<&CommentedToken>, expected: Expected) -> Self {
+        let (line, offset, span) = token
+            .map(|token| (token.line, token.offset, token.span.clone()))
+            .unwrap_or_default();
+        let found = match token.map(|token| &token.token) {
+            None | Some(Token::EOF) => "end of input".to_string(),
+            Some(token) => describe(token),
+        };
+        Self {
+            line,
+            offset,
+            span,
+            expected: expected.describe(),
+            found,
+            snippet: None,
+            placeholder_misuse: None,
+        }
+    }
+
+    /// Returns the error for the pipe placeholder `token` used where R does not allow it.
+    pub(crate) fn placeholder(token: &CommentedToken, misuse: PlaceholderMisuse) -> Self {
+        Self {
+            placeholder_misuse: Some(misuse),
+            ..Self::new(Some(token), Expected::Nothing)
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: ", self.line + 1, self.offset + 1)?;
+        match (&self.placeholder_misuse, &self.expected) {
+            (Some(misuse), _) => write!(f, "{misuse}")?,
+            (None, Some(expected)) => write!(f, "expected {expected}, found {}", self.found)?,
+            (None, None) => write!(f, "unexpected {}", self.found)?,
+        }
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n{snippet}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// What the parser was looking for when it failed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Expected {
+    /// Nothing in particular, the token cannot appear there.
+    Nothing,
+    /// A specific token, e.g. "`(`".
+    Token(&'static str),
+    Expression,
+    /// The delimiter closing a construct opened at `line` and `offset`.
+    Closing {
+        delimiter: &'static str,
+        construct: &'static str,
+        line: u32,
+        offset: usize,
+    },
+}
+
+impl Expected {
+    fn describe(self) -> Option<String> {
+        match self {
+            Expected::Nothing => None,
+            Expected::Token(token) => Some(token.to_string()),
+            Expected::Expression => Some("an expression".to_string()),
+            Expected::Closing {
+                delimiter,
+                construct,
+                line,
+                offset,
+            } => Some(format!(
+                "{delimiter} to close {construct} started at {}:{}",
+                line + 1,
+                offset + 1
+            )),
+        }
+    }
+}
+
+/// The error type of all the parsers in this crate.
+///
+/// Out of the alternatives tried by the parser it keeps the one that got
+/// the furthest into the input, which is usually the most helpful one.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SyntaxError<I> {
+    pub(crate) input: I,
+    pub(crate) expected: Expected,
+}
+
+impl<I> SyntaxError<I> {
+    pub(crate) fn new(input: I, expected: Expected) -> Self {
+        Self { input, expected }
+    }
+}
+
+impl<'a, 'b> nom::error::ParseError<Input<'a, 'b>> for SyntaxError<Input<'a, 'b>> {
+    fn from_error_kind(input: Input<'a, 'b>, _: ErrorKind) -> Self {
+        Self::new(input, Expected::Nothing)
+    }
+
+    fn append(_: Input<'a, 'b>, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn or(self, other: Self) -> Self {
+        let further = other.input.len() < self.input.len();
+        let as_far = other.input.len() == self.input.len();
+        if further || (as_far && other.expected != Expected::Nothing) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl<'a, 'b> SyntaxError<Input<'a, 'b>> {
+    /// Converts the error into the public [ParseError].
+    ///
+    /// `tokens` are all the tokens given to the parser, used to locate
+    /// the error if the parser ran out of the input.
+    pub(crate) fn into_parse_error(self, tokens: Input<'a, 'b>) -> ParseError {
+        let token = self.input.first().or(tokens.last()).copied();
+        ParseError::new(token, self.expected)
+    }
+}
+
+/// Makes the errors of `parser` that happen after its first token
+/// unrecoverable.
+///
+/// The parsers in this crate backtrack on errors, so e.g. `opt(expr)`
+/// would silently skip a half-parsed expression and report a confusing
+/// error at its start. Once a parser is past the first token, the input
+/// is malformed and there is nothing else to try.
+pub(crate) fn commit<'a: 'b, 'b, O, P>(
+    mut parser: P,
+) -> impl FnMut(Input<'a, 'b>) -> IResult<Input<'a, 'b>, O>
+where
+    P: Parser<Input<'a, 'b>, O, SyntaxError<Input<'a, 'b>>>,
+{
+    move |tokens: Input<'a, 'b>| match parser.parse(tokens) {
+        Err(nom::Err::Error(err)) if err.input.len() < tokens.len() => Err(nom::Err::Failure(err)),
+        result => result,
+    }
+}
+
+/// Reports the errors of `parser` on its first token as a missing `expected`.
+pub(crate) fn expecting<'a: 'b, 'b, O, P>(
+    expected: Expected,
+    mut parser: P,
+) -> impl FnMut(Input<'a, 'b>) -> IResult<Input<'a, 'b>, O>
+where
+    P: Parser<Input<'a, 'b>, O, SyntaxError<Input<'a, 'b>>>,
+{
+    move |tokens: Input<'a, 'b>| match parser.parse(tokens) {
+        Err(nom::Err::Error(err)) if err.input.len() == tokens.len() => {
+            Err(nom::Err::Error(SyntaxError::new(tokens, expected)))
+        }
+        result => result,
+    }
+}
+
+/// Parses the `delimiter` closing the `construct` that started with `opening`.
+///
+/// A missing closing delimiter is unrecoverable.
+pub(crate) fn closing<'a: 'b, 'b, O, P>(
+    opening: &CommentedToken,
+    delimiter: &'static str,
+    construct: &'static str,
+    mut parser: P,
+) -> impl FnMut(Input<'a, 'b>) -> IResult<Input<'a, 'b>, O>
+where
+    P: Parser<Input<'a, 'b>, O, SyntaxError<Input<'a, 'b>>>,
+{
+    let expected = Expected::Closing {
+        delimiter,
+        construct,
+        line: opening.line,
+        offset: opening.offset,
+    };
+    move |tokens: Input<'a, 'b>| match parser.parse(tokens) {
+        Err(nom::Err::Error(_)) => Err(nom::Err::Failure(SyntaxError::new(tokens, expected))),
+        result => result,
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Newline => "newline".to_string(),
+        Token::EOF => "end of input".to_string(),
+        token => format!("`{}`", token.text()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippets() {
+        let token = CommentedToken::new(Token::RParen, 1, 5).with_end(1, 6, 12..13);
+        let error = ParseError::new(Some(&token), Expected::Expression);
+        assert_eq!(
+            error.with_source("a <- 1\nb <- )\n").to_string(),
+            "2:6: expected an expression, found `)`\nb <- )\n     ^"
+        );
+
+        let token = CommentedToken::new(Token::Symbol("bc"), 0, 2).with_end(0, 4, 3..5);
+        let error = ParseError::new(Some(&token), Expected::Nothing);
+        assert_eq!(
+            error.with_source("\ta\tbc").to_string(),
+            "1:3: unexpected `bc`\n\ta\tbc\n\t \t^^"
+        );
+    }
+}
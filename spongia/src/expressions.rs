@@ -1,11 +1,12 @@
 use log::trace;
 use nom::branch::alt;
+use nom::combinator::flat_map;
 use nom::combinator::map;
 use nom::combinator::opt;
+use nom::combinator::success;
 use nom::multi::many0;
 use nom::sequence::delimited;
 use nom::sequence::tuple;
-use nom::IResult;
 use tokenizer::tokens::CommentedToken;
 use tokenizer::tokens_buffer::TokensBuffer;
 use tokenizer::Token::*;
@@ -15,16 +16,21 @@ use crate::ast::Delimiter;
 use crate::ast::Expression;
 use crate::ast::FunctionCall;
 use crate::ast::TermExpr;
-use crate::compound::delimited_comma_sep_exprs;
 use crate::compound::for_loop_expression;
 use crate::compound::function_def;
 use crate::compound::if_expression;
 use crate::compound::lambda_function;
 use crate::compound::repeat_expression;
 use crate::compound::while_expression;
-use crate::program::statement_or_expr;
+use crate::compound::{call_argument, delimited_comma_sep_exprs};
+use crate::error::closing;
+use crate::error::commit;
+use crate::error::expecting;
+use crate::error::Expected;
+use crate::error::SyntaxError;
+use crate::program::{statements, ParseState};
 use crate::token_parsers::*;
-use crate::Input;
+use crate::{IResult, Input};
 
 fn symbol_expr<'a, 'b: 'a>(tokens: Input<'a, 'b>) -> IResult<Input<'a, 'b>, Expression<'a>> {
     map(symbol, Expression::Symbol)(tokens)
@@ -34,50 +40,63 @@ fn literal_expr<'a, 'b: 'a>(tokens: Input<'a, 'b>) -> IResult<Input<'a, 'b>, Exp
     map(literal, Expression::Literal)(tokens)
 }
 
-pub(crate) fn term_expr<'a, 'b: 'a>(
-    tokens: Input<'a, 'b>,
-) -> IResult<Input<'a, 'b>, Expression<'a>> {
-    trace!("term_expr: {}", TokensBuffer(tokens));
-    alt((
-        for_loop_expression,
-        while_expression,
-        repeat_expression,
-        function_def,
-        lambda_function,
-        if_expression,
-        map(break_token, Expression::Break),
-        map(continue_token, Expression::Continue),
-        map(symbol_expr, |symbol| symbol),
-        map(literal_expr, |literal| literal),
-        map(
-            tuple((
-                lparen,
-                delimited(many0(newline), opt(expr), many0(newline)),
-                rparen,
-            )),
-            |(lparen, term, rparen)| {
-                Expression::Term(Box::new(TermExpr::new(
-                    Some(lparen),
-                    term.map(|t| vec![t]).unwrap_or(vec![]),
-                    Some(rparen),
-                )))
-            },
-        ),
-        map(
-            tuple((
-                lbrace,
-                delimited(many0(newline), many0(statement_or_expr), many0(newline)),
-                rbrace,
-            )),
-            |(lbrace, term, rbrace)| {
-                Expression::Term(Box::new(TermExpr::new(Some(lbrace), term, Some(rbrace))))
-            },
-        ),
-    ))(tokens)
+pub(crate) fn term_expr<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Expression<'a>> + '_ {
+    move |tokens| {
+        trace!("term_expr: {}", TokensBuffer(tokens));
+        alt((
+            for_loop_expression(state),
+            while_expression(state),
+            repeat_expression(state),
+            function_def(state),
+            lambda_function(state),
+            if_expression(state),
+            map(break_token, Expression::Break),
+            map(continue_token, Expression::Continue),
+            map(symbol_expr, |symbol| symbol),
+            map(literal_expr, |literal| literal),
+            map(placeholder, Expression::Placeholder),
+            map(
+                flat_map(lparen, |lparen| {
+                    tuple((
+                        success(lparen),
+                        opt(commit(expr(state))),
+                        closing(lparen, "`)`", "parenthesis", rparen),
+                    ))
+                }),
+                |(lparen, term, rparen)| {
+                    Expression::Term(Box::new(TermExpr::new(
+                        Some(lparen),
+                        term.map(|t| vec![t]).unwrap_or(vec![]),
+                        Some(rparen),
+                    )))
+                },
+            ),
+            map(
+                flat_map(lbrace, |lbrace| {
+                    tuple((
+                        success(lbrace),
+                        delimited(
+                            many0(newline),
+                            statements(state, |token| matches!(token, RBrace | EOF)),
+                            many0(newline),
+                        ),
+                        closing(lbrace, "`}`", "block", rbrace),
+                    ))
+                }),
+                |(lbrace, term, rbrace)| {
+                    Expression::Term(Box::new(TermExpr::new(Some(lbrace), term, Some(rbrace))))
+                },
+            ),
+        ))(tokens)
+    }
 }
 
 #[derive(Debug)]
 enum Tail<'a> {
+    /// `$`, `@`, `::` or `:::` and the name after it
+    Access(&'a CommentedToken<'a>, Expression<'a>),
     Call(Args<'a>),
     DoubleSubset(Args<'a>),
     SingleSubset(Args<'a>),
@@ -87,70 +106,108 @@ fn unary_op<'a, 'b: 'a>(tokens: Input<'a, 'b>) -> IResult<Input<'a, 'b>, &'b Com
     alt((minus, plus, unary_not, tilde, help))(tokens)
 }
 
-fn unary_term<'a, 'b: 'a>(tokens: Input<'a, 'b>) -> IResult<Input<'a, 'b>, Expression<'a>> {
-    alt((
-        map(tuple((tilde, expr)), |(tilde, term)| {
-            Expression::Formula(tilde, Box::new(term))
-        }),
-        map(tuple((unary_op, unary_term)), |(op, term)| {
-            Expression::Unary(op, Box::new(term))
-        }),
-        atomic_term,
-    ))(tokens)
+fn unary_term<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Expression<'a>> + '_ {
+    move |tokens| {
+        let (tokens, op) = match unary_op(tokens) {
+            Ok(parsed) => parsed,
+            Err(nom::Err::Error(_)) => return atomic_term(state)(tokens),
+            Err(err) => return Err(err),
+        };
+        let (tokens, operand) = expecting(Expected::Expression, unary_term(state))(tokens)?;
+        let (tokens, operand) = match tokens {
+            [] => (tokens, operand),
+            _ => ExprParser(unary_precedence(op) + 1, state).parse(operand, tokens)?,
+        };
+        let operand = Box::new(operand);
+        match op.token {
+            Tilde => Ok((tokens, Expression::Formula(op, operand))),
+            _ => Ok((tokens, Expression::Unary(op, operand))),
+        }
+    }
 }
 
-pub(crate) fn atomic_term<'a, 'b: 'a>(
-    tokens: Input<'a, 'b>,
-) -> IResult<Input<'a, 'b>, Expression<'a>> {
-    trace!("atomic_term: {}", TokensBuffer(tokens));
-    let (mut tokens, lhs) = term_expr(tokens)?;
-    let mut acc = lhs;
-    trace!("atomic_term: parsed LHS: {acc}");
-    trace!("atomic_term: parsing rhs: {}", TokensBuffer(tokens));
-    while let Ok((new_tokens, tail)) = alt((
-        map(
-            delimited_comma_sep_exprs(map(lparen, Delimiter::Paren), map(rparen, Delimiter::Paren)),
-            Tail::Call,
-        ),
-        map(
-            delimited_comma_sep_exprs(
-                map(tuple((lbracket, lbracket)), Delimiter::DoubleBracket),
-                map(tuple((rbracket, rbracket)), Delimiter::DoubleBracket),
-            ),
-            Tail::DoubleSubset,
-        ),
-        map(
-            delimited_comma_sep_exprs(
-                map(lbracket, Delimiter::SingleBracket),
-                map(rbracket, Delimiter::SingleBracket),
-            ),
-            Tail::SingleSubset,
-        ),
-    ))(tokens)
-    {
-        trace!("atomic_term: parsed the rhs to this tail: {tail:?}");
-        match tail {
-            Tail::Call(args) => {
-                acc = Expression::FunctionCall(FunctionCall {
-                    function_ref: Box::new(acc),
-                    args,
-                })
-            }
-            Tail::DoubleSubset(args) | Tail::SingleSubset(args) => {
-                acc = Expression::SubsetExpression(crate::ast::SubsetExpression {
-                    object_ref: Box::new(acc),
-                    args,
-                })
+pub(crate) fn atomic_term<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Expression<'a>> + '_ {
+    move |tokens| {
+        trace!("atomic_term: {}", TokensBuffer(tokens));
+        let (mut tokens, lhs) = term_expr(state)(tokens)?;
+        let mut acc = lhs;
+        trace!("atomic_term: parsed LHS: {acc}");
+        trace!("atomic_term: parsing rhs: {}", TokensBuffer(tokens));
+        loop {
+            let (new_tokens, tail) = match alt((
+                // R takes only a name after these, so `a$b(c)` calls `a$b`
+                map(
+                    tuple((
+                        alt((dollar, slot, ns_get_int, ns_get)),
+                        commit(expecting(
+                            Expected::Token("a name"),
+                            alt((symbol_expr, literal_expr)),
+                        )),
+                    )),
+                    |(op, name)| Tail::Access(op, name),
+                ),
+                map(
+                    delimited_comma_sep_exprs(
+                        map(lparen, Delimiter::Paren),
+                        map(rparen, Delimiter::Paren),
+                        call_argument(state),
+                        "call",
+                    ),
+                    Tail::Call,
+                ),
+                map(
+                    delimited_comma_sep_exprs(
+                        map(tuple((lbracket, lbracket)), Delimiter::DoubleBracket),
+                        map(tuple((rbracket, rbracket)), Delimiter::DoubleBracket),
+                        call_argument(state),
+                        "subset",
+                    ),
+                    Tail::DoubleSubset,
+                ),
+                map(
+                    delimited_comma_sep_exprs(
+                        map(lbracket, Delimiter::SingleBracket),
+                        map(rbracket, Delimiter::SingleBracket),
+                        call_argument(state),
+                        "subset",
+                    ),
+                    Tail::SingleSubset,
+                ),
+            ))(tokens)
+            {
+                Ok(parsed) => parsed,
+                Err(nom::Err::Error(_)) => break,
+                Err(err) => return Err(err),
+            };
+            trace!("atomic_term: parsed the rhs to this tail: {tail:?}");
+            match tail {
+                Tail::Access(op, name) => acc = Expression::Bop(op, Box::new(acc), Box::new(name)),
+                Tail::Call(args) => {
+                    acc = Expression::FunctionCall(FunctionCall {
+                        function_ref: Box::new(acc),
+                        args,
+                    })
+                }
+                Tail::DoubleSubset(args) | Tail::SingleSubset(args) => {
+                    acc = Expression::SubsetExpression(crate::ast::SubsetExpression {
+                        object_ref: Box::new(acc),
+                        args,
+                    })
+                }
             }
+            tokens = new_tokens;
         }
-        tokens = new_tokens;
-    }
 
-    trace!("atomic_term: final acc: {acc}");
-    Ok((tokens, acc))
+        trace!("atomic_term: final acc: {acc}");
+        Ok((tokens, acc))
+    }
 }
 
-// Precedence table from https://github.com/SurajGupta/r-source/blob/master/src/main/gram.y
+// Precedence table from https://github.com/wch/r-source/blob/trunk/src/main/gram.y
 // /* This is the precedence table, low to high */
 // %left		'?'
 // %left		LOW WHILE FOR REPEAT
@@ -160,19 +217,24 @@ pub(crate) fn atomic_term<'a, 'b: 'a>(
 // %right		EQ_ASSIGN
 // %left		RIGHT_ASSIGN
 // %left		'~' TILDE
-// %left		OR OR3
-// %left		AND AND3
+// %left		OR OR2
+// %left		AND AND2
 // %left		UNOT NOT
 // %nonassoc   	GT GE LT LE EQ NE
 // %left		'+' '-'
 // %left		'*' '/'
-// %left		SPECIAL
+// %left		SPECIAL PIPE
+// %left		PIPEBIND
 // %left		':'
 // %left		UMINUS UPLUS
 // %right		'^'
 // %left		'$' '@'
 // %left		NS_GET NS_GET_INT
 // %nonassoc	'(' '[' LBB
+//
+// `=` is the exception. R only accepts it as an operator in the top-level
+// `expr_or_assign_or_help` rule, where it takes a whole `expr` on its left,
+// so `x <- y = 5` is `(x <- y) = 5`. Here it binds looser than `<-`.
 
 #[derive(Debug, Clone, PartialEq)]
 enum Associativity {
@@ -183,44 +245,54 @@ enum Associativity {
 
 fn associativity(token: &CommentedToken) -> Associativity {
     match &token.token {
-        Help | RAssign | Tilde | Or | VectorizedOr | And | VectorizedAnd | NotEqual | Plus
-        | Minus | Multiply | Divide | Colon | Dollar | Slot | NsGet | NsGetInt => {
-            Associativity::Left
+        LAssign | SuperAssign | ColonAssign | OldAssign | Power => Associativity::Right,
+        GreaterThan | GreaterEqual | LowerThan | LowerEqual | Equal | NotEqual => {
+            Associativity::Non
         }
-        LAssign | OldAssign | Power => Associativity::Right,
-
-        _ => Associativity::Non,
+        _ => Associativity::Left,
     }
 }
 
 fn precedence(token: &CommentedToken) -> u8 {
     match &token.token {
         Help => 1,
-        LAssign => 5,
-        SuperAssign => 5,
-        ColonAssign => 5,
-        OldAssign => 6,
-        RAssign => 7,
-        Pipe => 8,
-        Tilde => 8,
-        Or | VectorizedOr => 9,
-        And | VectorizedAnd => 10,
-        GreaterThan | GreaterEqual | LowerThan | LowerEqual | Equal | NotEqual => 12,
-        Plus | Minus => 13,
-        Multiply | Divide => 14,
-        Special(_) => 15,
-        Colon => 16,
-        Power => 18,
-        Dollar | Slot => 19,
-        NsGet | NsGetInt => 20,
+        OldAssign => 2,
+        LAssign | SuperAssign | ColonAssign => 3,
+        RAssign | SuperRAssign => 4,
+        Tilde => 5,
+        Or | VectorizedOr => 6,
+        And | VectorizedAnd => 7,
+        // UNOT => 8
+        GreaterThan | GreaterEqual | LowerThan | LowerEqual | Equal | NotEqual => 9,
+        Plus | Minus => 10,
+        Multiply | Divide => 11,
+        Special(_) | Modulo | Pipe => 12,
+        Colon => 13,
+        // UMINUS => 14
+        Power => 15,
+        Dollar | Slot => 16,
+        NsGet | NsGetInt => 17,
         _ => panic!("{token:?} is not a binary operator"),
     }
 }
 
+/// The precedence of a prefix operator. Its operand takes
+/// all the binary operators that bind tighter.
+fn unary_precedence(token: &CommentedToken) -> u8 {
+    match &token.token {
+        Help => 1,
+        Tilde => 5,
+        UnaryNot => 8,
+        Minus | Plus => 14,
+        _ => panic!("{token:?} is not a unary operator"),
+    }
+}
+
 fn is_binary_operator(token: &CommentedToken) -> bool {
     matches!(
         &token.token,
         Help | RAssign
+            | SuperRAssign
             | Tilde
             | Or
             | VectorizedOr
@@ -247,15 +319,16 @@ fn is_binary_operator(token: &CommentedToken) -> bool {
             | OldAssign
             | Power
             | Pipe
+            | Modulo
             | Special(_)
     )
 }
 
 // This implements the precedence climbing method described here:
 // https://www.engr.mun.ca/~theo/Misc/exp_parsing.htm#climbing
-struct ExprParser(u8);
+struct ExprParser<'s>(u8, &'s ParseState);
 
-impl ExprParser {
+impl ExprParser<'_> {
     fn parse<'a, 'b: 'a>(
         &self,
         mut lhs: Expression<'a>,
@@ -265,12 +338,9 @@ impl ExprParser {
         let mut lookahead = &tokens[0];
         while is_binary_operator(lookahead) && precedence(lookahead) >= self.0 {
             let op = lookahead;
-            let mut it = 1;
-            while matches!(tokens[it].token, Newline) {
-                it += 1;
-            }
-            tokens = &tokens[it..];
-            let (new_tokens, mut rhs) = unary_term(tokens)?;
+            tokens = &tokens[1..];
+            let (new_tokens, mut rhs) =
+                expecting(Expected::Expression, unary_term(self.1))(tokens)?;
             tokens = new_tokens;
             lookahead = &tokens[0];
             while is_binary_operator(lookahead)
@@ -284,13 +354,23 @@ impl ExprParser {
                     } else {
                         0
                     });
-                let parser = ExprParser(q);
+                let parser = ExprParser(q, self.1);
                 let (new_tokens, new_rhs) = parser.parse(rhs, tokens)?;
                 rhs = new_rhs;
                 tokens = new_tokens;
                 lookahead = &tokens[0];
             }
             lhs = Expression::Bop(op, Box::new(lhs), Box::new(rhs));
+            // `a == b == c` is a syntax error in R
+            if associativity(op) == Associativity::Non
+                && is_binary_operator(lookahead)
+                && precedence(lookahead) == precedence(op)
+            {
+                return Err(nom::Err::Failure(SyntaxError::new(
+                    tokens,
+                    Expected::Nothing,
+                )));
+            }
         }
         trace!("ExprParser::parse: LHS {lhs}");
         trace!("ExprParser::parse: tokens left: {}", TokensBuffer(tokens));
@@ -298,14 +378,18 @@ impl ExprParser {
     }
 }
 
-pub(crate) fn expr<'a, 'b: 'a>(tokens: Input<'a, 'b>) -> IResult<Input<'a, 'b>, Expression<'a>> {
-    trace!("expr: {}", TokensBuffer(tokens));
-    let (tokens, term) = unary_term(tokens)?;
-    if !tokens.is_empty() {
-        let parser = ExprParser(0);
-        parser.parse(term, tokens)
-    } else {
-        Ok((tokens, term))
+pub(crate) fn expr<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Expression<'a>> + '_ {
+    move |tokens| {
+        trace!("expr: {}", TokensBuffer(tokens));
+        let (tokens, term) = expecting(Expected::Expression, unary_term(state))(tokens)?;
+        if !tokens.is_empty() {
+            let parser = ExprParser(0, state);
+            parser.parse(term, tokens)
+        } else {
+            Ok((tokens, term))
+        }
     }
 }
 
@@ -345,6 +429,11 @@ mod tests {
             OldAssign,
             Power,
             Special("%>%"),
+            SuperAssign,
+            ColonAssign,
+            SuperRAssign,
+            Modulo,
+            Pipe,
         ]
     }
 
@@ -369,7 +458,7 @@ mod tests {
         for token in binary_op_tokens() {
             let tokens_ = commented_tokens!(Literal("1"), token, Literal("1"), EOF);
             let tokens: Vec<_> = tokens_.iter().collect();
-            let res = expr(&tokens).unwrap().1;
+            let res = expr(&ParseState::default())(&tokens).unwrap().1;
             assert_eq!(
                 res,
                 Expression::Bop(
@@ -386,7 +475,7 @@ mod tests {
         let tokens_ =
             commented_tokens!(Literal("1"), Power, Literal("2"), Power, Literal("3"), EOF);
         let tokens: Vec<_> = tokens_.iter().collect();
-        let res = expr(&tokens).unwrap().1;
+        let res = expr(&ParseState::default())(&tokens).unwrap().1;
         assert_eq!(
             res,
             Expression::Bop(
@@ -405,7 +494,7 @@ mod tests {
     fn left_associative_bop() {
         let tokens_ = commented_tokens!(Literal("1"), Plus, Literal("2"), Plus, Literal("3"), EOF);
         let tokens: Vec<_> = tokens_.iter().collect();
-        let res = expr(&tokens).unwrap().1;
+        let res = expr(&ParseState::default())(&tokens).unwrap().1;
         assert_eq!(
             res,
             Expression::Bop(
@@ -431,7 +520,7 @@ mod tests {
             EOF
         );
         let tokens: Vec<_> = tokens_.iter().collect();
-        let res = expr(&tokens).unwrap().1;
+        let res = expr(&ParseState::default())(&tokens).unwrap().1;
         assert_eq!(
             res,
             Expression::Bop(
@@ -450,7 +539,7 @@ mod tests {
     fn double_brace() {
         let tokens_ = commented_tokens!(LBrace, LBrace, Literal("1"), RBrace, RBrace);
         let tokens: Vec<_> = tokens_.iter().collect();
-        let res = expr(&tokens).unwrap().1;
+        let res = expr(&ParseState::default())(&tokens).unwrap().1;
         assert_eq!(
             res,
             Expression::Term(Box::new(TermExpr {
@@ -1,13 +1,21 @@
 pub mod ast;
 pub(crate) mod compound;
+pub mod error;
 pub(crate) mod expressions;
+pub(crate) mod parse_data;
+pub use parse_data::{parse_data, parse_data_to_csv, ParseData};
 pub mod parser;
+pub(crate) mod placeholder;
+pub use error::ParseError;
 pub(crate) mod pre_parsing_hooks;
-pub use parser::parse;
+pub use parser::{parse, parse_with_recovery};
 pub use pre_parsing_hooks::pre_parse;
+pub(crate) mod printer;
+pub use printer::print;
 use tokenizer::tokens::CommentedToken;
 pub(crate) mod program;
 pub(crate) mod token_parsers;
 pub(crate) mod whitespace;
 
 type Input<'a, 'b> = &'b [&'a CommentedToken<'a>];
+type IResult<I, O> = nom::IResult<I, O, error::SyntaxError<I>>;
@@ -0,0 +1,613 @@
+use std::ops::Range;
+
+use tokenizer::{line_index::LineIndex, tokens::CommentedToken, Token};
+
+use crate::ast::{
+    Arg, Args, CallArgument, Delimiter, ElseIfConditional, Expression, FormalParameter,
+    IfConditional, TrailingElse,
+};
+
+/// A row of the table returned by R's `utils::getParseData`.
+///
+/// Lines and columns are counted from one and the end column is inclusive.
+/// A column counts characters and a tab moves it to the next multiple of
+/// eight, the way R counts them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseData<'a> {
+    pub line1: usize,
+    pub col1: usize,
+    pub line2: usize,
+    pub col2: usize,
+    pub id: usize,
+    /// The id of the enclosing expression, `0` for the top-level expressions.
+    /// A comment outside of all the expressions has minus the id of the next
+    /// top-level expression, or `0` if there is none.
+    pub parent: isize,
+    /// R's name of the token, e.g. `SYMBOL_FUNCTION_CALL`, `LEFT_ASSIGN`,
+    /// `'('` or `expr`.
+    pub token: &'static str,
+    pub terminal: bool,
+    /// The source of a terminal. Empty for the expressions.
+    pub text: &'a str,
+}
+
+/// Returns the parse data of a program in the format of R's `utils::getParseData`.
+///
+/// `source` is the program the expressions were parsed from. The rows are
+/// ordered like R orders them: by their start, and an expression before the
+/// rows it encloses. The ids are unique, but they are not the ids R gives,
+/// which depend on the order in which R's parser builds the expressions.
+pub fn parse_data<'a>(expressions: &[Expression<'a>], source: &'a str) -> Vec<ParseData<'a>> {
+    let mut table = Table {
+        source,
+        rows: vec![],
+        comments: vec![],
+    };
+    for expression in expressions {
+        table.statement(0, expression);
+    }
+    table.into_parse_data()
+}
+
+/// Writes parse data as CSV, the way R's `write.csv(data, row.names = FALSE)` does.
+pub fn parse_data_to_csv(rows: &[ParseData]) -> String {
+    let quote = |text: &str| format!("\"{}\"", text.replace('"', "\"\""));
+    let mut csv = String::from(
+        "\"line1\",\"col1\",\"line2\",\"col2\",\"id\",\"parent\",\"token\",\"terminal\",\"text\"\n",
+    );
+    for row in rows {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            row.line1,
+            row.col1,
+            row.line2,
+            row.col2,
+            row.id,
+            row.parent,
+            quote(row.token),
+            if row.terminal { "TRUE" } else { "FALSE" },
+            quote(row.text)
+        ));
+    }
+    csv
+}
+
+struct Row {
+    parent: usize,
+    token: &'static str,
+    terminal: bool,
+    /// The bytes of the row in the source.
+    bytes: Range<usize>,
+}
+
+/// Parse data with byte ranges instead of lines and columns. The id of a row
+/// is its index plus one.
+struct Table<'a> {
+    source: &'a str,
+    rows: Vec<Row>,
+    /// The bytes of the comments attached to the tokens.
+    comments: Vec<Range<usize>>,
+}
+
+impl<'a> Table<'a> {
+    /// Adds an expression named `token` enclosing the rows added by `children`.
+    fn node(
+        &mut self,
+        parent: usize,
+        token: &'static str,
+        children: impl FnOnce(&mut Self, usize),
+    ) {
+        let index = self.rows.len();
+        self.rows.push(Row {
+            parent,
+            token,
+            terminal: false,
+            bytes: 0..0,
+        });
+        children(self, index + 1);
+        let children = self.rows[index + 1..]
+            .iter()
+            .filter(|row| row.parent == index + 1)
+            .map(|row| row.bytes.clone());
+        let start = children.clone().map(|bytes| bytes.start).min();
+        let end = children.map(|bytes| bytes.end).max();
+        if let (Some(start), Some(end)) = (start, end) {
+            self.rows[index].bytes = start..end;
+        }
+    }
+
+    fn expr(&mut self, parent: usize, children: impl FnOnce(&mut Self, usize)) {
+        self.node(parent, "expr", children)
+    }
+
+    fn terminal(&mut self, parent: usize, token: &CommentedToken, name: &'static str) {
+        self.comments_of(token);
+        self.rows.push(Row {
+            parent,
+            token: name,
+            terminal: true,
+            bytes: token.span.clone(),
+        });
+    }
+
+    /// Adds a terminal named after its token.
+    fn token(&mut self, parent: usize, token: &CommentedToken) {
+        self.terminal(parent, token, token_name(&token.token))
+    }
+
+    /// Records the positions of the comments attached to `token`.
+    fn comments_of(&mut self, token: &CommentedToken) {
+        let Some(span) = &token.comments_span else {
+            return;
+        };
+        // Only whitespace separates the leading comments
+        let mut start = span.bytes.start;
+        for comment in token.leading_comments.iter().flatten() {
+            let found = self
+                .source
+                .get(start..token.span.start)
+                .and_then(|layout| layout.find(comment));
+            if let (false, Some(found)) = (comment.is_empty(), found) {
+                self.comments
+                    .push(start + found..start + found + comment.len());
+                start += found + comment.len();
+            }
+        }
+        if let Some(comment) = token.inline_comment {
+            self.comments
+                .push(span.bytes.end - comment.len()..span.bytes.end);
+        }
+    }
+
+    fn expression(&mut self, parent: usize, expression: &Expression) {
+        match expression {
+            Expression::Symbol(token) => {
+                self.expr(parent, |table, id| table.terminal(id, token, "SYMBOL"))
+            }
+            Expression::Literal(token)
+            | Expression::Placeholder(token)
+            | Expression::Break(token)
+            | Expression::Continue(token) => self.expr(parent, |table, id| table.token(id, token)),
+            Expression::Comment(token) => self.comments.push(token.span.clone()),
+            Expression::Term(term) => match term.pre_delimiters {
+                Some(left) => self.expr(parent, |table, id| {
+                    table.token(id, left);
+                    for statement in &term.term {
+                        match left.token {
+                            Token::LBrace => table.statement(id, statement),
+                            _ => table.expression(id, statement),
+                        }
+                    }
+                    if let Some(right) = term.post_delimiters {
+                        table.token(id, right);
+                    }
+                }),
+                None => {
+                    for statement in &term.term {
+                        self.statement(parent, statement);
+                    }
+                }
+            },
+            Expression::Unary(op, operand) | Expression::Formula(op, operand) => {
+                self.expr(parent, |table, id| {
+                    table.token(id, op);
+                    table.expression(id, operand);
+                })
+            }
+            Expression::Bop(op, lhs, rhs) => self.binary_operation(parent, op, lhs, rhs, "SYMBOL"),
+            Expression::Semicolon(token) => self.token(parent, token),
+            Expression::Newline(token) | Expression::EOF(token) => self.comments_of(token),
+            Expression::Whitespace(tokens) | Expression::Error(tokens) => {
+                tokens.iter().for_each(|token| self.comments_of(token))
+            }
+            Expression::FunctionDef(function_def) => self.expr(parent, |table, id| {
+                table.token(id, function_def.keyword);
+                table.parameters(id, &function_def.arguments);
+                table.expression(id, &function_def.body);
+            }),
+            Expression::LambdaFunction(lambda) => self.expr(parent, |table, id| {
+                table.token(id, lambda.keyword);
+                table.parameters(id, &lambda.args);
+                table.expression(id, &lambda.body);
+            }),
+            Expression::IfExpression(if_expression) => self.if_conditional(
+                parent,
+                &if_expression.if_conditional,
+                &if_expression.else_ifs,
+                if_expression.trailing_else.as_ref(),
+            ),
+            Expression::WhileExpression(while_expression) => self.expr(parent, |table, id| {
+                table.token(id, while_expression.while_keyword);
+                table.condition(id, &while_expression.condition);
+                table.expression(id, &while_expression.body);
+            }),
+            Expression::RepeatExpression(repeat_expression) => self.expr(parent, |table, id| {
+                table.token(id, repeat_expression.repeat_keyword);
+                table.expression(id, &repeat_expression.body);
+            }),
+            Expression::FunctionCall(function_call) => self.expr(parent, |table, id| {
+                table.function_ref(id, &function_call.function_ref);
+                table.arguments(id, &function_call.args);
+            }),
+            Expression::SubsetExpression(subset_expression) => self.expr(parent, |table, id| {
+                table.expression(id, &subset_expression.object_ref);
+                table.arguments(id, &subset_expression.args);
+            }),
+            Expression::ForLoopExpression(for_loop) => self.expr(parent, |table, id| {
+                table.token(id, for_loop.keyword);
+                table.node(id, "forcond", |table, id| {
+                    table.delimiter(id, &for_loop.left_delim);
+                    match &*for_loop.identifier {
+                        Expression::Symbol(token) => table.terminal(id, token, "SYMBOL"),
+                        identifier => table.expression(id, identifier),
+                    }
+                    table.token(id, for_loop.in_keyword);
+                    table.expression(id, &for_loop.collection);
+                    table.delimiter(id, &for_loop.right_delim);
+                });
+                table.expression(id, &for_loop.body);
+            }),
+        }
+    }
+
+    /// Adds a statement of a program or a block. R names an assignment with `=`
+    /// `expr_or_assign_or_help` there, and `EQ_ASSIGN` anywhere else, e.g.
+    /// in parentheses or in the body of an `if`.
+    fn statement(&mut self, parent: usize, statement: &Expression) {
+        match statement {
+            Expression::Bop(op, lhs, rhs) if op.token == Token::OldAssign => {
+                self.node(parent, "expr_or_assign_or_help", |table, id| {
+                    table.expression(id, lhs);
+                    table.token(id, op);
+                    // `a = b = 1` is a chain of statement-level assignments
+                    table.statement(id, rhs);
+                })
+            }
+            statement => self.expression(parent, statement),
+        }
+    }
+
+    /// Adds a binary operation. `symbol` is the name of the symbol on the
+    /// right-hand side of `::` and `:::`, which depends on whether it is called.
+    fn binary_operation(
+        &mut self,
+        parent: usize,
+        op: &CommentedToken,
+        lhs: &Expression,
+        rhs: &Expression,
+        symbol: &'static str,
+    ) {
+        // See [Table::statement] for the assignments with `=` in statements
+        let name = match op.token {
+            Token::OldAssign => "EQ_ASSIGN",
+            _ => "expr",
+        };
+        self.node(parent, name, |table, id| match (&op.token, lhs, rhs) {
+            (Token::Dollar | Token::Slot, lhs, Expression::Symbol(field)) => {
+                table.expression(id, lhs);
+                table.token(id, op);
+                let field_name = match op.token {
+                    Token::Slot => "SLOT",
+                    _ => "SYMBOL",
+                };
+                table.terminal(id, field, field_name);
+            }
+            (Token::Dollar | Token::Slot, lhs, Expression::Literal(field)) => {
+                table.expression(id, lhs);
+                table.token(id, op);
+                table.token(id, field);
+            }
+            (Token::NsGet | Token::NsGetInt, Expression::Symbol(package), rhs) => {
+                table.terminal(id, package, "SYMBOL_PACKAGE");
+                table.token(id, op);
+                match rhs {
+                    Expression::Symbol(name) => table.terminal(id, name, symbol),
+                    Expression::Literal(name) => table.token(id, name),
+                    rhs => table.expression(id, rhs),
+                }
+            }
+            (_, lhs, rhs) => {
+                table.expression(id, lhs);
+                table.token(id, op);
+                table.expression(id, rhs);
+            }
+        })
+    }
+
+    /// Adds the function of a call, with the called symbol named `SYMBOL_FUNCTION_CALL`.
+    fn function_ref(&mut self, parent: usize, function_ref: &Expression) {
+        match function_ref {
+            Expression::Symbol(token) => self.expr(parent, |table, id| {
+                table.terminal(id, token, "SYMBOL_FUNCTION_CALL")
+            }),
+            Expression::Bop(op, lhs, rhs) if matches!(op.token, Token::NsGet | Token::NsGetInt) => {
+                self.binary_operation(parent, op, lhs, rhs, "SYMBOL_FUNCTION_CALL")
+            }
+            function_ref => self.expression(parent, function_ref),
+        }
+    }
+
+    /// Adds `if` and the `else if`s and `else` after it. R nests every
+    /// `else if` in the `else` of the previous `if`.
+    fn if_conditional(
+        &mut self,
+        parent: usize,
+        if_conditional: &IfConditional,
+        else_ifs: &[ElseIfConditional],
+        trailing_else: Option<&TrailingElse>,
+    ) {
+        self.expr(parent, |table, id| {
+            table.token(id, if_conditional.keyword);
+            table.token(id, if_conditional.left_delimiter);
+            table.expression(id, &if_conditional.condition);
+            table.token(id, if_conditional.right_delimiter);
+            table.expression(id, &if_conditional.body);
+            match else_ifs.split_first() {
+                Some((else_if, else_ifs)) => {
+                    table.token(id, else_if.else_keyword);
+                    table.if_conditional(id, &else_if.if_conditional, else_ifs, trailing_else);
+                }
+                None => {
+                    if let Some(trailing_else) = trailing_else {
+                        table.token(id, trailing_else.else_keyword);
+                        table.expression(id, &trailing_else.body);
+                    }
+                }
+            }
+        })
+    }
+
+    /// Adds the parenthesised condition of `while`. R does not wrap the
+    /// parentheses in an expression.
+    fn condition(&mut self, parent: usize, condition: &Expression) {
+        match condition {
+            Expression::Term(term) if term.pre_delimiters.is_some() => {
+                if let Some(left) = term.pre_delimiters {
+                    self.token(parent, left);
+                }
+                for statement in &term.term {
+                    self.expression(parent, statement);
+                }
+                if let Some(right) = term.post_delimiters {
+                    self.token(parent, right);
+                }
+            }
+            condition => self.expression(parent, condition),
+        }
+    }
+
+    fn delimiter(&mut self, parent: usize, delimiter: &Delimiter) {
+        match delimiter {
+            Delimiter::Paren(token) | Delimiter::SingleBracket(token) => self.token(parent, token),
+            Delimiter::DoubleBracket((first, second)) if first.token == Token::LBracket => {
+                // R has a single token for `[[`, but two for `]]`
+                self.comments_of(first);
+                self.comments_of(second);
+                self.rows.push(Row {
+                    parent,
+                    token: "LBB",
+                    terminal: true,
+                    bytes: first.span.start..second.span.end,
+                });
+            }
+            Delimiter::DoubleBracket((first, second)) => {
+                self.token(parent, first);
+                self.token(parent, second);
+            }
+        }
+    }
+
+    fn arguments(&mut self, parent: usize, args: &Args<CallArgument>) {
+        self.delimiter(parent, &args.left_delimeter);
+        for Arg(argument, comma) in &args.args {
+            if let Some(argument) = argument {
+                if let Some((name, equal_sign)) = argument.name {
+                    let name_token = match name.token {
+                        Token::Symbol(_) | Token::Dots | Token::DotDotN(_) => "SYMBOL_SUB",
+                        _ => token_name(&name.token),
+                    };
+                    self.terminal(parent, name, name_token);
+                    self.terminal(parent, equal_sign, "EQ_SUB");
+                }
+                if let Some(value) = &argument.value {
+                    self.expression(parent, value);
+                }
+            }
+            self.comma(parent, comma);
+        }
+        self.delimiter(parent, &args.right_delimeter);
+    }
+
+    fn parameters(&mut self, parent: usize, args: &Args<FormalParameter>) {
+        self.delimiter(parent, &args.left_delimeter);
+        for Arg(parameter, comma) in &args.args {
+            if let Some(parameter) = parameter {
+                self.terminal(parent, parameter.name, "SYMBOL_FORMALS");
+                if let Some((equal_sign, default)) = &parameter.default {
+                    self.terminal(parent, equal_sign, "EQ_FORMALS");
+                    self.expression(parent, default);
+                }
+            }
+            self.comma(parent, comma);
+        }
+        self.delimiter(parent, &args.right_delimeter);
+    }
+
+    fn comma(&mut self, parent: usize, comma: &Option<Expression>) {
+        if let Some(Expression::Literal(comma)) = comma {
+            self.token(parent, comma);
+        }
+    }
+
+    fn into_parse_data(self) -> Vec<ParseData<'a>> {
+        let Table {
+            source,
+            rows,
+            comments,
+        } = self;
+        let top_level: Vec<_> = rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| row.parent == 0 && !row.terminal)
+            .map(|(index, row)| (index + 1, row.bytes.start))
+            .collect();
+        let comment_parent = |comment: &Range<usize>| -> isize {
+            let enclosing = rows
+                .iter()
+                .enumerate()
+                .filter(|(_, row)| {
+                    !row.terminal
+                        && row.bytes.start <= comment.start
+                        && comment.end <= row.bytes.end
+                })
+                .min_by_key(|(_, row)| row.bytes.len());
+            match enclosing {
+                Some((index, _)) => (index + 1) as isize,
+                None => top_level
+                    .iter()
+                    .find(|(_, start)| *start >= comment.end)
+                    .map_or(0, |(id, _)| -(*id as isize)),
+            }
+        };
+
+        let line_index = LineIndex::new(source);
+        let mut data: Vec<_> = rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                (
+                    index + 1,
+                    row.parent as isize,
+                    row.token,
+                    row.terminal,
+                    &row.bytes,
+                )
+            })
+            .chain(comments.iter().enumerate().map(|(index, comment)| {
+                (
+                    rows.len() + index + 1,
+                    comment_parent(comment),
+                    "COMMENT",
+                    true,
+                    comment,
+                )
+            }))
+            .filter(|(_, _, _, _, bytes)| !bytes.is_empty())
+            .map(|(id, parent, token, terminal, bytes)| {
+                let (line1, col1) = position(&line_index, source, bytes.start);
+                let last_char = source[..bytes.end]
+                    .char_indices()
+                    .next_back()
+                    .map_or(bytes.start, |(offset, _)| offset);
+                let (line2, col2) = position(&line_index, source, last_char);
+                ParseData {
+                    line1,
+                    col1,
+                    line2,
+                    col2,
+                    id,
+                    parent,
+                    token,
+                    terminal,
+                    text: if terminal { &source[bytes.clone()] } else { "" },
+                }
+            })
+            .collect();
+        data.sort_by_key(|row| {
+            (
+                row.line1,
+                row.col1,
+                std::cmp::Reverse((row.line2, row.col2)),
+                row.terminal,
+            )
+        });
+        data
+    }
+}
+
+/// Returns the line and the column of the character starting at the byte
+/// `offset`, as R counts them.
+fn position(line_index: &LineIndex, source: &str, offset: usize) -> (usize, usize) {
+    let (line, _) = line_index.line_col(offset).unwrap_or_default();
+    let line_start = line_index.offset(line, 0).unwrap_or_default();
+    let character = source[offset..].chars().next().map_or(0, char::len_utf8);
+    let column = source[line_start..offset + character]
+        .chars()
+        .fold(0, |column, character| match character {
+            '\t' => (column + 8) & !7,
+            _ => column + 1,
+        });
+    (line as usize + 1, column)
+}
+
+/// Returns R's name of a terminal that does not depend on where it is.
+fn token_name(token: &Token) -> &'static str {
+    match token {
+        Token::Symbol(_) | Token::Dots | Token::DotDotN(_) | Token::Stop => "SYMBOL",
+        Token::Literal(text) if text.starts_with(['"', '\'', 'r', 'R']) => "STR_CONST",
+        Token::Literal(_)
+        | Token::Na
+        | Token::NaInteger
+        | Token::NaReal
+        | Token::NaCharacter
+        | Token::Inf
+        | Token::NaN => "NUM_CONST",
+        Token::Null => "NULL_CONST",
+        Token::Placeholder => "PLACEHOLDER",
+        Token::Semicolon => "';'",
+        Token::Newline => "'\\n'",
+        Token::LParen => "'('",
+        Token::RParen => "')'",
+        Token::LBrace => "'{'",
+        Token::RBrace => "'}'",
+        Token::LBracket => "'['",
+        Token::RBracket => "']'",
+        Token::Comma => "','",
+        Token::Continue => "NEXT",
+        Token::Break => "BREAK",
+        Token::If => "IF",
+        Token::Else => "ELSE",
+        Token::While => "WHILE",
+        Token::For => "FOR",
+        Token::Repeat => "REPEAT",
+        Token::In => "IN",
+        Token::Function => "FUNCTION",
+        Token::Lambda => "'\\\\'",
+        Token::LAssign | Token::SuperAssign | Token::ColonAssign => "LEFT_ASSIGN",
+        Token::RAssign | Token::SuperRAssign => "RIGHT_ASSIGN",
+        Token::OldAssign => "EQ_ASSIGN",
+        Token::Equal => "EQ",
+        Token::NotEqual => "NE",
+        Token::LowerThan => "LT",
+        Token::GreaterThan => "GT",
+        Token::LowerEqual => "LE",
+        Token::GreaterEqual => "GE",
+        Token::Power => "'^'",
+        Token::Divide => "'/'",
+        Token::Multiply => "'*'",
+        Token::Minus => "'-'",
+        Token::Plus => "'+'",
+        Token::Help => "'?'",
+        Token::And => "AND2",
+        Token::VectorizedAnd => "AND",
+        Token::Or => "OR2",
+        Token::VectorizedOr => "OR",
+        Token::Dollar => "'$'",
+        Token::Pipe => "PIPE",
+        Token::Modulo | Token::Special(_) => "SPECIAL",
+        Token::NsGet => "NS_GET",
+        Token::NsGetInt => "NS_GET_INT",
+        Token::Tilde => "'~'",
+        Token::Colon => "':'",
+        Token::Slot => "'@'",
+        Token::UnaryNot => "'!'",
+        Token::InlineComment(_)
+        | Token::Comment(_)
+        | Token::RoxygenComment(_)
+        | Token::SectionComment(_)
+        | Token::ChunkOptionComment(_)
+        | Token::KnitrOutputComment(_) => "COMMENT",
+        Token::Whitespace(_) => "WHITESPACE",
+        Token::EOF => "END_OF_INPUT",
+    }
+}
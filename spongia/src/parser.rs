@@ -2,10 +2,30 @@ use log::trace;
 use tokenizer::{tokens::CommentedToken, tokens_buffer::TokensBuffer};
 
 use crate::ast::Expression;
+use crate::error::ParseError;
+use crate::placeholder::check_placeholders;
+use crate::program::{program, ParseState};
 
+/// Parses the tokens returned by [crate::pre_parse] into a list of expressions.
+///
+/// # Errors
+///
+/// Returns a [ParseError] pointing at the first token that does not fit
+/// the R grammar, or at a pipe placeholder `_` used where R does not allow it. Use [ParseError::with_source] to get a snippet of the source.
 pub fn parse<'a, 'b: 'a>(
     tokens: &'b [&'a CommentedToken<'a>],
-) -> Result<Vec<Expression<'a>>, String> {
+) -> Result<Vec<Expression<'a>>, ParseError> {
+    let expressions = parse_program(tokens, &ParseState::default())?;
+    match check_placeholders(&expressions).into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(expressions),
+    }
+}
+
+fn parse_program<'a, 'b: 'a>(
+    tokens: &'b [&'a CommentedToken<'a>],
+    state: &ParseState,
+) -> Result<Vec<Expression<'a>>, ParseError> {
     let mut expressions = vec![];
     let mut remaining_tokens = tokens;
 
@@ -14,12 +34,39 @@ pub fn parse<'a, 'b: 'a>(
             "Main parse function, remaining tokens: {}",
             TokensBuffer(remaining_tokens)
         );
-        let (new_remaining_tokens, mut expr) = crate::program::program(remaining_tokens)
-            .map_err(|err| format!("Could not parse: {:?}", err))?;
+        let (new_remaining_tokens, mut expr) =
+            program(state)(remaining_tokens).map_err(|err| match err {
+                nom::Err::Error(err) | nom::Err::Failure(err) => err.into_parse_error(tokens),
+                nom::Err::Incomplete(_) => unreachable!("the parsers are not streaming"),
+            })?;
         expressions.append(&mut expr);
         trace!("New remaining tokens: {}", TokensBuffer(remaining_tokens));
         remaining_tokens = new_remaining_tokens;
     }
-
     Ok(expressions)
 }
+
+/// Parses the tokens like [parse], but does not give up on syntax errors.
+///
+/// The top-level and block statements that fail to parse become
+/// [Expression::Error] nodes holding their tokens, so the rest of the
+/// program can still be formatted. Returns the errors recovered from
+/// along with the expressions.
+pub fn parse_with_recovery<'a, 'b: 'a>(
+    tokens: &'b [&'a CommentedToken<'a>],
+) -> (Vec<Expression<'a>>, Vec<ParseError>) {
+    let state = ParseState::recovering();
+    let result = parse_program(tokens, &state);
+    let mut errors = state.into_recovered_errors();
+    match result {
+        Ok(expressions) => {
+            errors.extend(check_placeholders(&expressions));
+            (expressions, errors)
+        }
+        // Only happens for the input without the EOF token
+        Err(err) => {
+            errors.push(err);
+            (vec![Expression::Error(tokens)], errors)
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Checks the uses of the native pipe placeholder `_`.
+//!
+//! Since R 4.2 the placeholder can pass the left-hand side of `|>` as a named
+//! argument of the call on its right-hand side, `x |> f(y = _)`. Since R 4.3
+//! it can also start a chain of extractions there, `x |> _$a[[1]]`. It may
+//! appear only once and nowhere else.
+
+use tokenizer::{tokens::CommentedToken, Token};
+
+use crate::ast::{walk_expression, Expression, Visitor};
+use crate::error::{ParseError, PlaceholderMisuse};
+
+/// Returns the errors for all the invalid uses of the placeholder in
+/// `expressions`, in the order they appear in.
+pub(crate) fn check_placeholders(expressions: &[Expression]) -> Vec<ParseError> {
+    let mut checker = PlaceholderChecker::default();
+    for expression in expressions {
+        checker.visit_expression(expression);
+    }
+    checker.errors.sort_by_key(|error| error.span.start);
+    checker.errors
+}
+
+#[derive(Default)]
+struct PlaceholderChecker<'a> {
+    /// The placeholder allowed by the innermost pipe being visited.
+    allowed: Option<&'a CommentedToken<'a>>,
+    /// The placeholders reported in `errors`.
+    reported: Vec<&'a CommentedToken<'a>>,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> PlaceholderChecker<'a> {
+    /// Reports `token`, unless it has been reported already.
+    fn fail(&mut self, token: &'a CommentedToken<'a>, misuse: PlaceholderMisuse) {
+        if !self
+            .reported
+            .iter()
+            .any(|reported| std::ptr::eq(*reported, token))
+        {
+            self.reported.push(token);
+            self.errors.push(ParseError::placeholder(token, misuse));
+        }
+    }
+
+    fn visit_pipe_rhs(&mut self, rhs: &Expression<'a>) {
+        let mut allowed = None;
+        match rhs {
+            Expression::FunctionCall(call) => {
+                let placeholders = call
+                    .args
+                    .args
+                    .iter()
+                    .filter_map(|arg| arg.0.as_ref())
+                    .filter_map(|arg| match arg.value {
+                        Some(Expression::Placeholder(token)) => Some((arg.name.is_some(), token)),
+                        _ => None,
+                    });
+                for (named, token) in placeholders {
+                    match (named, allowed) {
+                        (false, _) => self.fail(token, PlaceholderMisuse::NotNamed),
+                        (true, None) => allowed = Some(token),
+                        (true, Some(_)) => self.fail(token, PlaceholderMisuse::Repeated),
+                    }
+                }
+            }
+            Expression::Placeholder(token) => self.fail(token, PlaceholderMisuse::NotNamed),
+            _ => {
+                if let Expression::Placeholder(token) = extraction_head(rhs) {
+                    allowed = Some(token);
+                }
+            }
+        }
+        let outer = std::mem::replace(&mut self.allowed, allowed);
+        self.visit_expression(rhs);
+        self.allowed = outer;
+    }
+}
+
+impl<'a> Visitor<'a> for PlaceholderChecker<'a> {
+    fn visit_expression(&mut self, expression: &Expression<'a>) {
+        match expression {
+            Expression::Placeholder(token) => match self.allowed {
+                Some(allowed) if std::ptr::eq(allowed, *token) => {}
+                _ => self.fail(token, PlaceholderMisuse::OutsideOfPipe),
+            },
+            Expression::Bop(op, lhs, rhs) if op.token == Token::Pipe => {
+                self.visit_expression(lhs);
+                self.visit_pipe_rhs(rhs);
+            }
+            _ => walk_expression(self, expression),
+        }
+    }
+}
+
+/// Returns the object at the start of a chain of `$`, `@`, `[` and `[[`.
+fn extraction_head<'e, 'a>(expression: &'e Expression<'a>) -> &'e Expression<'a> {
+    match expression {
+        Expression::Bop(op, lhs, _) if matches!(op.token, Token::Dollar | Token::Slot) => {
+            extraction_head(lhs)
+        }
+        Expression::SubsetExpression(subset) => extraction_head(&subset.object_ref),
+        _ => expression,
+    }
+}
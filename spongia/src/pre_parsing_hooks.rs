@@ -7,42 +7,195 @@ use tokenizer::tokens::Token;
 /// It achieves this by attaching all the comments that precede
 /// a token, and the inline comments that follow a token to the token itself.
 /// Thus, all the comments are attached to non-comment tokens.
+/// The span of a token with its comments is recorded in `comments_span`.
 /// The comments are then unfurled in the formatting stage.
+///
+/// Whitespace tokens emitted by the lossless tokenizer are dropped,
+/// and so are the newlines that R ignores, e.g. the ones inside parentheses.
 pub fn pre_parse<'a>(tokens: &'a mut [CommentedToken<'a>]) -> Vec<&'a CommentedToken<'a>> {
     let mut it = 0;
     let mut tokens_without_comments = vec![];
     while it < tokens.len() {
-        if let Token::Comment(comment) = tokens[it].token {
+        if let Some(comment) = tokens[it].token.line_comment() {
+            let first_comment = it;
             let mut comments = vec![comment];
             it += 1;
             loop {
                 match tokens[it].token {
                     Token::Newline => {
-                        if matches!(tokens[it - 1].token, Token::Newline) {
+                        if matches!(
+                            tokens[previous_non_whitespace(tokens, it)].token,
+                            Token::Newline
+                        ) {
                             comments.push("");
                         }
                     }
-                    Token::Comment(comment) => comments.push(comment),
-                    _ => break,
+                    Token::Whitespace(_) => {}
+                    ref token => match token.line_comment() {
+                        Some(comment) => comments.push(comment),
+                        None => break,
+                    },
                 }
                 it += 1;
             }
             tokens[it].leading_comments = Some(comments);
+            tokens[it].comments_span = Some(Box::new(
+                tokens[first_comment]
+                    .token_span()
+                    .to(&tokens[it].token_span()),
+            ));
             tokens_without_comments.push(it);
         } else if let Token::InlineComment(comment) = tokens[it].token {
-            tokens[it - 1].inline_comment = Some(comment);
-        } else {
+            let previous = previous_non_whitespace(tokens, it);
+            tokens[previous].inline_comment = Some(comment);
+            tokens[previous].comments_span = Some(Box::new(
+                tokens[previous].full_span().to(&tokens[it].token_span()),
+            ));
+        } else if !matches!(tokens[it].token, Token::Whitespace(_)) {
             tokens_without_comments.push(it);
         }
         it += 1;
     }
 
+    let mut context = NewlineContext::default();
     tokens_without_comments
-        .into_iter()
-        .map(|id| &tokens[id])
+        .iter()
+        .enumerate()
+        .filter(|&(i, &id)| {
+            let token = &tokens[id].token;
+            let next = match token {
+                Token::Newline => tokens_without_comments[i + 1..]
+                    .iter()
+                    .map(|&id| &tokens[id].token)
+                    .find(|token| !matches!(token, Token::Newline)),
+                _ => None,
+            };
+            context.is_significant(token, next)
+        })
+        .map(|(_, &id)| &tokens[id])
         .collect()
 }
 
+/// Tracks where a newline ends a statement, the way R's lexer does.
+///
+/// Newlines are ignored inside `(` and `[`, after an operator or a keyword
+/// expecting more input, and after the parenthesised part of `if`, `for`,
+/// `while` and function definitions. Inside `{` and at the top level
+/// they end the statement, except before an `else` inside `{`. That is why
+/// `else` on a new line is a syntax error only at the top level.
+#[derive(Debug, Default)]
+struct NewlineContext {
+    /// The open brackets, `true` for the parentheses after `if`, `for`,
+    /// `while`, `function` and `\`.
+    open: Vec<(Bracket, bool)>,
+    /// The next `(` belongs to `if`, `for`, `while`, `function` or `\`.
+    condition_next: bool,
+    /// The newlines until the next token are ignored.
+    eat_lines: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Bracket {
+    Paren,
+    Square,
+    Brace,
+}
+
+impl NewlineContext {
+    /// Returns whether the parser should see `token`,
+    /// which is followed by the token `next` on the next non-empty line.
+    fn is_significant(&mut self, token: &Token, next: Option<&Token>) -> bool {
+        if let Token::Newline = token {
+            return is_significant_newline(
+                self.open.last().map(|(bracket, _)| *bracket),
+                self.eat_lines,
+                next,
+            );
+        }
+        self.eat_lines = false;
+        match token {
+            Token::LParen => {
+                self.open.push((Bracket::Paren, self.condition_next));
+                self.condition_next = false;
+            }
+            Token::LBracket => self.open.push((Bracket::Square, false)),
+            Token::LBrace => self.open.push((Bracket::Brace, false)),
+            Token::RParen | Token::RBracket | Token::RBrace => {
+                if let Some((_, condition)) = self.open.pop() {
+                    self.eat_lines = condition;
+                }
+            }
+            Token::If | Token::For | Token::While | Token::Function | Token::Lambda => {
+                self.condition_next = true;
+                self.eat_lines = true;
+            }
+            token => self.eat_lines = expects_more(token),
+        }
+        true
+    }
+}
+
+/// Returns whether a newline ends the statement, given the innermost
+/// open bracket, whether the previous token expects more input,
+/// and the token on the next non-empty line.
+fn is_significant_newline(open: Option<Bracket>, eat_lines: bool, next: Option<&Token>) -> bool {
+    match open {
+        Some(Bracket::Paren | Bracket::Square) => false,
+        _ if eat_lines => false,
+        Some(Bracket::Brace) => !matches!(next, Some(Token::Else)),
+        None => true,
+    }
+}
+
+/// Returns whether the token cannot end a statement, e.g. a binary operator.
+fn expects_more(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::LAssign
+            | Token::SuperAssign
+            | Token::ColonAssign
+            | Token::RAssign
+            | Token::SuperRAssign
+            | Token::OldAssign
+            | Token::Equal
+            | Token::NotEqual
+            | Token::LowerThan
+            | Token::GreaterThan
+            | Token::LowerEqual
+            | Token::GreaterEqual
+            | Token::Power
+            | Token::Divide
+            | Token::Multiply
+            | Token::Minus
+            | Token::Plus
+            | Token::Help
+            | Token::And
+            | Token::VectorizedAnd
+            | Token::Or
+            | Token::VectorizedOr
+            | Token::Dollar
+            | Token::Pipe
+            | Token::Modulo
+            | Token::NsGet
+            | Token::NsGetInt
+            | Token::Tilde
+            | Token::Colon
+            | Token::Slot
+            | Token::Special(_)
+            | Token::UnaryNot
+            | Token::Else
+            | Token::Repeat
+            | Token::In
+    )
+}
+
+fn previous_non_whitespace(tokens: &[CommentedToken], it: usize) -> usize {
+    (0..it)
+        .rev()
+        .find(|&id| !matches!(tokens[id].token, Token::Whitespace(_)))
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,4 +227,26 @@ mod tests {
             "Inline comment"
         ));
     }
+
+    #[test]
+    fn whitespace_is_dropped() {
+        let mut tokens = commented_tokens![
+            Token::Whitespace("  "),
+            Token::Comment("Comment"),
+            Token::Newline,
+            Token::Whitespace("  "),
+            Token::Newline,
+            Token::Symbol("a"),
+            Token::Whitespace(" "),
+            Token::InlineComment("Inline comment")
+        ];
+        let commented_tokens = pre_parse(&mut tokens);
+        assert_eq!(commented_tokens.len(), 1);
+        assert_eq!(commented_tokens[0].token, Token::Symbol("a"));
+        assert_eq!(
+            commented_tokens[0].leading_comments,
+            Some(vec!["Comment", ""])
+        );
+        assert_eq!(commented_tokens[0].inline_comment, Some("Inline comment"));
+    }
 }
@@ -0,0 +1,186 @@
+use tokenizer::{tokens::CommentedToken, Token};
+
+use crate::ast::{walk_term, Expression, TermExpr, Visitor};
+
+/// Prints `expressions` back to R source.
+///
+/// `source` is the program the expressions were parsed from. Between two
+/// tokens that are unchanged and still next to each other, the whitespace,
+/// the line breaks and the comments of `source` are kept as they are.
+/// Elsewhere, e.g. around the tokens created by a rewrite or where tokens
+/// were removed, the tokens are separated by a space, or by a line break
+/// between statements, and their comments are printed from the tokens.
+///
+/// ```
+/// use tergo_parser::{parse, pre_parse, print};
+/// use tokenizer::Tokenizer;
+///
+/// let source = "x   <- c(1,2) # numbers\n";
+/// let mut commented_tokens = Tokenizer::new(source).tokenize().unwrap();
+/// let tokens = pre_parse(&mut commented_tokens);
+/// let program = parse(&tokens).unwrap();
+/// assert_eq!(print(&program, source), source);
+/// ```
+pub fn print(expressions: &[Expression], source: &str) -> String {
+    let mut printer = Printer {
+        source,
+        output: String::new(),
+        previous: None,
+        statement_start: false,
+    };
+    for expression in expressions {
+        printer.statement_start = true;
+        printer.visit_expression(expression);
+    }
+    printer.output
+}
+
+struct Printer<'s, 'a> {
+    source: &'s str,
+    output: String,
+    /// The last printed token and whether it is unchanged.
+    previous: Option<(&'a CommentedToken<'a>, bool)>,
+    /// Whether the next token starts a statement of a program or a block.
+    statement_start: bool,
+}
+
+impl<'s, 'a> Printer<'s, 'a> {
+    /// Returns whether `token` is the one found at its span in the source.
+    fn is_unchanged(&self, token: &CommentedToken) -> bool {
+        let Some(text) = self.source.get(token.span.clone()) else {
+            return false;
+        };
+        match token.token {
+            Token::Newline => matches!(text, "\n" | "\r\n" | "\r"),
+            Token::EOF => token.span.start == self.source.len(),
+            _ => !text.is_empty() && text == token.token.text(),
+        }
+    }
+
+    /// Returns the source between the end of the previous token and `token`
+    /// if it holds only whitespace and comments.
+    fn layout_before(&self, token: &CommentedToken) -> Option<&'s str> {
+        let start = match self.previous {
+            Some((previous, true)) => previous.span.end,
+            Some((_, false)) => return None,
+            None => 0,
+        };
+        let layout = self.source.get(start..token.span.start)?;
+        let mut lines = layout.split('\n');
+        let only_layout = lines.all(|line| {
+            let code = line.split_once('#').map_or(line, |(code, _)| code);
+            code.trim().is_empty()
+        });
+        // A subtree starts with its own leading comments
+        match (only_layout, self.previous) {
+            (true, _) => Some(layout),
+            (false, None) => self
+                .source
+                .get(token.full_span().bytes.start..token.span.start),
+            (false, Some(_)) => None,
+        }
+    }
+
+    /// Separates `token` from the previous one and prints the comments
+    /// between them, when the layout of the source cannot be used.
+    fn separate(&mut self, token: &CommentedToken) {
+        let mut new_line = self.statement_start && self.previous.is_some();
+        if let Some(comment) = self
+            .previous
+            .and_then(|(previous, _)| previous.inline_comment)
+        {
+            self.output.push(' ');
+            self.output.push_str(comment);
+            new_line = true;
+        }
+        if let Some(comments) = &token.leading_comments {
+            if self.previous.is_some() {
+                self.output.push('\n');
+            }
+            for comment in comments {
+                self.output.push_str(comment);
+                self.output.push('\n');
+            }
+        } else if new_line {
+            self.output.push('\n');
+        } else if self
+            .previous
+            .is_some_and(|(previous, _)| needs_space(&previous.token, &token.token))
+        {
+            self.output.push(' ');
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Printer<'_, 'a> {
+    fn visit_term(&mut self, term: &TermExpr<'a>) {
+        let is_block = term
+            .pre_delimiters
+            .is_none_or(|delimiter| delimiter.token == Token::LBrace);
+        if !is_block {
+            return walk_term(self, term);
+        }
+        if let Some(token) = term.pre_delimiters {
+            self.visit_token(token);
+        }
+        for statement in &term.term {
+            self.statement_start = true;
+            self.visit_expression(statement);
+        }
+        if let Some(token) = term.post_delimiters {
+            self.statement_start = true;
+            self.visit_token(token);
+        }
+    }
+
+    fn visit_token(&mut self, token: &'a CommentedToken<'a>) {
+        let unchanged = self.is_unchanged(token);
+        match self.layout_before(token).filter(|_| unchanged) {
+            Some(layout) => self.output.push_str(layout),
+            None => self.separate(token),
+        }
+        match unchanged {
+            true => self.output.push_str(&self.source[token.span.clone()]),
+            false => self.output.push_str(token.token.text()),
+        }
+        self.previous = Some((token, unchanged));
+        self.statement_start = false;
+    }
+}
+
+/// Returns whether a space is needed between two tokens that are not separated
+/// in the source. `[[` and `]]` are two tokens each and must stay together.
+fn needs_space(previous: &Token, next: &Token) -> bool {
+    !matches!(
+        (previous, next),
+        (
+            Token::LParen
+                | Token::LBracket
+                | Token::Dollar
+                | Token::Slot
+                | Token::NsGet
+                | Token::NsGetInt
+                | Token::Lambda
+                | Token::UnaryNot,
+            _
+        ) | (
+            _,
+            Token::RParen
+                | Token::RBracket
+                | Token::Comma
+                | Token::Semicolon
+                | Token::Dollar
+                | Token::Slot
+                | Token::NsGet
+                | Token::NsGetInt
+                | Token::EOF
+        ) | (
+            Token::Symbol(_)
+                | Token::Placeholder
+                | Token::RParen
+                | Token::RBracket
+                | Token::Function,
+            Token::LParen | Token::LBracket
+        )
+    )
+}
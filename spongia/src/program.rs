@@ -1,37 +1,177 @@
+use std::cell::RefCell;
+
 use log::trace;
 use nom::combinator::opt;
-use nom::{branch::alt, combinator::map, multi::many0, sequence::tuple, IResult};
+use nom::{branch::alt, combinator::map, sequence::tuple};
 use tokenizer::tokens_buffer::TokensBuffer;
+use tokenizer::Token;
 
 use crate::ast::Expression;
+use crate::error::{ParseError, SyntaxError};
 use crate::expressions::expr;
 use crate::token_parsers::{eof, newline, semicolon};
 use crate::whitespace::whitespace_or_comment;
-use crate::Input;
+use crate::{IResult, Input};
+
+pub(crate) fn statement_or_expr<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Expression<'a>> + '_ {
+    move |tokens| {
+        trace!("statement_or_expr: {}", TokensBuffer(tokens));
+        alt((
+            expr(state),
+            map(whitespace_or_comment, Expression::Whitespace),
+        ))(tokens)
+    }
+}
+
+/// Parses the `;` ending a statement together with the line break after it.
+fn terminator<'a, 'b: 'a>(tokens: Input<'a, 'b>) -> IResult<Input<'a, 'b>, Expression<'a>> {
+    map(tuple((semicolon, opt(newline))), |(semicolon, _)| {
+        Expression::Semicolon(semicolon)
+    })(tokens)
+}
+
+/// The state of a single parse, passed down to the parsers that depend on it.
+#[derive(Debug, Default)]
+pub(crate) struct ParseState {
+    /// The errors skipped over in the recovery mode, `None` outside of it.
+    recovered_errors: Option<RefCell<Vec<ParseError>>>,
+}
+
+impl ParseState {
+    /// The state of the recovery mode, in which [statements] keeps
+    /// the statements failing to parse as [Expression::Error].
+    pub(crate) fn recovering() -> Self {
+        Self {
+            recovered_errors: Some(RefCell::new(vec![])),
+        }
+    }
+
+    /// Returns the errors recovered from.
+    pub(crate) fn into_recovered_errors(self) -> Vec<ParseError> {
+        self.recovered_errors
+            .map(RefCell::into_inner)
+            .unwrap_or_default()
+    }
+}
 
-pub(crate) fn statement_or_expr<'a, 'b: 'a>(
+/// Parses the statements of a program or a block up to the token matching `end`.
+///
+/// In the recovery mode a statement that fails to parse becomes
+/// an [Expression::Error] instead of failing the whole program.
+pub(crate) fn statements<'a>(
+    state: &ParseState,
+    end: fn(&Token) -> bool,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Vec<Expression<'a>>> + '_ {
+    move |mut tokens| {
+        let mut statements = vec![];
+        loop {
+            let err = match statement_or_expr(state)(tokens) {
+                Ok((rest, statement)) => {
+                    let is_whitespace = matches!(statement, Expression::Whitespace(_));
+                    statements.push(statement);
+                    tokens = rest;
+                    if !is_whitespace {
+                        tokens = end_of_statement(tokens, &mut statements);
+                    }
+                    continue;
+                }
+                Err(nom::Err::Error(err) | nom::Err::Failure(err)) => err,
+                Err(err) => return Err(err),
+            };
+            let at_start = err.input.len() == tokens.len();
+            let at_end = tokens.first().is_none_or(|token| end(&token.token));
+            let Some(recovered_errors) = &state.recovered_errors else {
+                return match at_start {
+                    true => Ok((tokens, statements)),
+                    false => Err(nom::Err::Failure(err)),
+                };
+            };
+            if at_start && at_end {
+                return Ok((tokens, statements));
+            }
+            tokens = recover(
+                err,
+                tokens,
+                &mut statements,
+                &mut recovered_errors.borrow_mut(),
+            );
+        }
+    }
+}
+
+/// Skips the statement at the start of `tokens` that failed with `err`
+/// and adds the error to `errors`. Returns the tokens after it.
+fn recover<'a, 'b: 'a>(
+    err: SyntaxError<Input<'a, 'b>>,
     tokens: Input<'a, 'b>,
-) -> IResult<Input<'a, 'b>, Expression<'a>> {
-    trace!("statement_or_expr: {}", TokensBuffer(tokens));
-    alt((
-        map(
-            // TODO: This colon might have a trailing comment attached to it...
-            // or a normal comment...
-            tuple((expr, opt(alt((semicolon, newline))))),
-            |(expr, _)| expr,
-        ),
-        map(whitespace_or_comment, Expression::Whitespace),
-    ))(tokens)
+    statements: &mut Vec<Expression<'a>>,
+    errors: &mut Vec<ParseError>,
+) -> Input<'a, 'b> {
+    let error = err.into_parse_error(tokens);
+    trace!("Recovering from: {error}");
+    errors.push(error);
+    let length = statement_length(tokens);
+    statements.push(Expression::Error(&tokens[..length]));
+    end_of_statement(&tokens[length..], statements)
 }
 
-pub(crate) fn program<'a, 'b: 'a>(
+/// Skips the line break or the `;` ending a statement. The `;` is kept
+/// in `statements`. Returns the tokens after it.
+fn end_of_statement<'a, 'b: 'a>(
     tokens: Input<'a, 'b>,
-) -> IResult<Input<'a, 'b>, Vec<Expression<'a>>> {
-    trace!("program: {}", TokensBuffer(tokens));
-    map(tuple((many0(statement_or_expr), eof)), |(mut xprs, eof)| {
-        xprs.push(Expression::EOF(eof));
-        xprs
-    })(tokens)
+    statements: &mut Vec<Expression<'a>>,
+) -> Input<'a, 'b> {
+    if let Ok((rest, semicolon)) = terminator(tokens) {
+        statements.push(semicolon);
+        return rest;
+    }
+    match newline(tokens) {
+        Ok((rest, _)) => rest,
+        Err(_) => tokens,
+    }
+}
+
+/// Guesses the number of tokens in the statement at the start of `tokens`
+/// without parsing it. The statement ends with the first line break or
+/// semicolon outside of any brackets or with a `}` closing the enclosing block.
+fn statement_length(tokens: Input) -> usize {
+    let mut open = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        match token.token {
+            Token::LParen | Token::LBracket | Token::LBrace => open.push(&token.token),
+            Token::RParen if open.last() == Some(&&Token::LParen) => {
+                open.pop();
+            }
+            Token::RBracket if open.last() == Some(&&Token::LBracket) => {
+                open.pop();
+            }
+            Token::RBrace => match open.iter().rposition(|open| **open == Token::LBrace) {
+                Some(brace) => open.truncate(brace),
+                None => return i.max(1),
+            },
+            Token::Newline | Token::Semicolon if open.is_empty() => return i.max(1),
+            Token::EOF => return i.max(1),
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+pub(crate) fn program<'a>(
+    state: &ParseState,
+) -> impl FnMut(Input<'a, 'a>) -> IResult<Input<'a, 'a>, Vec<Expression<'a>>> + '_ {
+    move |tokens| {
+        trace!("program: {}", TokensBuffer(tokens));
+        map(
+            tuple((statements(state, |token| matches!(token, Token::EOF)), eof)),
+            |(mut xprs, eof)| {
+                xprs.push(Expression::EOF(eof));
+                xprs
+            },
+        )(tokens)
+    }
 }
 
 #[cfg(test)]
@@ -46,7 +186,7 @@ mod tests {
     fn program_parses_newline() {
         let tokens_ = commented_tokens![Token::Newline, Token::EOF];
         let tokens: Vec<_> = tokens_.iter().collect();
-        let res = program(&tokens).unwrap().1;
+        let res = program(&ParseState::default())(&tokens).unwrap().1;
 
         assert_eq!(
             res,
@@ -61,7 +201,7 @@ mod tests {
     fn parses_literal_ending_with_a_newline() {
         let tokens_ = commented_tokens![Token::Literal("7"), Token::Newline, Token::EOF];
         let tokens: Vec<_> = tokens_.iter().collect();
-        let res = program(&tokens).unwrap().1;
+        let res = program(&ParseState::default())(&tokens).unwrap().1;
         assert_eq!(
             res,
             vec![Expression::Literal(tokens[0]), Expression::EOF(tokens[2])]
@@ -77,7 +217,7 @@ mod tests {
             Token::EOF
         ];
         let tokens: Vec<_> = tokens_.iter().collect();
-        let res = program(&tokens).unwrap().1;
+        let res = program(&ParseState::default())(&tokens).unwrap().1;
         assert_eq!(
             res,
             vec![
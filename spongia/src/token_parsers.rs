@@ -1,10 +1,10 @@
-use crate::Input;
-use nom::IResult;
+use crate::error::{Expected, SyntaxError};
+use crate::{IResult, Input};
 use tokenizer::tokens::CommentedToken;
 use tokenizer::Token::*;
 
 macro_rules! token_parser {
-    ($name:ident, $token:pat) => {
+    ($name:ident, $token:pat, $expected:expr) => {
         pub(crate) fn $name<'a, 'b>(
             input: Input<'a, 'b>,
         ) -> IResult<Input<'a, 'b>, &'b CommentedToken<'a>>
@@ -13,45 +13,51 @@ macro_rules! token_parser {
         {
             match input {
                 [token @ CommentedToken { token: $token, .. }, rest @ ..] => Ok((rest, token)),
-                _ => Err(nom::Err::Error(nom::error::Error::new(
-                    input,
-                    nom::error::ErrorKind::Tag,
-                ))),
+                _ => Err(nom::Err::Error(SyntaxError::new(input, $expected))),
             }
         }
     };
 }
 
-token_parser!(symbol, Symbol(_));
-token_parser!(literal, Literal(_));
-token_parser!(semicolon, Semicolon);
-token_parser!(newline, Newline);
-token_parser!(lparen, LParen);
-token_parser!(rparen, RParen);
-token_parser!(lbrace, LBrace);
-token_parser!(rbrace, RBrace);
-token_parser!(lbracket, LBracket);
-token_parser!(rbracket, RBracket);
-token_parser!(comma, Comma);
+token_parser!(
+    symbol,
+    Symbol(_) | Dots | DotDotN(_),
+    Expected::Token("a symbol")
+);
+token_parser!(
+    literal,
+    Literal(_) | Null | Na | NaInteger | NaReal | NaCharacter | Inf | NaN,
+    Expected::Token("a literal")
+);
+token_parser!(placeholder, Placeholder, Expected::Token("`_`"));
+token_parser!(semicolon, Semicolon, Expected::Token("`;`"));
+token_parser!(newline, Newline, Expected::Token("newline"));
+token_parser!(lparen, LParen, Expected::Token("`(`"));
+token_parser!(rparen, RParen, Expected::Token("`)`"));
+token_parser!(lbrace, LBrace, Expected::Token("`{`"));
+token_parser!(rbrace, RBrace, Expected::Token("`}`"));
+token_parser!(lbracket, LBracket, Expected::Token("`[`"));
+token_parser!(rbracket, RBracket, Expected::Token("`]`"));
+token_parser!(comma, Comma, Expected::Token("`,`"));
 
 // Reserved
-token_parser!(continue_token, Continue);
-token_parser!(break_token, Break);
+token_parser!(continue_token, Continue, Expected::Token("`continue`"));
+token_parser!(break_token, Break, Expected::Token("`break`"));
 
 // Compound
-token_parser!(if_token, If);
-token_parser!(else_token, Else);
-token_parser!(while_token, While);
-token_parser!(for_token, For);
-token_parser!(repeat, Repeat);
-token_parser!(in_token, In);
-token_parser!(function, Function);
-token_parser!(lambda, Lambda);
+token_parser!(if_token, If, Expected::Token("`if`"));
+token_parser!(else_token, Else, Expected::Token("`else`"));
+token_parser!(while_token, While, Expected::Token("`while`"));
+token_parser!(for_token, For, Expected::Token("`for`"));
+token_parser!(repeat, Repeat, Expected::Token("`repeat`"));
+token_parser!(in_token, In, Expected::Token("`in`"));
+token_parser!(function, Function, Expected::Token("`function`"));
+token_parser!(lambda, Lambda, Expected::Token("`\\`"));
 
 // Binary operators
 // token_parser!(lassign, LAssign);
 // token_parser!(rassign, RAssign);
-// token_parser!(old_assign, OldAssign);
+token_parser!(old_assign, OldAssign, Expected::Token("`=`"));
 // token_parser!(equal, Equal);
 // token_parser!(not_equal, NotEqual);
 // token_parser!(lower_than, LowerThan);
@@ -65,26 +71,27 @@ token_parser!(lambda, Lambda);
 // token_parser!(vectorized_and, VectorizedAnd);
 // token_parser!(or, Or);
 // token_parser!(vectorized_or, VectorizedOr);
-// token_parser!(dollar, Dollar);
+token_parser!(dollar, Dollar, Expected::Token("`$`"));
 // token_parser!(pipe, Pipe);
 // token_parser!(modulo, Modulo);
-// token_parser!(ns_get, NsGet);
-// token_parser!(ns_get_int, NsGetInt);
+token_parser!(ns_get, NsGet, Expected::Token("`::`"));
+token_parser!(ns_get_int, NsGetInt, Expected::Token("`:::`"));
 // token_parser!(colon, Colon);
+token_parser!(slot, Slot, Expected::Token("`@`"));
 
 // Unary operators
-token_parser!(unary_not, UnaryNot);
-token_parser!(minus, Minus);
-token_parser!(plus, Plus);
-token_parser!(tilde, Tilde);
-token_parser!(help, Help);
+token_parser!(unary_not, UnaryNot, Expected::Token("`!`"));
+token_parser!(minus, Minus, Expected::Token("`-`"));
+token_parser!(plus, Plus, Expected::Token("`+`"));
+token_parser!(tilde, Tilde, Expected::Token("`~`"));
+token_parser!(help, Help, Expected::Token("`?`"));
 
 // Comments
 // token_parser!(inline_comment, InlineComment(_));
 // token_parser!(comment, Comment(_));
 
 // EOF
-token_parser!(eof, EOF);
+token_parser!(eof, EOF, Expected::Nothing);
 
 #[cfg(test)]
 mod tests {
@@ -94,7 +101,11 @@ mod tests {
 
     #[test]
     fn symbols() {
-        let examples = [commented_tokens!(Symbol("a"))];
+        let examples = [
+            commented_tokens!(Symbol("a")),
+            commented_tokens!(Dots),
+            commented_tokens!(DotDotN("..2")),
+        ];
 
         for tokens in &examples {
             let tokens: Vec<_> = tokens.iter().collect();
@@ -105,7 +116,16 @@ mod tests {
 
     #[test]
     fn literals() {
-        let examples = [commented_tokens!(Literal("a"))];
+        let examples = [
+            commented_tokens!(Literal("a")),
+            commented_tokens!(Null),
+            commented_tokens!(Na),
+            commented_tokens!(NaInteger),
+            commented_tokens!(NaReal),
+            commented_tokens!(NaCharacter),
+            commented_tokens!(Inf),
+            commented_tokens!(NaN),
+        ];
 
         for tokens in &examples {
             let tokens: Vec<_> = tokens.iter().collect();
@@ -1,14 +1,11 @@
 use log::trace;
-use nom::{
-    error::{make_error, ErrorKind},
-    IResult,
-};
+use nom::error::{make_error, ErrorKind};
 use tokenizer::{tokens::CommentedToken, tokens_buffer::TokensBuffer, Token};
 
-use crate::Input;
+use crate::{IResult, Input};
 
 fn is_comment_or_newline(token: &CommentedToken) -> bool {
-    matches!(token.token, Token::Comment(_) | Token::Newline)
+    token.token.line_comment().is_some() || matches!(token.token, Token::Newline)
 }
 
 pub(crate) fn whitespace_or_comment<'a, 'b: 'a>(
@@ -1,9 +1,11 @@
 use tergo_parser::ast::{
-    Arg, Args, Delimiter, ElseIfConditional, Expression, ExpressionsBuffer, ForLoop, FunctionCall,
-    FunctionDefinition, IfConditional, IfExpression, Lambda, RepeatExpression, TermExpr,
-    TrailingElse, WhileExpression,
+    walk_expression_mut, walk_function_call, walk_if_conditional_mut, Arg, Args, CallArgument,
+    Delimiter, ElseIfConditional, Expression, ExpressionsBuffer, ForLoop, FormalParameter,
+    FunctionCall, FunctionDefinition, IfConditional, IfExpression, Lambda, RepeatExpression,
+    TermExpr, TokenArena, TrailingElse, Visitor, VisitorMut, WhileExpression,
 };
-use tergo_parser::{parse, pre_parse};
+use tergo_parser::{parse, parse_data, parse_data_to_csv, parse_with_recovery, pre_parse, print};
+use tokenizer::tokens::{CommentedToken, Span, Token};
 use tokenizer::Tokenizer;
 
 fn log_init() {
@@ -15,7 +17,7 @@ fn test_cases() {
     log_init();
     let code = include_str!("./test_cases/001.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
 
     let tokens = pre_parse(&mut commented_tokens);
     let res = parse(&tokens).unwrap();
@@ -32,7 +34,7 @@ fn literal_with_parentheses() {
     log_init();
     let code = include_str!("./test_cases/002.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
 
     let tokens = pre_parse(&mut commented_tokens);
     let res = parse(&tokens).unwrap();
@@ -52,7 +54,7 @@ fn bop_with_parentheses() {
     log_init();
     let code = include_str!("./test_cases/003.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
 
     let tokens = pre_parse(&mut commented_tokens);
     let res = parse(&tokens).unwrap();
@@ -76,7 +78,7 @@ fn empty_function_definition() {
     log_init();
     let code = include_str!("./test_cases/004.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
 
     let tokens = pre_parse(&mut commented_tokens);
     let res = parse(&tokens).unwrap();
@@ -105,7 +107,7 @@ fn function_def_with_one_arg() {
     log_init();
     let code = include_str!("./test_cases/005.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
 
     let tokens = pre_parse(&mut commented_tokens);
     let res = parse(&tokens).unwrap();
@@ -114,7 +116,13 @@ fn function_def_with_one_arg() {
             tokens[0],
             Args::new(
                 Delimiter::Paren(tokens[1]),
-                vec![Arg(Some(Expression::Symbol(tokens[2])), None)],
+                vec![Arg(
+                    Some(FormalParameter {
+                        name: tokens[2],
+                        default: None,
+                    }),
+                    None,
+                )],
                 Delimiter::Paren(tokens[3]),
             ),
             Box::new(Expression::Term(Box::new(TermExpr::new(
@@ -140,7 +148,7 @@ fn function_def_with_one_arg_with_default_value() {
     log_init();
     let code = include_str!("./test_cases/006.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
 
     let tokens = pre_parse(&mut commented_tokens);
     let res = parse(&tokens).unwrap();
@@ -150,11 +158,10 @@ fn function_def_with_one_arg_with_default_value() {
             Args::new(
                 Delimiter::Paren(tokens[1]),
                 vec![Arg(
-                    Some(Expression::Bop(
-                        tokens[3],
-                        Box::new(Expression::Symbol(tokens[2])),
-                        Box::new(Expression::Literal(tokens[4])),
-                    )),
+                    Some(FormalParameter {
+                        name: tokens[2],
+                        default: Some((tokens[3], Expression::Literal(tokens[4]))),
+                    }),
                     None,
                 )],
                 Delimiter::Paren(tokens[5]),
@@ -182,7 +189,7 @@ fn function_def_with_two_args() {
     log_init();
     let code = include_str!("./test_cases/007.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
 
     let tokens = pre_parse(&mut commented_tokens);
     let res = parse(&tokens).unwrap();
@@ -193,10 +200,19 @@ fn function_def_with_two_args() {
                 Delimiter::Paren(tokens[1]),
                 vec![
                     Arg(
-                        Some(Expression::Symbol(tokens[2])),
+                        Some(FormalParameter {
+                            name: tokens[2],
+                            default: None,
+                        }),
                         Some(Expression::Literal(tokens[3])),
                     ),
-                    Arg(Some(Expression::Symbol(tokens[4])), None),
+                    Arg(
+                        Some(FormalParameter {
+                            name: tokens[4],
+                            default: None,
+                        }),
+                        None,
+                    ),
                 ],
                 Delimiter::Paren(tokens[5]),
             ),
@@ -223,7 +239,7 @@ fn function_inline_body() {
     log_init();
     let code = include_str!("./test_cases/008.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
 
     let tokens = pre_parse(&mut commented_tokens);
     let res = parse(&tokens).unwrap();
@@ -254,7 +270,7 @@ fn function_multiline_body() {
     log_init();
     let code = include_str!("./test_cases/009.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
     let tokens = pre_parse(&mut commented_tokens);
 
     let res = parse(&tokens).unwrap();
@@ -293,7 +309,7 @@ fn if_conditional() {
 
     let code = include_str!("./test_cases/010.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
     let tokens = pre_parse(&mut commented_tokens);
 
     let res = parse(&tokens).unwrap();
@@ -331,7 +347,7 @@ fn if_with_else() {
 
     let code = include_str!("./test_cases/011.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
     let tokens = pre_parse(&mut commented_tokens);
 
     let res = parse(&tokens).unwrap();
@@ -376,7 +392,7 @@ fn if_with_if_else_and_else() {
 
     let code = include_str!("./test_cases/012.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
     let tokens = pre_parse(&mut commented_tokens);
 
     let res = parse(&tokens).unwrap();
@@ -434,7 +450,7 @@ fn while_test() {
 
     let code = include_str!("./test_cases/013.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
     let tokens = pre_parse(&mut commented_tokens);
 
     let res = parse(&tokens).unwrap();
@@ -470,7 +486,7 @@ fn repeat_test() {
 
     let code = include_str!("./test_cases/014.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
     let tokens = pre_parse(&mut commented_tokens);
 
     let res = parse(&tokens).unwrap();
@@ -501,7 +517,7 @@ fn function_call_test() {
 
     let code = include_str!("./test_cases/015.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
     let tokens = pre_parse(&mut commented_tokens);
 
     let res = parse(&tokens).unwrap();
@@ -546,7 +562,7 @@ fn for_loop_test() {
 
     let code = include_str!("./test_cases/016.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
     let tokens = pre_parse(&mut commented_tokens);
 
     let res = parse(&tokens).unwrap();
@@ -560,7 +576,13 @@ fn for_loop_test() {
                 function_ref: Box::new(Expression::Symbol(tokens[4])),
                 args: Args {
                     left_delimeter: Delimiter::Paren(tokens[5]),
-                    args: vec![Arg(Some(Expression::Literal(tokens[6])), None)],
+                    args: vec![Arg(
+                        Some(CallArgument {
+                            name: None,
+                            value: Some(Expression::Literal(tokens[6])),
+                        }),
+                        None,
+                    )],
                     right_delimeter: Delimiter::Paren(tokens[7]),
                 },
             })),
@@ -585,7 +607,7 @@ fn lambda_function_test() {
 
     let code = include_str!("./test_cases/017.R");
     let mut tokenizer = Tokenizer::new(code);
-    let mut commented_tokens = tokenizer.tokenize();
+    let mut commented_tokens = tokenizer.tokenize().unwrap();
     let tokens = pre_parse(&mut commented_tokens);
 
     let res = parse(&tokens).unwrap();
@@ -610,3 +632,746 @@ fn lambda_function_test() {
         ExpressionsBuffer(&expected)
     );
 }
+
+#[test]
+fn whitespace_tokens_do_not_change_the_tree() {
+    log_init();
+    let examples = [
+        include_str!("./test_cases/003.R"),
+        include_str!("./test_cases/010.R"),
+        include_str!("./test_cases/016.R"),
+        "  a <- function(x,  y) {\n  # comment\n  x + y # inline\n}\n",
+    ];
+    for code in examples {
+        let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+        let mut lossless_tokens = Tokenizer::new(code).lossless().tokenize().unwrap();
+
+        let tokens = pre_parse(&mut commented_tokens);
+        let lossless = pre_parse(&mut lossless_tokens);
+        assert_eq!(parse(&tokens).unwrap(), parse(&lossless).unwrap());
+    }
+}
+
+#[test]
+fn parse_errors() {
+    log_init();
+    let examples = [
+        (
+            "f(a, b\n",
+            "2:1: expected `)` to close call started at 1:2, found end of input",
+        ),
+        (
+            "x[1\n",
+            "2:1: expected `]` to close subset started at 1:2, found end of input",
+        ),
+        (
+            "x[[1]\n",
+            "1:5: expected `]]` to close subset started at 1:2, found `]`",
+        ),
+        (
+            "(1 2)",
+            "1:4: expected `)` to close parenthesis started at 1:1, found `2`",
+        ),
+        (
+            "{\n  a\n",
+            "3:1: expected `}` to close block started at 1:1, found end of input",
+        ),
+        (
+            "if (a b) c",
+            "1:7: expected `)` to close condition started at 1:4, found `b`",
+        ),
+        (
+            "function(a, b c",
+            "1:15: expected `)` to close parameter list started at 1:9, found `c`",
+        ),
+        (
+            "function(1) a",
+            "1:10: expected `)` to close parameter list started at 1:9, found `1`",
+        ),
+        (
+            "function(a = ) a",
+            "1:14: expected an expression, found `)`",
+        ),
+        ("a <- )", "1:6: expected an expression, found `)`"),
+        ("f(a <- )", "1:8: expected an expression, found `)`"),
+        ("{\n  b <- \n}", "3:1: expected an expression, found `}`"),
+        ("a\n)", "2:1: unexpected `)`"),
+        ("a == b == c", "1:8: unexpected `==`"),
+        ("a < b > c", "1:7: unexpected `>`"),
+        ("- )", "1:3: expected an expression, found `)`"),
+    ];
+    for (code, expected) in examples {
+        let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+        let tokens = pre_parse(&mut commented_tokens);
+        let err = parse(&tokens).unwrap_err();
+        assert_eq!(err.to_string(), expected, "{code}");
+    }
+}
+
+#[test]
+fn parse_error_snippets() {
+    let code = "f <- function(x) {\n  g(x,\n    y\n}\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let err = parse(&tokens).unwrap_err().with_source(code);
+    assert_eq!((err.line, err.offset), (3, 0));
+    assert_eq!(
+        err.expected.as_deref(),
+        Some("`)` to close call started at 2:4")
+    );
+    assert_eq!(err.snippet.as_deref(), Some("}\n^"));
+}
+
+#[test]
+fn recovering_from_syntax_errors() {
+    log_init();
+    let code = "a <- (1 2)\nb <- 3\nf <- function() {\n  g(\n  h\n}\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    assert!(parse(&tokens).is_err());
+
+    let (res, errors) = parse_with_recovery(&tokens);
+    let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        messages,
+        [
+            "1:9: expected `)` to close parenthesis started at 1:6, found `2`",
+            "6:1: expected `)` to close call started at 4:4, found `}`",
+        ]
+    );
+    assert_eq!(res[0], Expression::Error(&tokens[..6]));
+    assert!(matches!(res[1], Expression::Bop(..)));
+    match &res[2] {
+        Expression::Bop(_, _, function) => match &**function {
+            Expression::FunctionDef(function) => match &*function.body {
+                Expression::Term(block) => {
+                    assert_eq!(block.term, [Expression::Error(&tokens[18..21])]);
+                }
+                other => panic!("Expected a block, got {other}"),
+            },
+            other => panic!("Expected a function definition, got {other}"),
+        },
+        other => panic!("Expected an assignment, got {other}"),
+    }
+    assert!(matches!(res.last(), Some(Expression::EOF(_))));
+}
+
+#[test]
+fn spans_of_nodes() {
+    log_init();
+    let code = "# Adds one\nf <- function(x) {\n  if (x > 1) x[[1]] else g(x) # inline\n}\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let res = parse(&tokens).unwrap();
+    let text = |span: Span| &code[span.bytes];
+
+    let assignment = res[0].span().unwrap();
+    assert_eq!((assignment.start_line, assignment.start_offset), (0, 0));
+    assert_eq!((assignment.end_line, assignment.end_offset), (3, 1));
+    assert_eq!(text(assignment), code.trim_end());
+
+    let Expression::Bop(_, _, function) = &res[0] else {
+        panic!("Expected an assignment, got {}", res[0]);
+    };
+    let Expression::FunctionDef(function) = &**function else {
+        panic!("Expected a function definition, got {function}");
+    };
+    assert_eq!(text(function.arguments.span()), "(x)");
+    let Expression::Term(block) = &*function.body else {
+        panic!("Expected a block, got {}", function.body);
+    };
+    let Expression::IfExpression(if_expression) = &block.term[0] else {
+        panic!("Expected an if expression, got {}", block.term[0]);
+    };
+    let if_span = if_expression.span();
+    assert_eq!((if_span.start_line, if_span.start_offset), (2, 2));
+    assert_eq!(text(if_span), "if (x > 1) x[[1]] else g(x) # inline");
+    assert_eq!(
+        text(if_expression.if_conditional.condition.span().unwrap()),
+        "x > 1"
+    );
+    assert_eq!(
+        text(if_expression.if_conditional.body.span().unwrap()),
+        "x[[1]]"
+    );
+    let trailing_else = if_expression.trailing_else.as_ref().unwrap();
+    assert_eq!(text(trailing_else.span()), "else g(x) # inline");
+}
+
+#[test]
+fn visiting_every_token_in_order() {
+    #[derive(Default)]
+    struct Tokens<'a> {
+        tokens: Vec<&'a CommentedToken<'a>>,
+        calls: Vec<String>,
+    }
+
+    impl<'a> Visitor<'a> for Tokens<'a> {
+        fn visit_function_call(&mut self, call: &FunctionCall<'a>) {
+            self.calls.push(call.function_ref.to_string());
+            walk_function_call(self, call);
+        }
+
+        fn visit_token(&mut self, token: &'a CommentedToken<'a>) {
+            self.tokens.push(token);
+        }
+    }
+
+    log_init();
+    let code = "f <- function(x, y = 2) {\n  for (i in x[[1]]) if (i) g(-i) else break\n}\nh(\\(z) z ~ y, )\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let res = parse(&tokens).unwrap();
+    let mut visitor = Tokens::default();
+    res.iter()
+        .for_each(|expression| visitor.visit_expression(expression));
+    // The newlines separating the statements are not kept in the tree
+    let expected: Vec<_> = tokens
+        .iter()
+        .filter(|token| token.token != Token::Newline)
+        .collect();
+    assert_eq!(visitor.tokens.len(), expected.len());
+    assert!(visitor
+        .tokens
+        .iter()
+        .zip(expected)
+        .all(|(visited, token)| std::ptr::eq(*visited, *token)));
+    assert_eq!(visitor.calls, ["Symbol(\"g\")", "Symbol(\"h\")"]);
+}
+
+/// Prints the tree the way the R function in `test_cases/precedence.txt` does.
+fn sexp(expression: &Expression) -> String {
+    fn call<'e>(parts: impl IntoIterator<Item = &'e Expression<'e>>, head: String) -> String {
+        let parts: Vec<_> = std::iter::once(head)
+            .chain(parts.into_iter().map(sexp))
+            .collect();
+        format!("({})", parts.join(" "))
+    }
+    fn args<'e>(args: &'e Args<'e>) -> impl Iterator<Item = &'e Expression<'e>> {
+        args.args
+            .iter()
+            .filter_map(|arg| arg.0.as_ref().and_then(|arg| arg.value.as_ref()))
+    }
+    fn text(token: &Token) -> String {
+        match token {
+            Token::Symbol(text) | Token::Literal(text) | Token::Special(text) => text.to_string(),
+            Token::LAssign => "<-".to_string(),
+            Token::SuperAssign => "<<-".to_string(),
+            Token::OldAssign => "=".to_string(),
+            Token::Equal => "==".to_string(),
+            Token::LowerThan => "<".to_string(),
+            Token::Power => "^".to_string(),
+            Token::Divide => "/".to_string(),
+            Token::Multiply => "*".to_string(),
+            Token::Minus => "-".to_string(),
+            Token::Plus => "+".to_string(),
+            Token::Help => "?".to_string(),
+            Token::And => "&&".to_string(),
+            Token::VectorizedAnd => "&".to_string(),
+            Token::Or => "||".to_string(),
+            Token::VectorizedOr => "|".to_string(),
+            Token::Dollar => "$".to_string(),
+            Token::Slot => "@".to_string(),
+            Token::Modulo => "%%".to_string(),
+            Token::NsGet => "::".to_string(),
+            Token::Tilde => "~".to_string(),
+            Token::Colon => ":".to_string(),
+            Token::UnaryNot => "!".to_string(),
+            Token::Placeholder => "_".to_string(),
+            other => panic!("Unexpected token {other:?}"),
+        }
+    }
+
+    match expression {
+        Expression::Symbol(token) | Expression::Literal(token) | Expression::Placeholder(token) => {
+            text(token)
+        }
+        Expression::Term(term) => match term.pre_delimiters.map(|token| &token.token) {
+            None => sexp(&term.term[0]),
+            Some(Token::LParen) => call(&term.term, "(".to_string()),
+            Some(Token::LBrace) => call(&term.term, "{".to_string()),
+            Some(other) => panic!("Unexpected delimiter {other:?}"),
+        },
+        Expression::Unary(op, operand) | Expression::Formula(op, operand) => {
+            call([&**operand], text(op))
+        }
+        Expression::Bop(op, lhs, rhs) => match op.token {
+            Token::RAssign => call([&**rhs, &**lhs], "<-".to_string()),
+            Token::SuperRAssign => call([&**rhs, &**lhs], "<<-".to_string()),
+            Token::Pipe => match &**rhs {
+                Expression::FunctionCall(function_call) => call(
+                    std::iter::once(&**lhs).chain(args(&function_call.args)),
+                    sexp(&function_call.function_ref),
+                ),
+                other => panic!("Expected a call on the right side of a pipe, got {other}"),
+            },
+            _ => call([&**lhs, &**rhs], text(op)),
+        },
+        Expression::FunctionCall(function_call) => {
+            call(args(&function_call.args), sexp(&function_call.function_ref))
+        }
+        Expression::SubsetExpression(subset) => {
+            let head = match subset.args.left_delimeter {
+                Delimiter::SingleBracket(_) => "[",
+                _ => "[[",
+            };
+            call(
+                std::iter::once(&*subset.object_ref).chain(args(&subset.args)),
+                head.to_string(),
+            )
+        }
+        Expression::IfExpression(if_expression) => {
+            let if_conditional = &if_expression.if_conditional;
+            call(
+                [&*if_conditional.condition, &*if_conditional.body]
+                    .into_iter()
+                    .chain(if_expression.trailing_else.iter().map(|e| &*e.body)),
+                "if".to_string(),
+            )
+        }
+        other => panic!("Unexpected expression {other}"),
+    }
+}
+
+#[test]
+fn operator_precedence() {
+    log_init();
+    let fixtures = include_str!("./test_cases/precedence.txt");
+    let cases = fixtures.split("\n\n").filter(|case| !case.starts_with('#'));
+    for case in cases {
+        let (code, tree) = case.trim().split_once('\n').unwrap();
+        let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+        let tokens = pre_parse(&mut commented_tokens);
+        let res = parse(&tokens).unwrap();
+        assert_eq!(sexp(&res[0]), tree, "{code}");
+    }
+}
+
+#[test]
+fn newlines_inside_brackets_and_after_operators_are_ignored() {
+    log_init();
+    let examples = [
+        ("f(a\n+ b)", "(f (+ a b))"),
+        ("x[\n1\n]", "([ x 1)"),
+        ("x[[\n1\n]]", "([[ x 1)"),
+        ("(a\n- b)", "(( (- a b))"),
+        ("a <-\n  b", "(<- a b)"),
+        ("a |>\n  f()", "(f a)"),
+        ("-\n1", "(- 1)"),
+        ("x$\n  y", "($ x y)"),
+        ("if (a)\n  b", "(if a b)"),
+        ("if (a) b else\n  c", "(if a b c)"),
+        ("(if (a) b\nelse c)", "(( (if a b c))"),
+        ("f(if (a) b\nelse c)", "(f (if a b c))"),
+    ];
+    for (code, tree) in examples {
+        let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+        let tokens = pre_parse(&mut commented_tokens);
+        let res = parse(&tokens).unwrap();
+        assert_eq!(res.len(), 2, "{code}");
+        assert_eq!(sexp(&res[0]), tree, "{code}");
+    }
+}
+
+#[test]
+fn newlines_end_statements_at_top_level_and_in_braces() {
+    log_init();
+    let code = "a\n-1\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let res = parse(&tokens).unwrap();
+    let trees: Vec<_> = res[..2].iter().map(sexp).collect();
+    assert_eq!(trees, ["a", "(- 1)"]);
+
+    let code = "{\n  a\n  -1\n}\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let res = parse(&tokens).unwrap();
+    assert_eq!(sexp(&res[0]), "({ a (- 1))");
+}
+
+#[test]
+fn else_on_a_new_line() {
+    log_init();
+    // Allowed inside braces
+    let code = "{\n  if (a) b\n  else c\n}\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let res = parse(&tokens).unwrap();
+    assert_eq!(sexp(&res[0]), "({ (if a b c))");
+
+    // But not at the top level, where the newline ends the `if`
+    let code = "if (a) b\nelse c\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let err = parse(&tokens).unwrap_err();
+    assert_eq!(err.to_string(), "2:1: unexpected `else`");
+}
+
+#[test]
+fn named_call_arguments_and_formal_parameters() {
+    log_init();
+    let code = "f(a, b = 1, 'c' = 2, d = , NULL = x == y)\n\\(x, y = 2) x\nx[i, drop = ]\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let res = parse(&tokens).unwrap();
+    let text = |span: Span| &code[span.bytes];
+
+    let Expression::FunctionCall(call) = &res[0] else {
+        panic!("Expected a call, got {}", res[0]);
+    };
+    let arguments: Vec<_> = call
+        .args
+        .args
+        .iter()
+        .map(|arg| arg.0.as_ref().unwrap())
+        .map(|arg| {
+            (
+                arg.name.map(|(name, _)| &name.token),
+                arg.value.as_ref().map(|value| text(value.span().unwrap())),
+            )
+        })
+        .collect();
+    assert_eq!(
+        arguments,
+        [
+            (None, Some("a")),
+            (Some(&Token::Symbol("b")), Some("1")),
+            (Some(&Token::Literal("'c'")), Some("2")),
+            (Some(&Token::Symbol("d")), None),
+            (Some(&Token::Null), Some("x == y")),
+        ]
+    );
+    assert_eq!(text(call.args.args[3].span().unwrap()), "d = ,");
+
+    let Expression::LambdaFunction(lambda) = &res[1] else {
+        panic!("Expected a lambda, got {}", res[1]);
+    };
+    let parameters: Vec<_> = lambda
+        .args
+        .args
+        .iter()
+        .map(|arg| arg.0.as_ref().unwrap())
+        .map(|parameter| {
+            (
+                &parameter.name.token,
+                parameter
+                    .default
+                    .as_ref()
+                    .map(|(_, default)| text(default.span().unwrap())),
+            )
+        })
+        .collect();
+    assert_eq!(
+        parameters,
+        [
+            (&Token::Symbol("x"), None),
+            (&Token::Symbol("y"), Some("2"))
+        ]
+    );
+
+    let Expression::SubsetExpression(subset) = &res[2] else {
+        panic!("Expected a subset, got {}", res[2]);
+    };
+    let drop = subset.args.args[1].0.as_ref().unwrap();
+    assert_eq!(
+        drop.name.map(|(name, _)| &name.token),
+        Some(&Token::Symbol("drop"))
+    );
+    assert_eq!(drop.value, None);
+}
+
+#[test]
+fn semicolons_end_statements() {
+    log_init();
+    let code = "a <- 1; b # note\n{ c; }\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let res = parse(&tokens).unwrap();
+    assert_eq!(res[1], Expression::Semicolon(tokens[3]));
+    assert_eq!(res[2], Expression::Symbol(tokens[4]));
+    let Expression::Term(block) = &res[3] else {
+        panic!("Expected a block, got {}", res[3]);
+    };
+    assert_eq!(
+        block.term,
+        [
+            Expression::Symbol(tokens[7]),
+            Expression::Semicolon(tokens[8])
+        ]
+    );
+
+    let code = "a <- 1; # note\nb\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let res = parse(&tokens).unwrap();
+    let Expression::Semicolon(semicolon) = res[1] else {
+        panic!("Expected a semicolon, got {}", res[1]);
+    };
+    assert_eq!(semicolon.inline_comment, Some("# note"));
+    assert_eq!(res[2], Expression::Symbol(tokens[5]));
+
+    for code in ["a\n; b", "; a", "a;; b"] {
+        let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+        let tokens = pre_parse(&mut commented_tokens);
+        assert!(parse(&tokens).is_err(), "{code}");
+    }
+}
+
+#[test]
+fn pipe_placeholder() {
+    log_init();
+    // The right-hand sides of the pipes
+    let valid = [
+        ("df |> lm(y ~ x, data = _)", "(lm (~ y x) _)"),
+        ("x |> f(y = _) |> g(z = _)", "(g _)"),
+        ("x |> _$a[[1]]", "([[ ($ _ a) 1)"),
+        ("x |> _@a", "(@ _ a)"),
+    ];
+    for (code, expected) in valid {
+        let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+        let tokens = pre_parse(&mut commented_tokens);
+        let res = parse(&tokens).unwrap();
+        let Expression::Bop(_, _, rhs) = &res[0] else {
+            panic!("Expected a pipe, got {}", res[0]);
+        };
+        assert_eq!(sexp(rhs), expected, "{code}");
+    }
+
+    let invalid = [
+        (
+            "x |> f(_)",
+            "1:8: pipe placeholder can only be used as a named argument",
+        ),
+        (
+            "x |> _",
+            "1:6: pipe placeholder can only be used as a named argument",
+        ),
+        (
+            "x |> f(y = _, z = _)",
+            "1:19: pipe placeholder may only appear once",
+        ),
+        (
+            "f(x = _)",
+            "1:7: invalid use of pipe placeholder",
+        ),
+        (
+            "x |> f(y = g(_))",
+            "1:14: invalid use of pipe placeholder",
+        ),
+    ];
+    for (code, expected) in invalid {
+        let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+        let tokens = pre_parse(&mut commented_tokens);
+        let err = parse(&tokens).unwrap_err();
+        assert_eq!(err.to_string(), expected, "{code}");
+    }
+
+    let code = "a <- _\nb\nx |> f(_, y = g(_))\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let (res, errors) = parse_with_recovery(&tokens);
+    let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        errors,
+        [
+            "1:6: invalid use of pipe placeholder",
+            "3:8: pipe placeholder can only be used as a named argument",
+            "3:17: invalid use of pipe placeholder",
+        ]
+    );
+    assert_eq!(sexp(&res[0]), "(<- a _)");
+}
+
+#[test]
+fn rewriting_with_synthesised_tokens() {
+    log_init();
+    struct Rewriter<'a>(&'a TokenArena<'a>);
+
+    impl<'a> VisitorMut<'a> for Rewriter<'a> {
+        fn visit_expression(&mut self, expression: &mut Expression<'a>) {
+            if let Expression::Bop(op, _, _) = expression {
+                if op.token == Token::OldAssign {
+                    *op = self.0.replace(op, Token::LAssign);
+                }
+            }
+            walk_expression_mut(self, expression);
+        }
+
+        fn visit_if_conditional(&mut self, if_conditional: &mut IfConditional<'a>) {
+            if !matches!(&*if_conditional.body, Expression::Term(_)) {
+                let position = if_conditional.right_delimiter;
+                let lbrace = self.0.alloc(CommentedToken::new(
+                    Token::LBrace,
+                    position.line,
+                    position.end_offset,
+                ));
+                let rbrace = self.0.replace(lbrace, Token::RBrace);
+                let body = std::mem::replace(&mut *if_conditional.body, Expression::EOF(lbrace));
+                *if_conditional.body = Expression::Term(Box::new(TermExpr::new(
+                    Some(lbrace),
+                    vec![body],
+                    Some(rbrace),
+                )));
+            }
+            walk_if_conditional_mut(self, if_conditional);
+        }
+    }
+
+    let code = "x = 1\nif (a) b = 2\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let arena = TokenArena::new();
+    let mut res = parse(&tokens).unwrap();
+    res.iter_mut()
+        .for_each(|expression| Rewriter(&arena).visit_expression(expression));
+    assert_eq!(sexp(&res[0]), "(<- x 1)");
+    assert_eq!(sexp(&res[1]), "(if a ({ (<- b 2)))");
+    assert_eq!(print(&res, code), "x <- 1\nif (a) {\nb <- 2\n}\n");
+}
+
+#[test]
+fn printing_keeps_the_layout_of_unchanged_nodes() {
+    log_init();
+    let code = "# Header\r\n\r\nf <- function(a,  b = 2) {\r\n  # Body\r\n  a+b; a  # Sum\r\n}\r\nf(1)[[1]]\r\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let res = parse(&tokens).unwrap();
+    assert_eq!(print(&res, code), code);
+}
+
+#[test]
+fn serializing_to_json() {
+    log_init();
+    let code = "# c\nx <- 1\n";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let res = parse(&tokens).unwrap();
+    let token = |kind: &str, text: Option<&str>, line, offset, end_offset, start, end| {
+        let mut token = serde_json::json!({
+            "kind": kind,
+            "line": line,
+            "offset": offset,
+            "end_line": line,
+            "end_offset": end_offset,
+            "span": { "start": start, "end": end },
+            "leading_comments": null,
+            "inline_comment": null,
+            "comments_span": null,
+        });
+        if let Some(text) = text {
+            token["text"] = text.into();
+        }
+        token
+    };
+    let mut x = token("Symbol", Some("x"), 1, 0, 1, 4, 5);
+    x["leading_comments"] = serde_json::json!(["# c"]);
+    x["comments_span"] = serde_json::json!({
+        "start_line": 0,
+        "start_offset": 0,
+        "end_line": 1,
+        "end_offset": 1,
+        "bytes": { "start": 0, "end": 5 },
+    });
+    assert_eq!(
+        serde_json::to_value(&res).unwrap(),
+        serde_json::json!([
+            {
+                "type": "Bop",
+                "operator": token("LAssign", None, 1, 2, 4, 6, 8),
+                "lhs": { "type": "Symbol", "token": x },
+                "rhs": { "type": "Literal", "token": token("Literal", Some("1"), 1, 5, 6, 9, 10) },
+            },
+            { "type": "EOF", "token": token("EOF", None, 2, 0, 0, 11, 11) },
+        ])
+    );
+
+    let code = "f(a, n = 1)[[1]]";
+    let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+    let tokens = pre_parse(&mut commented_tokens);
+    let res = serde_json::to_value(parse(&tokens).unwrap()).unwrap();
+    let args = &res[0]["object_ref"]["args"];
+    assert_eq!(res[0]["type"], "SubsetExpression");
+    assert_eq!(res[0]["args"]["left_delimiter"]["type"], "DoubleBracket");
+    assert_eq!(
+        res[0]["args"]["left_delimiter"]["tokens"][1]["kind"],
+        "LBracket"
+    );
+    assert_eq!(res[0]["args"]["right_delimiter"]["type"], "DoubleBracket");
+    assert_eq!(args["args"][0]["argument"]["value"]["type"], "Symbol");
+    assert_eq!(args["args"][0]["comma"]["token"]["kind"], "Comma");
+    assert_eq!(args["args"][1]["argument"]["name"][0]["text"], "n");
+    assert_eq!(args["args"][1]["comma"], serde_json::Value::Null);
+}
+
+/// Reads a table written by R's `write.csv`, without the header.
+fn read_csv(csv: &str) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut characters = csv.chars().peekable();
+    while let Some(character) = characters.next() {
+        match (character, quoted) {
+            ('"', true) if characters.peek() == Some(&'"') => {
+                field.push('"');
+                characters.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            (character, _) => field.push(character),
+        }
+    }
+    rows.split_off(1)
+}
+
+/// Replaces the ids of parse data with the positions and the tokens of the
+/// rows, because R's ids depend on the order in which its parser works.
+fn without_ids(rows: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let describe = |id: &str| {
+        rows.iter()
+            .find(|row| row[4] == id)
+            .map_or(String::from("0"), |row| {
+                format!("{}:{}-{}:{} {}", row[0], row[1], row[2], row[3], row[6])
+            })
+    };
+    rows.iter()
+        .map(|row| {
+            let parent = match row[5].strip_prefix('-') {
+                Some(id) => format!("before {}", describe(id)),
+                None => describe(&row[5]),
+            };
+            let mut row = row.clone();
+            row[4] = String::new();
+            row[5] = parent;
+            row
+        })
+        .collect()
+}
+
+macro_rules! parse_data_test {
+    ($name:ident, $file_number:literal) => {
+        #[test]
+        fn $name() {
+            log_init();
+            let code = include_str!(concat!("parse_data/", $file_number, ".R"));
+            let expected = include_str!(concat!("parse_data/", $file_number, ".csv"));
+            let mut commented_tokens = Tokenizer::new(code).tokenize().unwrap();
+            let tokens = pre_parse(&mut commented_tokens);
+            let res = parse(&tokens).unwrap();
+            let csv = parse_data_to_csv(&parse_data(&res, code));
+            assert_eq!(without_ids(read_csv(&csv)), without_ids(read_csv(expected)));
+        }
+    };
+}
+
+parse_data_test!(parse_data_of_calls_and_comments, "001");
+parse_data_test!(parse_data_of_functions_and_conditionals, "002");
+parse_data_test!(parse_data_of_loops, "003");
+parse_data_test!(parse_data_of_operators_and_lambdas, "004");
+parse_data_test!(parse_data_of_multiline_and_unicode_text, "005");
+parse_data_test!(parse_data_of_nested_equal_assignments, "006");
@@ -1,15 +1,25 @@
 use std::{
     ffi::OsStr,
+    io::Write,
     path::{Path, PathBuf},
     str::FromStr,
 };
 
-use clap::{arg, Parser};
+use clap::{Parser, Subcommand, ValueEnum};
 use log::{info, trace, warn};
-use tergo_lib::{config::Config, tergo_format};
+use tergo_lib::{
+    config::Config, error::FormattingError, tergo_format, tergo_parse, tergo_parse_data,
+    tergo_tokens,
+};
 
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The R file or the directory of R files to format. Write it after `--`
+    /// if it is named like a command, e.g. `tergo -- parse`
     #[arg(default_value = ".")]
     path: String,
 
@@ -17,11 +27,41 @@ struct Cli {
     config: String,
 }
 
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the tokens of an R file as JSON
+    Tokens { file: PathBuf },
+    /// Print the syntax tree of an R file
+    Parse {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+    },
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum Format {
+    /// The syntax tree as JSON
+    Json,
+    /// The table of R's `getParseData` as CSV
+    Csv,
+}
+
 #[derive(Debug)]
 enum Error {
     ReadFileToString,
     WriteToFile,
-    Formatting,
+    Formatting(FormattingError),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::ReadFileToString => f.write_str("could not read the file"),
+            Error::WriteToFile => f.write_str("could not write to the file"),
+            Error::Formatting(err) => write!(f, "{err}"),
+        }
+    }
 }
 
 fn get_config(path: &Path) -> Config {
@@ -42,7 +82,7 @@ fn format_file_in_place(path: &Path, config: &Config) -> Result<(), Error> {
     })?;
     let formatted = tergo_format(&content, Some(config)).map_err(|e| {
         trace!("Error when formatting: {e}");
-        Formatting
+        Formatting(e)
     })?;
     trace!("Formatted code:\n:{}", formatted);
     std::fs::write(path, formatted).map_err(|e| {
@@ -65,14 +105,10 @@ fn list_r_files(path: &Path) -> Vec<PathBuf> {
         Err(_) => {
             trace!("{path:?} is not a directory");
             match path.extension() {
-                Some(extension) => {
-                    if extension == OsStr::new("R") || extension == OsStr::new("r") {
-                        vec![path.to_path_buf()]
-                    } else {
-                        vec![]
-                    }
+                Some(extension) if extension == OsStr::new("R") || extension == OsStr::new("r") => {
+                    vec![path.to_path_buf()]
                 }
-                None => {
+                _ => {
                     vec![]
                 }
             }
@@ -86,18 +122,55 @@ fn format_r_files(path: &Path, config_path: &Path) {
     for file in r_files {
         match format_file_in_place(&file, &config) {
             Ok(_) => info!("Formatted: {:?}", &file),
+            // Reported as `file:line:col: message`, the way compilers do
+            Err(Error::Formatting(e)) => eprintln!("{}:{e}", file.display()),
             Err(e) => {
-                warn!("Failed to format {:?}. Error: {e:?}", &file);
+                warn!("Failed to format {:?}. Error: {e}", &file);
                 trace!("Error was: {e:?}");
             }
         }
     }
 }
 
+fn print_output(path: &Path, to_output: fn(&str) -> Result<String, FormattingError>) {
+    let output = std::fs::read_to_string(path)
+        .map_err(|e| {
+            trace!("Error when reading the file {e}");
+            Error::ReadFileToString
+        })
+        .and_then(|content| to_output(&content).map_err(Error::Formatting));
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("{}:{e}", path.display());
+            std::process::exit(1);
+        }
+    };
+    match writeln!(std::io::stdout().lock(), "{}", output.trim_end()) {
+        Ok(()) => {}
+        // The reader is gone, e.g. `tergo parse big.R | head`
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            eprintln!("could not write the output: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
 fn main() {
     env_logger::init();
     let cli = Cli::parse();
 
+    match cli.command {
+        Some(Command::Tokens { file }) => return print_output(&file, tergo_tokens),
+        Some(Command::Parse { file, format }) => {
+            return match format {
+                Format::Json => print_output(&file, tergo_parse),
+                Format::Csv => print_output(&file, tergo_parse_data),
+            }
+        }
+        None => {}
+    }
     let path = PathBuf::from_str(&cli.path).unwrap();
     let config_path = PathBuf::from_str(&cli.config).unwrap();
     format_r_files(&path, &config_path);
@@ -3,7 +3,9 @@ use crate::{
     format::DocAlgebra,
 };
 
-use parser::ast::{Arg, Args, Delimiter, Expression, IfConditional, TermExpr};
+use parser::ast::{
+    Arg, Args, CallArgument, Delimiter, Expression, FormalParameter, IfConditional, TermExpr,
+};
 use tokenizer::tokens::CommentedToken;
 
 use crate::format::{
@@ -14,16 +16,22 @@ use std::{ops::Deref, rc::Rc};
 use tokenizer::Token;
 
 pub(crate) trait Code {
-    fn to_docs(&self, config: &impl FormattingConfig, doc_ref: &mut usize) -> Rc<Doc>;
+    fn to_docs(&self, config: &impl FormattingConfig, source: &str, doc_ref: &mut usize)
+        -> Rc<Doc>;
 }
 
 impl<T> Code for Option<T>
 where
     T: Code,
 {
-    fn to_docs(&self, config: &impl FormattingConfig, doc_ref: &mut usize) -> Rc<Doc> {
+    fn to_docs(
+        &self,
+        config: &impl FormattingConfig,
+        source: &str,
+        doc_ref: &mut usize,
+    ) -> Rc<Doc> {
         match self {
-            Some(inner) => inner.to_docs(config, doc_ref),
+            Some(inner) => inner.to_docs(config, source, doc_ref),
             None => text!(""),
         }
     }
@@ -33,6 +41,7 @@ pub(crate) trait CodeWithoutLeadingComments {
     fn to_docs_without_leading_comments(
         &self,
         config: &impl FormattingConfig,
+        source: &str,
         doc_ref: &mut usize,
     ) -> Rc<Doc>;
 }
@@ -44,10 +53,11 @@ where
     fn to_docs_without_leading_comments(
         &self,
         config: &impl FormattingConfig,
+        source: &str,
         doc_ref: &mut usize,
     ) -> Rc<Doc> {
         match self {
-            Some(code) => code.to_docs_without_leading_comments(config, doc_ref),
+            Some(code) => code.to_docs_without_leading_comments(config, source, doc_ref),
             None => Rc::new(Doc::Nil),
         }
     }
@@ -66,7 +76,6 @@ macro_rules! group {
         ))
     }};
 }
-pub(crate) use group;
 
 // Macro that creates a Doc::Break
 macro_rules! nl {
@@ -74,7 +83,6 @@ macro_rules! nl {
         Rc::new(Doc::Break($txt))
     };
 }
-pub(crate) use nl;
 
 // Macro that creates a Doc::Text
 macro_rules! text {
@@ -109,74 +117,26 @@ macro_rules! text {
 pub(crate) use text;
 
 impl<'a> Code for Token<'a> {
-    fn to_docs(&self, _: &impl FormattingConfig, _: &mut usize) -> Rc<Doc> {
+    fn to_docs(&self, _: &impl FormattingConfig, _: &str, _: &mut usize) -> Rc<Doc> {
         match self {
-            Token::Symbol(s) | Token::Literal(s) => text!(*s),
-            Token::Semicolon => text!(";"),
-            Token::Newline => text!("\n"),
-            Token::LParen => text!("("),
-            Token::RParen => text!(")"),
-            Token::LBrace => text!("{"),
-            Token::RBrace => text!("}"),
-            Token::LBracket => text!("["),
-            Token::RBracket => text!("]"),
-            Token::Comma => text!(","),
-            Token::Continue => text!("continue"),
-            Token::Break => text!("break"),
-            Token::Stop => text!("stop"),
-            Token::If => text!("if"),
-            Token::Else => text!("else"),
-            Token::While => text!("while"),
-            Token::For => text!("for"),
-            Token::Repeat => text!("repeat"),
-            Token::In => text!("in"),
-            Token::Function => text!("function"),
-            Token::Lambda => text!("\\"),
-            Token::LAssign => text!("<-"),
-            Token::SuperAssign => text!("<<-"),
-            Token::ColonAssign => text!(":="),
-            Token::RAssign => text!("->"),
-            Token::OldAssign => text!("="),
-            Token::Equal => text!("=="),
-            Token::NotEqual => text!("!="),
-            Token::LowerThan => text!("<"),
-            Token::GreaterThan => text!(">"),
-            Token::LowerEqual => text!("<="),
-            Token::GreaterEqual => text!(">="),
-            Token::Power => text!("^"),
-            Token::Divide => text!("/"),
-            Token::Multiply => text!("*"),
-            Token::Minus => text!("-"),
-            Token::Plus => text!("+"),
-            Token::Help => text!("?"),
-            Token::And => text!("&&"),
-            Token::VectorizedAnd => text!("&"),
-            Token::Or => text!("||"),
-            Token::VectorizedOr => text!("|"),
-            Token::Dollar => text!("$"),
-            Token::Pipe => text!("|>"),
-            Token::Modulo => text!("%"),
-            Token::NsGet => text!("::"),
-            Token::NsGetInt => text!(":::"),
-            Token::Tilde => text!("~"),
-            Token::Colon => text!(":"),
-            Token::Slot => text!("@"),
-            Token::Special(s) => text!(*s),
-            Token::UnaryNot => text!("!"),
             Token::InlineComment(s) => text!(*s, 0),
-            Token::Comment(s) => text!(*s),
-            Token::EOF => text!(""),
+            token => text!(token.text()),
         }
     }
 }
 
 impl Code for CommentedToken<'_> {
-    fn to_docs(&self, config: &impl FormattingConfig, doc_ref: &mut usize) -> Rc<Doc> {
+    fn to_docs(
+        &self,
+        config: &impl FormattingConfig,
+        source: &str,
+        doc_ref: &mut usize,
+    ) -> Rc<Doc> {
         match (&self.leading_comments, self.inline_comment) {
-            (None, None) => self.token.to_docs(config, doc_ref),
+            (None, None) => self.token.to_docs(config, source, doc_ref),
             (None, Some(inline_comment)) => self
                 .token
-                .to_docs(config, doc_ref)
+                .to_docs(config, source, doc_ref)
                 .cons(text!(" "))
                 .cons(text!(inline_comment, 0, InlineCommentPosition::End)),
             (Some(leading_comments), None) => {
@@ -193,7 +153,7 @@ impl Code for CommentedToken<'_> {
                     .cons(nl!(""))
                     .cons(
                         self.token
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .to_group(ShouldBreak::No, &mut 0),
                     )
                     .to_group(ShouldBreak::Yes, &mut 0)
@@ -212,7 +172,7 @@ impl Code for CommentedToken<'_> {
                     .cons(nl!(""))
                     .cons(
                         self.token
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .cons(text!(" "))
                             .cons(text!(inline_comment, 0, InlineCommentPosition::End))
                             .to_group(ShouldBreak::No, &mut 0),
@@ -227,13 +187,14 @@ impl CodeWithoutLeadingComments for CommentedToken<'_> {
     fn to_docs_without_leading_comments(
         &self,
         config: &impl FormattingConfig,
+        source: &str,
         doc_ref: &mut usize,
     ) -> Rc<Doc> {
         match self.inline_comment {
-            None => self.token.to_docs(config, doc_ref),
+            None => self.token.to_docs(config, source, doc_ref),
             Some(inline_comment) => self
                 .token
-                .to_docs(config, doc_ref)
+                .to_docs(config, source, doc_ref)
                 .cons(text!(" "))
                 .cons(text!(inline_comment, 0, InlineCommentPosition::End)),
         }
@@ -241,16 +202,70 @@ impl CodeWithoutLeadingComments for CommentedToken<'_> {
 }
 
 impl Code for Delimiter<'_> {
-    fn to_docs(&self, config: &impl FormattingConfig, doc_ref: &mut usize) -> Rc<Doc> {
+    fn to_docs(
+        &self,
+        config: &impl FormattingConfig,
+        source: &str,
+        doc_ref: &mut usize,
+    ) -> Rc<Doc> {
         match self {
             Delimiter::Paren(single) | Delimiter::SingleBracket(single) => {
-                single.to_docs(config, doc_ref)
+                single.to_docs(config, source, doc_ref)
             }
             Delimiter::DoubleBracket((b1, b2)) => b1
-                .to_docs(config, doc_ref)
-                .cons(b2.to_docs(config, doc_ref)),
+                .to_docs(config, source, doc_ref)
+                .cons(b2.to_docs(config, source, doc_ref)),
+        }
+    }
+}
+
+/// Returns the docs of the lines of a program or a block. A statement ending
+/// with `;` shares its line with the statements after it on the same line,
+/// unless `split_semicolon_statements` is set. Then the `;` is dropped
+/// and only its comment is kept.
+fn statements_to_docs(
+    statements: &[Expression],
+    config: &impl FormattingConfig,
+    source: &str,
+    doc_ref: &mut usize,
+) -> Vec<Rc<Doc>> {
+    let mut docs = vec![];
+    let mut statements = statements.iter().peekable();
+    while let Some(statement) = statements.next() {
+        let mut doc = statement
+            .to_docs(config, source, doc_ref)
+            .to_group(ShouldBreak::No, doc_ref);
+        while let Some(Expression::Semicolon(semicolon)) = statements.peek() {
+            statements.next();
+            if config.split_semicolon_statements() {
+                if let Some(comment) = semicolon.inline_comment {
+                    doc = doc
+                        .cons(text!(" "))
+                        .cons(text!(comment, 0, InlineCommentPosition::End));
+                }
+                break;
+            }
+            doc = doc.cons(semicolon.to_docs(config, source, doc_ref));
+            let same_line = |next: &&Expression| {
+                semicolon.inline_comment.is_none()
+                    && !matches!(next, Expression::Whitespace(_) | Expression::EOF(_))
+                    && next
+                        .span()
+                        .is_some_and(|span| span.start_line == semicolon.line)
+            };
+            match statements.next_if(same_line) {
+                Some(next) => {
+                    doc = doc.cons(text!(" ")).cons(
+                        next.to_docs(config, source, doc_ref)
+                            .to_group(ShouldBreak::No, doc_ref),
+                    )
+                }
+                None => break,
+            }
         }
+        docs.push(doc);
     }
+    docs
 }
 
 /// Returns a Doc::Group
@@ -293,13 +308,19 @@ where
 }
 
 impl<'a> Code for Expression<'a> {
-    fn to_docs(&self, config: &impl FormattingConfig, doc_ref: &mut usize) -> Rc<Doc> {
+    fn to_docs(
+        &self,
+        config: &impl FormattingConfig,
+        source: &str,
+        doc_ref: &mut usize,
+    ) -> Rc<Doc> {
         match self {
             Expression::Symbol(token)
             | Expression::Literal(token)
+            | Expression::Placeholder(token)
             | Expression::Comment(token)
             | Expression::Continue(token)
-            | Expression::Break(token) => token.to_docs(config, doc_ref),
+            | Expression::Break(token) => token.to_docs(config, source, doc_ref),
             Expression::Term(term_expr) => match &**term_expr {
                 // Case for the embracing operator
                 TermExpr {
@@ -320,7 +341,7 @@ impl<'a> Code for Expression<'a> {
                                 let inner_docs: Vec<_> = inner_term_expr
                                     .term
                                     .iter()
-                                    .map(|t| t.to_docs(config, doc_ref))
+                                    .map(|t| t.to_docs(config, source, doc_ref))
                                     .collect();
                                 let inner_docs = join_docs(
                                     inner_docs,
@@ -330,13 +351,13 @@ impl<'a> Code for Expression<'a> {
                                     doc_ref,
                                 );
                                 pre_delim
-                                    .to_docs(config, doc_ref)
+                                    .to_docs(config, source, doc_ref)
                                     .cons(
                                         inner_term_expr
                                             .pre_delimiters
                                             .as_ref()
                                             .unwrap()
-                                            .to_docs(config, doc_ref),
+                                            .to_docs(config, source, doc_ref),
                                     )
                                     .cons(text!(" "))
                                     .cons(inner_docs)
@@ -346,13 +367,15 @@ impl<'a> Code for Expression<'a> {
                                             .post_delimiters
                                             .as_ref()
                                             .unwrap()
-                                            .to_docs(config, doc_ref),
+                                            .to_docs(config, source, doc_ref),
                                     )
-                                    .cons(post_delim.to_docs(config, doc_ref))
+                                    .cons(post_delim.to_docs(config, source, doc_ref))
                                     .to_group(ShouldBreak::No, doc_ref)
                             } else {
-                                let docs: Vec<_> =
-                                    term.iter().map(|t| t.to_docs(config, doc_ref)).collect();
+                                let docs: Vec<_> = term
+                                    .iter()
+                                    .map(|t| t.to_docs(config, source, doc_ref))
+                                    .collect();
                                 let inner = join_docs(
                                     docs,
                                     Rc::new(Doc::Nil),
@@ -361,10 +384,10 @@ impl<'a> Code for Expression<'a> {
                                     doc_ref,
                                 );
                                 pre_delim
-                                    .to_docs(config, doc_ref)
+                                    .to_docs(config, source, doc_ref)
                                     .cons(nl!(" ").cons(inner).nest(config.indent()))
                                     .cons(nl!(" "))
-                                    .cons(post_delim.to_docs(config, doc_ref))
+                                    .cons(post_delim.to_docs(config, source, doc_ref))
                                     .to_group(ShouldBreak::Yes, doc_ref)
                             }
                         }
@@ -378,19 +401,13 @@ impl<'a> Code for Expression<'a> {
                 } if matches!(pre_delim.token, Token::LBrace) => {
                     if term.is_empty() {
                         pre_delim
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .cons(nl!(""))
                             .nest(config.indent())
-                            .cons(post_delim.to_docs(config, doc_ref))
+                            .cons(post_delim.to_docs(config, source, doc_ref))
                             .to_group(ShouldBreak::No, &mut 0)
                     } else {
-                        let docs = term
-                            .iter()
-                            .map(|t| {
-                                t.to_docs(config, doc_ref)
-                                    .to_group(ShouldBreak::No, doc_ref)
-                            })
-                            .collect::<Vec<_>>();
+                        let docs = statements_to_docs(term, config, source, doc_ref);
                         let inner =
                             join_docs(docs, Rc::new(Doc::Nil), ShouldBreak::Yes, config, doc_ref);
                         delimited_content_to_docs(
@@ -398,6 +415,7 @@ impl<'a> Code for Expression<'a> {
                             inner,
                             post_delim,
                             config,
+                            source,
                             doc_ref,
                             ShouldBreak::Yes,
                         )
@@ -408,13 +426,7 @@ impl<'a> Code for Expression<'a> {
                     term,
                     post_delimiters: None,
                 } => {
-                    let docs = term
-                        .iter()
-                        .map(|t| {
-                            t.to_docs(config, doc_ref)
-                                .to_group(ShouldBreak::No, doc_ref)
-                        })
-                        .collect::<Vec<_>>();
+                    let docs = statements_to_docs(term, config, source, doc_ref);
                     join_docs(docs, Rc::new(Doc::Nil), ShouldBreak::Yes, config, doc_ref)
                 }
                 TermExpr {
@@ -424,8 +436,8 @@ impl<'a> Code for Expression<'a> {
                 } => {
                     if term.is_empty() {
                         pre_delim
-                            .to_docs(config, doc_ref)
-                            .cons(post_delim.to_docs(config, doc_ref))
+                            .to_docs(config, source, doc_ref)
+                            .cons(post_delim.to_docs(config, source, doc_ref))
                     } else if term.len() == 1 && matches!(term[0], Expression::Term(..)) {
                         // Special case for these scenarios
                         // ({
@@ -435,19 +447,19 @@ impl<'a> Code for Expression<'a> {
                         // In these cases we delegate the line breaks to the inner term.
                         let docs = term
                             .iter()
-                            .map(|t| t.to_docs(config, doc_ref))
+                            .map(|t| t.to_docs(config, source, doc_ref))
                             .collect::<Vec<_>>();
                         let inner =
                             join_docs(docs, Rc::new(Doc::Nil), ShouldBreak::No, config, doc_ref);
                         pre_delim
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .cons(inner)
-                            .cons(post_delim.to_docs(config, doc_ref))
+                            .cons(post_delim.to_docs(config, source, doc_ref))
                             .to_group(ShouldBreak::No, doc_ref)
                     } else {
                         let docs = term
                             .iter()
-                            .map(|t| t.to_docs(config, doc_ref))
+                            .map(|t| t.to_docs(config, source, doc_ref))
                             .collect::<Vec<_>>();
                         let inner =
                             join_docs(docs, Rc::new(Doc::Nil), ShouldBreak::No, config, doc_ref);
@@ -456,6 +468,7 @@ impl<'a> Code for Expression<'a> {
                             inner,
                             post_delim,
                             config,
+                            source,
                             doc_ref,
                             ShouldBreak::No,
                         )
@@ -464,23 +477,15 @@ impl<'a> Code for Expression<'a> {
                 _ => panic!("Term with not matching delimiters found"),
             },
             Expression::Unary(op, expr) => op
-                .to_docs(config, doc_ref)
-                .cons(expr.to_docs(config, doc_ref)),
+                .to_docs(config, source, doc_ref)
+                .cons(expr.to_docs(config, source, doc_ref)),
             Expression::Bop(op, lhs, rhs) => match op.token {
-                Token::OldAssign | Token::LAssign | Token::ColonAssign | Token::SuperAssign
-                    if !config.allow_nl_after_assignment() =>
-                {
-                    lhs.to_docs(config, doc_ref)
-                        .cons(text!(" "))
-                        .cons(op.to_docs(config, doc_ref))
-                        .cons(text!(" ").cons(rhs.to_docs(config, doc_ref)))
-                        .to_group(ShouldBreak::No, doc_ref)
+                Token::OldAssign | Token::LAssign | Token::ColonAssign | Token::SuperAssign => {
+                    let lhs = lhs.to_docs(config, source, doc_ref);
+                    assignment_to_docs(lhs, op, Some(rhs.as_ref()), config, source, doc_ref)
                 }
-                Token::LAssign
-                | Token::SuperAssign
-                | Token::ColonAssign
-                | Token::RAssign
-                | Token::OldAssign
+                Token::RAssign
+                | Token::SuperRAssign
                 | Token::Equal
                 | Token::NotEqual
                 | Token::LowerThan
@@ -499,12 +504,12 @@ impl<'a> Code for Expression<'a> {
                 | Token::Modulo
                 | Token::Tilde
                 | Token::Special(_) => lhs
-                    .to_docs(config, doc_ref)
+                    .to_docs(config, source, doc_ref)
                     .cons(text!(" "))
-                    .cons(op.to_docs(config, doc_ref))
+                    .cons(op.to_docs(config, source, doc_ref))
                     .cons(
                         nl!(" ")
-                            .cons(rhs.to_docs(config, doc_ref))
+                            .cons(rhs.to_docs(config, source, doc_ref))
                             .nest(config.indent()),
                     ),
                 Token::Dollar
@@ -514,9 +519,9 @@ impl<'a> Code for Expression<'a> {
                 | Token::Slot
                 | Token::Power
                 | Token::Help => lhs
-                    .to_docs(config, doc_ref)
-                    .cons(op.to_docs(config, doc_ref))
-                    .cons(rhs.to_docs(config, doc_ref))
+                    .to_docs(config, source, doc_ref)
+                    .cons(op.to_docs(config, source, doc_ref))
+                    .cons(rhs.to_docs(config, source, doc_ref))
                     .to_group(ShouldBreak::No, doc_ref),
                 _ => panic!(
                     "Got a not a binary operator token inside a binary expression when \
@@ -525,16 +530,18 @@ impl<'a> Code for Expression<'a> {
                 ),
             },
             Expression::Formula(tilde, term) => tilde
-                .to_docs(config, doc_ref)
+                .to_docs(config, source, doc_ref)
                 .cons(if matches!(**term, Expression::Symbol(_)) {
                     text!("")
                 } else {
                     text!(" ")
                 })
-                .cons(term.to_docs(config, doc_ref)),
+                .cons(term.to_docs(config, source, doc_ref)),
             Expression::Newline(_) => Rc::new(Doc::Break("\n")),
-            Expression::EOF(eof) => eof.to_docs(config, doc_ref),
+            Expression::Semicolon(semicolon) => semicolon.to_docs(config, source, doc_ref),
+            Expression::EOF(eof) => eof.to_docs(config, source, doc_ref),
             Expression::Whitespace(_) => text!(""),
+            Expression::Error(tokens) => verbatim_to_docs(tokens, source),
             Expression::FunctionDef(function_def) => {
                 let (keyword, args, body) = (
                     function_def.keyword,
@@ -546,11 +553,11 @@ impl<'a> Code for Expression<'a> {
                         let args_doc = join_docs_ungroupped(
                             args.args.iter().map(|arg| {
                                 arg.0
-                                    .to_docs(config, doc_ref)
+                                    .to_docs(config, source, doc_ref)
                                     .cons(
                                         arg.1
                                             .as_ref()
-                                            .map(|sep| sep.to_docs(config, doc_ref))
+                                            .map(|sep| sep.to_docs(config, source, doc_ref))
                                             .unwrap_or(Rc::new(Doc::Nil)),
                                     )
                                     .to_group(ShouldBreak::No, doc_ref)
@@ -560,26 +567,26 @@ impl<'a> Code for Expression<'a> {
                         );
                         let args_group = args
                             .left_delimeter
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .cons(args_doc.nest_hanging())
-                            .cons(args.right_delimeter.to_docs(config, doc_ref))
+                            .cons(args.right_delimeter.to_docs(config, source, doc_ref))
                             .to_group(ShouldBreak::No, doc_ref);
                         keyword
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .cons(args_group)
                             .cons(text!(" "))
-                            .cons(body.to_docs(config, doc_ref))
+                            .cons(body.to_docs(config, source, doc_ref))
                             .to_group(ShouldBreak::No, doc_ref)
                     }
                     FunctionLineBreaks::Double => {
                         let args_doc = join_docs_ungroupped(
                             args.args.iter().map(|arg| {
                                 arg.0
-                                    .to_docs(config, doc_ref)
+                                    .to_docs(config, source, doc_ref)
                                     .cons(
                                         arg.1
                                             .as_ref()
-                                            .map(|sep| sep.to_docs(config, doc_ref))
+                                            .map(|sep| sep.to_docs(config, source, doc_ref))
                                             .unwrap_or(Rc::new(Doc::Nil)),
                                     )
                                     .to_group(ShouldBreak::No, doc_ref)
@@ -589,29 +596,29 @@ impl<'a> Code for Expression<'a> {
                         );
                         let args_group = args
                             .left_delimeter
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .cons(nl!(""))
                             .cons(args_doc)
                             .nest(2 * config.indent())
                             .cons(nl!(""))
-                            .cons(args.right_delimeter.to_docs(config, doc_ref))
+                            .cons(args.right_delimeter.to_docs(config, source, doc_ref))
                             .to_group(ShouldBreak::No, doc_ref);
                         keyword
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .cons(args_group)
                             .cons(text!(" "))
-                            .cons(body.to_docs(config, doc_ref))
+                            .cons(body.to_docs(config, source, doc_ref))
                             .to_group(ShouldBreak::No, doc_ref)
                     }
                     FunctionLineBreaks::Single => {
                         let args_doc = join_docs_ungroupped(
                             args.args.iter().map(|arg| {
                                 arg.0
-                                    .to_docs(config, doc_ref)
+                                    .to_docs(config, source, doc_ref)
                                     .cons(
                                         arg.1
                                             .as_ref()
-                                            .map(|sep| sep.to_docs(config, doc_ref))
+                                            .map(|sep| sep.to_docs(config, source, doc_ref))
                                             .unwrap_or(Rc::new(Doc::Nil)),
                                     )
                                     .to_group(ShouldBreak::No, doc_ref)
@@ -621,18 +628,18 @@ impl<'a> Code for Expression<'a> {
                         );
                         let args_group = args
                             .left_delimeter
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .cons(nl!(""))
                             .cons(args_doc)
                             .nest(config.indent())
                             .cons(nl!(""))
-                            .cons(args.right_delimeter.to_docs(config, doc_ref))
+                            .cons(args.right_delimeter.to_docs(config, source, doc_ref))
                             .to_group(ShouldBreak::No, doc_ref);
                         keyword
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .cons(args_group)
                             .cons(text!(" "))
-                            .cons(body.to_docs(config, doc_ref))
+                            .cons(body.to_docs(config, source, doc_ref))
                             .to_group(ShouldBreak::No, doc_ref)
                     }
                 }
@@ -654,19 +661,19 @@ impl<'a> Code for Expression<'a> {
                             &if_conditional.body,
                         );
                         let condition_docs = left_delim
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .cons(nl!(""))
-                            .cons(condition.to_docs(config, doc_ref))
+                            .cons(condition.to_docs(config, source, doc_ref))
                             .nest(config.indent())
                             .cons(nl!(""))
-                            .cons(right_delim.to_docs(config, doc_ref))
+                            .cons(right_delim.to_docs(config, source, doc_ref))
                             .to_group(ShouldBreak::No, doc_ref);
                         keyword
-                            .to_docs(config, doc_ref)
+                            .to_docs(config, source, doc_ref)
                             .cons(text!(" "))
                             .cons(condition_docs)
                             .cons(text!(" "))
-                            .cons(body.to_docs(config, doc_ref))
+                            .cons(body.to_docs(config, source, doc_ref))
                     };
                 let mut docs = if_conditional_to_docs(if_conditional, doc_ref);
                 for else_if in else_ifs {
@@ -674,7 +681,7 @@ impl<'a> Code for Expression<'a> {
                         (else_if.else_keyword, &else_if.if_conditional);
                     docs = docs
                         .cons(text!(" "))
-                        .cons(else_keyword.to_docs(config, doc_ref))
+                        .cons(else_keyword.to_docs(config, source, doc_ref))
                         .cons(text!(" "))
                         .cons(if_conditional_to_docs(conditional, doc_ref));
                 }
@@ -682,9 +689,9 @@ impl<'a> Code for Expression<'a> {
                     let (else_keyword, body) = (&trailing_else.else_keyword, &trailing_else.body);
                     docs = docs
                         .cons(text!(" "))
-                        .cons(else_keyword.to_docs(config, doc_ref))
+                        .cons(else_keyword.to_docs(config, source, doc_ref))
                         .cons(text!(" "))
-                        .cons(body.to_docs(config, doc_ref));
+                        .cons(body.to_docs(config, source, doc_ref));
                 }
                 docs
             }
@@ -695,18 +702,18 @@ impl<'a> Code for Expression<'a> {
                     &while_expression.body,
                 );
                 keyword
-                    .to_docs(config, doc_ref)
+                    .to_docs(config, source, doc_ref)
                     .cons(text!(" "))
-                    .cons(condition.to_docs(config, doc_ref))
+                    .cons(condition.to_docs(config, source, doc_ref))
                     .cons(text!(" "))
-                    .cons(body.to_docs(config, doc_ref))
+                    .cons(body.to_docs(config, source, doc_ref))
                     .to_group(ShouldBreak::No, doc_ref)
             }
             Expression::RepeatExpression(repeat_expression) => {
                 let (keyword, body) = (&repeat_expression.repeat_keyword, &repeat_expression.body);
                 keyword
-                    .to_docs(config, doc_ref)
-                    .cons(body.to_docs(config, doc_ref))
+                    .to_docs(config, source, doc_ref)
+                    .cons(body.to_docs(config, source, doc_ref))
                     .to_group(ShouldBreak::No, doc_ref)
             }
             Expression::FunctionCall(function_call) => {
@@ -725,7 +732,7 @@ impl<'a> Code for Expression<'a> {
                 let group_ref = *doc_ref + 1;
                 *doc_ref += 1;
                 let inner_docs =
-                    args_to_docs_with_conditional_nest(args, config, doc_ref, group_ref);
+                    args_to_docs_with_conditional_nest(args, config, source, doc_ref, group_ref);
                 if is_function_ref_quote
                     && args.args.len() == 1
                     && args.args[0].0.is_some()
@@ -747,13 +754,17 @@ impl<'a> Code for Expression<'a> {
                     // One of the few case it makes some miniscule
                     // sense to have more indent
                     group!(
-                        function_ref.to_docs(config, doc_ref).cons(inner_docs),
+                        function_ref
+                            .to_docs(config, source, doc_ref)
+                            .cons(inner_docs),
                         ShouldBreak::Yes,
                         group_ref
                     )
                 } else {
                     group!(
-                        function_ref.to_docs(config, doc_ref).cons(inner_docs),
+                        function_ref
+                            .to_docs(config, source, doc_ref)
+                            .cons(inner_docs),
                         should_break_args(args),
                         group_ref
                     )
@@ -762,8 +773,8 @@ impl<'a> Code for Expression<'a> {
             Expression::SubsetExpression(subset_expression) => {
                 let (object_ref, args) = (&subset_expression.object_ref, &subset_expression.args);
                 object_ref
-                    .to_docs(config, doc_ref)
-                    .cons(args.to_docs(config, doc_ref))
+                    .to_docs(config, source, doc_ref)
+                    .cons(args.to_docs(config, source, doc_ref))
                     .to_group(should_break_args(args), doc_ref)
             }
             Expression::ForLoopExpression(for_loop) => {
@@ -777,77 +788,169 @@ impl<'a> Code for Expression<'a> {
                     &for_loop.body,
                 );
                 keyword
-                    .to_docs(config, doc_ref)
+                    .to_docs(config, source, doc_ref)
                     .cons(
                         text!(" ")
-                            .cons(left_delim.to_docs(config, doc_ref))
+                            .cons(left_delim.to_docs(config, source, doc_ref))
                             .cons(nl!(""))
-                            .cons(identifier.to_docs(config, doc_ref))
+                            .cons(identifier.to_docs(config, source, doc_ref))
                             .cons(text!(" "))
-                            .cons(in_keyword.to_docs(config, doc_ref))
+                            .cons(in_keyword.to_docs(config, source, doc_ref))
                             .cons(nl!(" "))
-                            .cons(collection.to_docs(config, doc_ref))
+                            .cons(collection.to_docs(config, source, doc_ref))
                             .nest(config.indent()),
                     )
                     .cons(nl!(""))
-                    .cons(right_delim.to_docs(config, doc_ref))
+                    .cons(right_delim.to_docs(config, source, doc_ref))
                     .to_group(ShouldBreak::No, doc_ref)
                     .cons(text!(" "))
-                    .cons(body.to_docs(config, doc_ref))
+                    .cons(body.to_docs(config, source, doc_ref))
                     .to_group(ShouldBreak::No, doc_ref)
             }
             Expression::LambdaFunction(lambda) => {
                 let (keyword, args, body) = (&lambda.keyword, &lambda.args, &lambda.body);
                 keyword
-                    .to_docs(config, doc_ref)
-                    .cons(args.to_docs(config, doc_ref))
+                    .to_docs(config, source, doc_ref)
+                    .cons(args.to_docs(config, source, doc_ref))
                     .cons(text!(" "))
-                    .cons(body.to_docs(config, doc_ref))
+                    .cons(body.to_docs(config, source, doc_ref))
                     .to_group(ShouldBreak::No, doc_ref)
             }
         }
     }
 }
 
-impl Code for Args<'_> {
-    fn to_docs(&self, config: &impl FormattingConfig, doc_ref: &mut usize) -> Rc<Doc> {
+impl<T: Code> Code for Args<'_, T> {
+    fn to_docs(
+        &self,
+        config: &impl FormattingConfig,
+        source: &str,
+        doc_ref: &mut usize,
+    ) -> Rc<Doc> {
         let inside_delims = self
             .args
             .iter()
             .map(|arg| {
-                arg.to_docs(config, doc_ref)
+                arg.to_docs(config, source, doc_ref)
                     .to_group(ShouldBreak::No, doc_ref)
             })
             .reduce(|first, second| first.cons(nl!(" ")).cons(second));
 
         if let Some(inside_delims) = inside_delims {
             self.left_delimeter
-                .to_docs(config, doc_ref)
+                .to_docs(config, source, doc_ref)
                 .cons(nl!("").cons(inside_delims).nest(config.indent()))
                 .cons(nl!(""))
-                .cons(self.right_delimeter.to_docs(config, doc_ref))
+                .cons(self.right_delimeter.to_docs(config, source, doc_ref))
         } else {
             self.left_delimeter
-                .to_docs(config, doc_ref)
-                .cons(self.right_delimeter.to_docs(config, doc_ref))
+                .to_docs(config, source, doc_ref)
+                .cons(self.right_delimeter.to_docs(config, source, doc_ref))
         }
     }
 }
-impl Code for Arg<'_> {
-    fn to_docs(&self, config: &impl FormattingConfig, doc_ref: &mut usize) -> Rc<Doc> {
+impl<T: Code> Code for Arg<'_, T> {
+    fn to_docs(
+        &self,
+        config: &impl FormattingConfig,
+        source: &str,
+        doc_ref: &mut usize,
+    ) -> Rc<Doc> {
         if let Some(comma) = &self.1 {
             self.0
-                .to_docs(config, doc_ref)
-                .cons(comma.to_docs(config, doc_ref))
+                .to_docs(config, source, doc_ref)
+                .cons(comma.to_docs(config, source, doc_ref))
         } else {
-            self.0.to_docs(config, doc_ref)
+            self.0.to_docs(config, source, doc_ref)
+        }
+    }
+}
+
+impl Code for CallArgument<'_> {
+    fn to_docs(
+        &self,
+        config: &impl FormattingConfig,
+        source: &str,
+        doc_ref: &mut usize,
+    ) -> Rc<Doc> {
+        match self.name {
+            Some((name, equal_sign)) => {
+                let name = name.to_docs(config, source, doc_ref);
+                assignment_to_docs(
+                    name,
+                    equal_sign,
+                    self.value.as_ref(),
+                    config,
+                    source,
+                    doc_ref,
+                )
+            }
+            None => self.value.to_docs(config, source, doc_ref),
+        }
+    }
+}
+
+impl Code for FormalParameter<'_> {
+    fn to_docs(
+        &self,
+        config: &impl FormattingConfig,
+        source: &str,
+        doc_ref: &mut usize,
+    ) -> Rc<Doc> {
+        let name = self.name.to_docs(config, source, doc_ref);
+        match &self.default {
+            Some((equal_sign, default)) => {
+                assignment_to_docs(name, equal_sign, Some(default), config, source, doc_ref)
+            }
+            None => name,
         }
     }
 }
 
+/// Formats `lhs op rhs` for the assignment operators, named arguments and
+/// default values of parameters. The value of a named argument can be missing,
+/// as in `alist(x = )`.
+fn assignment_to_docs(
+    lhs: Rc<Doc>,
+    op: &CommentedToken,
+    rhs: Option<&Expression>,
+    config: &impl FormattingConfig,
+    source: &str,
+    doc_ref: &mut usize,
+) -> Rc<Doc> {
+    let lhs = lhs
+        .cons(text!(" "))
+        .cons(op.to_docs(config, source, doc_ref));
+    let Some(rhs) = rhs else {
+        return lhs;
+    };
+    if config.allow_nl_after_assignment() {
+        lhs.cons(
+            nl!(" ")
+                .cons(rhs.to_docs(config, source, doc_ref))
+                .nest(config.indent()),
+        )
+    } else {
+        lhs.cons(text!(" ").cons(rhs.to_docs(config, source, doc_ref)))
+            .to_group(ShouldBreak::No, doc_ref)
+    }
+}
+
+/// Prints a statement that failed to parse exactly as it is in `source`,
+/// together with the comments attached to its tokens.
+fn verbatim_to_docs(tokens: &[&CommentedToken], source: &str) -> Rc<Doc> {
+    let text = Expression::Error(tokens)
+        .span()
+        .and_then(|span| source.get(span.bytes))
+        .unwrap_or_default();
+    let width = text.lines().next().map_or(0, |line| line.chars().count());
+    text!(text, width)
+}
+
 fn args_to_docs_with_conditional_nest(
     args: &Args,
     config: &impl FormattingConfig,
+    source: &str,
     doc_ref: &mut usize,
     observed_doc: usize,
 ) -> Rc<Doc> {
@@ -855,7 +958,7 @@ fn args_to_docs_with_conditional_nest(
         .args
         .iter()
         .map(|arg| {
-            arg.to_docs(config, doc_ref)
+            arg.to_docs(config, source, doc_ref)
                 .to_group(ShouldBreak::No, doc_ref)
         })
         .reduce(|first, second| first.cons(nl!(" ")).cons(second));
@@ -865,14 +968,14 @@ fn args_to_docs_with_conditional_nest(
             .cons(inside_delims)
             .nest_if_break(config.indent(), observed_doc);
         args.left_delimeter
-            .to_docs(config, doc_ref)
+            .to_docs(config, source, doc_ref)
             .cons(nested_inside_delims)
             .cons(nl!(""))
-            .cons(args.right_delimeter.to_docs(config, doc_ref))
+            .cons(args.right_delimeter.to_docs(config, source, doc_ref))
     } else {
         args.left_delimeter
-            .to_docs(config, doc_ref)
-            .cons(args.right_delimeter.to_docs(config, doc_ref))
+            .to_docs(config, source, doc_ref)
+            .cons(args.right_delimeter.to_docs(config, source, doc_ref))
     }
 }
 
@@ -915,7 +1018,8 @@ fn should_break_args(args: &Args) -> ShouldBreak {
 
     if args.args.len() >= 2
         && args.args.iter().take(args.args.len() - 1).any(|arg| {
-            arg.0.iter().any(|expr| {
+            let values = arg.0.iter().filter(|arg| arg.name.is_none());
+            values.filter_map(|arg| arg.value.as_ref()).any(|expr| {
             matches!(expr, Expression::Term(term_expr) if !is_embracing_operator_closure(term_expr))
                 || matches!(expr, Expression::FunctionDef(..))
         })
@@ -974,6 +1078,7 @@ fn delimited_content_to_docs(
     inner: Rc<Doc>,
     right_delim: &CommentedToken<'_>,
     config: &impl FormattingConfig,
+    source: &str,
     doc_ref: &mut usize,
     should_break: ShouldBreak,
 ) -> Rc<Doc> {
@@ -992,7 +1097,7 @@ fn delimited_content_to_docs(
             .nest_hanging()
             .to_group(ShouldBreak::Yes, &mut 0);
         left_delim
-            .to_docs(config, doc_ref)
+            .to_docs(config, source, doc_ref)
             .cons(
                 nl().cons(inner)
                     .cons(nl!(""))
@@ -1000,14 +1105,14 @@ fn delimited_content_to_docs(
                     .nest(config.indent()),
             )
             .cons(nl())
-            .cons(right_delim.to_docs_without_leading_comments(config, doc_ref))
+            .cons(right_delim.to_docs_without_leading_comments(config, source, doc_ref))
             .to_group(ShouldBreak::Yes, doc_ref)
     } else {
         left_delim
-            .to_docs(config, doc_ref)
+            .to_docs(config, source, doc_ref)
             .cons(nl().cons(inner).nest(config.indent()))
             .cons(nl())
-            .cons(right_delim.to_docs_without_leading_comments(config, doc_ref))
+            .cons(right_delim.to_docs_without_leading_comments(config, source, doc_ref))
             .to_group(should_break, doc_ref)
     }
 }
@@ -1015,7 +1120,7 @@ fn delimited_content_to_docs(
 #[cfg(test)]
 mod tests {
     use crate::{
-        config::FunctionLineBreaks,
+        config::{FunctionLineBreaks, LineEnding},
         format::{format_to_sdoc, simple_doc_to_string, Mode},
     };
 
@@ -1053,6 +1158,18 @@ mod tests {
         fn insert_newline_in_quote_call(&self) -> bool {
             true
         }
+
+        fn line_ending(&self) -> LineEnding {
+            LineEnding::Lf
+        }
+
+        fn expand_logical_abbreviations(&self) -> bool {
+            false
+        }
+
+        fn split_semicolon_statements(&self) -> bool {
+            true
+        }
     }
     impl std::fmt::Display for MockConfig {
         fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -1080,7 +1197,7 @@ mod tests {
         let mut s = HashSet::default();
         let sdoc = Rc::new(format_to_sdoc(0, &mut doc, &mock_config, &mut s));
 
-        assert_eq!(simple_doc_to_string(sdoc), "test\ntest2")
+        assert_eq!(simple_doc_to_string(sdoc, "\n"), "test\ntest2")
     }
 
     #[test]
@@ -1102,6 +1219,6 @@ mod tests {
         let mut s = HashSet::default();
         let sdoc = Rc::new(format_to_sdoc(0, &mut doc, &mock_config, &mut s));
 
-        assert_eq!(simple_doc_to_string(sdoc), "test")
+        assert_eq!(simple_doc_to_string(sdoc, "\n"), "test")
     }
 }
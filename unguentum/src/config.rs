@@ -8,6 +8,54 @@ pub enum FunctionLineBreaks {
     Single,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    /// Keep the line endings used by most of the lines of the input
+    #[default]
+    Auto,
+    /// CRLF on Windows, LF everywhere else
+    Native,
+}
+
+impl LineEnding {
+    /// Resolves `Auto` and `Native` to either `Lf` or `Crlf`.
+    ///
+    /// `Auto` picks the line ending used by the majority of the lines
+    /// in the input. A tie resolves to `Lf`.
+    pub fn resolve(self, input: &str) -> LineEnding {
+        match self {
+            LineEnding::Lf | LineEnding::Crlf => self,
+            LineEnding::Auto => {
+                let crlf = input.matches("\r\n").count();
+                let lf = input.matches('\n').count() - crlf;
+                if crlf > lf {
+                    LineEnding::Crlf
+                } else {
+                    LineEnding::Lf
+                }
+            }
+            LineEnding::Native => {
+                if cfg!(windows) {
+                    LineEnding::Crlf
+                } else {
+                    LineEnding::Lf
+                }
+            }
+        }
+    }
+
+    /// The characters ending a line. An unresolved `Auto` is treated as `Lf`.
+    pub fn as_str(self) -> &'static str {
+        match self.resolve("") {
+            LineEnding::Crlf => "\r\n",
+            _ => "\n",
+        }
+    }
+}
+
 pub trait FormattingConfig: std::fmt::Display {
     fn line_length(&self) -> i32;
     fn indent(&self) -> i32;
@@ -18,4 +66,7 @@ pub trait FormattingConfig: std::fmt::Display {
     fn strip_suffix_whitespace_in_function_defs(&self) -> bool;
     fn function_line_breaks(&self) -> FunctionLineBreaks;
     fn insert_newline_in_quote_call(&self) -> bool;
+    fn line_ending(&self) -> LineEnding;
+    fn expand_logical_abbreviations(&self) -> bool;
+    fn split_semicolon_statements(&self) -> bool;
 }
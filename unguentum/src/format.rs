@@ -178,16 +178,26 @@ pub(crate) enum SimpleDoc {
     Line(usize, Rc<SimpleDoc>),
 }
 
-pub(crate) fn simple_doc_to_string(doc: Rc<SimpleDoc>) -> String {
-    match &*doc {
-        SimpleDoc::Nil => "".to_string(),
-        SimpleDoc::Text(s, doc) => format!("{s}{}", simple_doc_to_string(Rc::clone(doc))),
-        SimpleDoc::Line(indent, doc) => format!(
-            "\n{:width$}{}",
-            "",
-            simple_doc_to_string(Rc::clone(doc)),
-            width = indent
-        ),
+/// Prints `doc`. The line breaks of the formatter are printed as `line_ending`,
+/// without the spaces and tabs before them. The text is printed as it is,
+/// so the line breaks inside multi-line string literals are kept.
+pub(crate) fn simple_doc_to_string(doc: Rc<SimpleDoc>, line_ending: &str) -> String {
+    let mut output = String::new();
+    let mut doc = &*doc;
+    loop {
+        match doc {
+            SimpleDoc::Nil => return output,
+            SimpleDoc::Text(s, next) => {
+                output.push_str(s);
+                doc = next;
+            }
+            SimpleDoc::Line(indent, next) => {
+                output.truncate(output.trim_end_matches([' ', '\t']).len());
+                output.push_str(line_ending);
+                output.extend(std::iter::repeat_n(' ', *indent));
+                doc = next;
+            }
+        }
     }
 }
 
@@ -327,6 +337,10 @@ pub(crate) fn format_to_sdoc(
                         Rc::new(format_to_sdoc(consumed + length, docs, config, broken_docs)),
                     )
                 }
+                (_, Mode::Flat, Doc::Break("\n")) => SimpleDoc::Line(
+                    0,
+                    Rc::new(format_to_sdoc(consumed + 1, docs, config, broken_docs)),
+                ),
                 (_, Mode::Flat, Doc::Break(s)) => {
                     let length = s.len() as i32;
                     SimpleDoc::Text(
@@ -360,7 +374,7 @@ pub(crate) fn format_to_sdoc(
 
 #[cfg(test)]
 mod tests {
-    use crate::config::FunctionLineBreaks;
+    use crate::config::{FunctionLineBreaks, LineEnding};
 
     use super::*;
 
@@ -400,6 +414,18 @@ mod tests {
         fn insert_newline_in_quote_call(&self) -> bool {
             true
         }
+
+        fn line_ending(&self) -> LineEnding {
+            LineEnding::Lf
+        }
+
+        fn expand_logical_abbreviations(&self) -> bool {
+            false
+        }
+
+        fn split_semicolon_statements(&self) -> bool {
+            true
+        }
     }
     impl std::fmt::Display for MockConfig {
         fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -419,7 +445,7 @@ mod tests {
         let mut s = HashSet::default();
         let sdoc = Rc::new(format_to_sdoc(0, &mut doc, &mock_config, &mut s));
 
-        assert_eq!(simple_doc_to_string(sdoc), "Test")
+        assert_eq!(simple_doc_to_string(sdoc, "\n"), "Test")
     }
 
     #[test]
@@ -448,6 +474,6 @@ mod tests {
         let mut s = HashSet::default();
         let sdoc = Rc::new(format_to_sdoc(0, &mut doc, &mock_config, &mut s));
 
-        assert_eq!(simple_doc_to_string(sdoc), "Test\nTest2")
+        assert_eq!(simple_doc_to_string(sdoc, "\n"), "Test\nTest2")
     }
 }
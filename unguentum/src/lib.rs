@@ -10,23 +10,33 @@ use crate::format::simple_doc_to_string;
 use crate::format::DocBuffer;
 use crate::format::Mode;
 use log::trace;
-use parser::ast::Expression;
+use parser::ast::{Expression, TokenArena};
 use post_format_hooks::trim_line_endings;
 use std::collections::VecDeque;
 use std::rc::Rc;
 
+/// Formats `expression`, parsed from `source`.
+///
+/// The statements that failed to parse are copied from `source` as they are.
 pub fn format_code<T: config::FormattingConfig>(
-    mut expression: Expression,
+    expression: Expression,
+    source: &str,
     formatting_config: &T,
 ) -> String {
-    // Pre formatting hooks
-    let mut pre_format: Vec<fn(&mut Expression<'_>)> = vec![];
+    // Pre formatting hooks. The tokens they create live in the arena,
+    // which is declared first so that it outlives the expression.
+    let arena = TokenArena::new();
+    let mut expression = expression;
+    let mut pre_format: Vec<for<'a> fn(&mut Expression<'a>, &'a TokenArena<'a>)> = vec![];
     if formatting_config.strip_suffix_whitespace_in_function_defs() {
         pre_format.push(pre_format_hooks::remove_trailing_whitespace_from_function_defs);
     }
+    if formatting_config.expand_logical_abbreviations() {
+        pre_format.push(pre_format_hooks::expand_logical_abbreviations);
+    }
 
     for hook in pre_format {
-        hook(&mut expression);
+        hook(&mut expression, &arena);
     }
 
     // Doc stage
@@ -34,7 +44,7 @@ pub fn format_code<T: config::FormattingConfig>(
     let mut docs: VecDeque<_> = VecDeque::from([(
         0i32,
         Mode::Flat,
-        expression.to_docs(formatting_config, &mut doc_ref),
+        expression.to_docs(formatting_config, source, &mut doc_ref),
     )]);
     trace!("Config: {}", formatting_config);
     trace!("Docs: {}", DocBuffer(&docs));
@@ -51,12 +61,13 @@ pub fn format_code<T: config::FormattingConfig>(
     trace!("Simple docs: {:?}", simple_doc);
 
     // Printing to string
-    let mut formatted = simple_doc_to_string(simple_doc);
+    let line_ending = formatting_config.line_ending().as_str();
+    let mut formatted = simple_doc_to_string(simple_doc, line_ending);
 
     // Post-format hooks
     let post_format_hooks = vec![trim_line_endings];
     for hook in post_format_hooks {
-        formatted = hook(formatted);
+        formatted = hook(formatted, line_ending);
     }
 
     // Add a new line because trimming whitespace removes the trailing line
@@ -1,8 +1,9 @@
-pub(crate) fn trim_line_endings(s: String) -> String {
-    s.lines()
-        .fold(String::with_capacity(s.len()), |mut acc, line| {
-            acc.push_str(line.trim_end());
-            acc.push('\n');
-            acc
-        })
+/// Trims the whitespace at the end of the last line and ends it with `line_ending`.
+/// The line breaks before it are already printed with `line_ending`.
+pub(crate) fn trim_line_endings(mut s: String, line_ending: &str) -> String {
+    s.truncate(s.trim_end_matches([' ', '\t']).len());
+    if !s.is_empty() && !s.ends_with('\n') {
+        s.push_str(line_ending);
+    }
+    s
 }
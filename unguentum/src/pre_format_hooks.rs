@@ -1,87 +1,232 @@
-use parser::ast::Expression;
-
-pub(crate) fn remove_trailing_whitespace_from_function_defs(expression: &mut Expression) {
-    match expression {
-        Expression::Symbol(_)
-        | Expression::Literal(_)
-        | Expression::Comment(_)
-        | Expression::Continue(_)
-        | Expression::Formula(_, _)
-        | Expression::Newline(_)
-        | Expression::Whitespace(_)
-        | Expression::EOF(_)
-        | Expression::Break(_) => {}
-        Expression::Term(term) => {
-            term.term
-                .iter_mut()
-                .for_each(remove_trailing_whitespace_from_function_defs);
-        }
-        Expression::Unary(_, expression) => {
-            remove_trailing_whitespace_from_function_defs(expression)
-        }
-        Expression::Bop(_, expression1, expression2) => {
-            remove_trailing_whitespace_from_function_defs(expression1);
-            remove_trailing_whitespace_from_function_defs(expression2);
+use log::info;
+use parser::ast::{
+    walk_expression, walk_expression_mut, walk_for_loop, walk_function_def, walk_function_def_mut,
+    walk_lambda, walk_lambda_mut, Args, Expression, ForLoop, FormalParameter, FunctionDefinition,
+    Lambda, TokenArena, Visitor, VisitorMut,
+};
+use tokenizer::Token;
+
+pub(crate) fn remove_trailing_whitespace_from_function_defs<'a>(
+    expression: &mut Expression<'a>,
+    _: &'a TokenArena<'a>,
+) {
+    TrailingWhitespaceRemover.visit_expression(expression);
+}
+
+struct TrailingWhitespaceRemover;
+
+impl<'a> VisitorMut<'a> for TrailingWhitespaceRemover {
+    fn visit_function_def(&mut self, function_def: &mut FunctionDefinition<'a>) {
+        remove_trailing_whitespace(&mut function_def.body);
+        walk_function_def_mut(self, function_def);
+    }
+
+    fn visit_lambda(&mut self, lambda: &mut Lambda<'a>) {
+        remove_trailing_whitespace(&mut lambda.body);
+        walk_lambda_mut(self, lambda);
+    }
+}
+
+fn remove_trailing_whitespace(body: &mut Expression) {
+    if let Expression::Term(terms) = body {
+        while terms
+            .term
+            .last()
+            .is_some_and(|last_expr| matches!(last_expr, Expression::Whitespace(_)))
+        {
+            terms.term.pop();
         }
-        Expression::FunctionDef(function_def) => {
-            let body = &mut function_def.body;
-            if let Expression::Term(ref mut terms) = **body {
-                while terms
-                    .term
-                    .last()
-                    .is_some_and(|last_expr| matches!(last_expr, Expression::Whitespace(_)))
-                {
-                    terms.term.pop();
-                }
+    }
+}
+
+/// Rewrites `T` and `F` to `TRUE` and `FALSE`.
+///
+/// The symbols are replaced with the `TRUE` and `FALSE` literals allocated in `arena`.
+/// The symbols that are assigned to, accessed with `$`, `@` or `::`,
+/// used as parameter names or loop variables are left untouched.
+/// So are all the uses of `T` or `F` in a scope binding it, because there they
+/// are ordinary variables. The scopes are the file and the functions, and a
+/// function sees the bindings of the scopes around it. Assigning with `<<-`
+/// binds the symbol in the whole file.
+pub(crate) fn expand_logical_abbreviations<'a>(
+    expression: &mut Expression<'a>,
+    arena: &'a TokenArena<'a>,
+) {
+    let mut bindings = Bindings::default();
+    bindings.visit_expression(expression);
+    bindings.local.append(&mut bindings.global);
+    LogicalAbbreviationsExpander {
+        arena,
+        scopes: vec![bindings.local],
+    }
+    .visit_expression(expression);
+}
+
+struct LogicalAbbreviationsExpander<'a> {
+    arena: &'a TokenArena<'a>,
+    /// The abbreviations bound in the file and in the functions around
+    /// the visited expression, from the outermost scope.
+    scopes: Vec<Vec<&'a str>>,
+}
+
+impl LogicalAbbreviationsExpander<'_> {
+    fn is_bound(&self, name: &str) -> bool {
+        self.scopes.iter().flatten().any(|bound| *bound == name)
+    }
+}
+
+impl<'a> VisitorMut<'a> for LogicalAbbreviationsExpander<'a> {
+    fn visit_expression(&mut self, expression: &mut Expression<'a>) {
+        match expression {
+            Expression::Symbol(token)
+                if abbreviation(&token.token).is_some_and(|name| !self.is_bound(name)) =>
+            {
+                let literal = if token.token == Token::Symbol("T") {
+                    "TRUE"
+                } else {
+                    "FALSE"
+                };
+                info!(
+                    "Rewriting {} to {} at {}:{}",
+                    token,
+                    literal,
+                    token.line + 1,
+                    token.offset + 1
+                );
+                let literal = self.arena.replace(token, Token::Literal(literal));
+                *expression = Expression::Literal(literal);
             }
-        }
-        Expression::LambdaFunction(lambda) => {
-            let body = &mut lambda.body;
-            if let Expression::Term(ref mut terms) = **body {
-                while terms
-                    .term
-                    .last()
-                    .is_some_and(|last_expr| matches!(last_expr, Expression::Whitespace(_)))
-                {
-                    terms.term.pop();
+            Expression::Bop(op, lhs, rhs) => match op.token {
+                Token::LAssign | Token::SuperAssign | Token::OldAssign | Token::ColonAssign => {
+                    self.visit_expression(rhs)
                 }
-            }
+                Token::RAssign | Token::SuperRAssign => self.visit_expression(lhs),
+                Token::Dollar | Token::Slot => self.visit_expression(lhs),
+                Token::NsGet | Token::NsGetInt => {}
+                _ => walk_expression_mut(self, expression),
+            },
+            _ => walk_expression_mut(self, expression),
         }
-        Expression::IfExpression(if_expr) => {
-            remove_trailing_whitespace_from_function_defs(&mut if_expr.if_conditional.body);
-            if_expr
-                .trailing_else
-                .as_mut()
-                .iter_mut()
-                .for_each(|trailing_else| {
-                    remove_trailing_whitespace_from_function_defs(&mut trailing_else.body)
-                });
-            if_expr.else_ifs.iter_mut().for_each(|else_if| {
-                remove_trailing_whitespace_from_function_defs(&mut else_if.if_conditional.body)
-            });
-        }
-        Expression::WhileExpression(while_loop) => {
-            remove_trailing_whitespace_from_function_defs(&mut while_loop.condition);
-            remove_trailing_whitespace_from_function_defs(&mut while_loop.body);
-        }
-        Expression::RepeatExpression(repeat_loop) => {
-            remove_trailing_whitespace_from_function_defs(&mut repeat_loop.body);
+    }
+
+    fn visit_function_def(&mut self, function_def: &mut FunctionDefinition<'a>) {
+        self.scopes
+            .push(function_scope(&function_def.arguments, &function_def.body));
+        walk_function_def_mut(self, function_def);
+        self.scopes.pop();
+    }
+
+    fn visit_lambda(&mut self, lambda: &mut Lambda<'a>) {
+        self.scopes.push(function_scope(&lambda.args, &lambda.body));
+        walk_lambda_mut(self, lambda);
+        self.scopes.pop();
+    }
+
+    /// The loop variable is skipped.
+    fn visit_for_loop(&mut self, for_loop: &mut ForLoop<'a>) {
+        self.visit_expression(&mut for_loop.collection);
+        self.visit_expression(&mut for_loop.body);
+    }
+}
+
+/// Returns the abbreviations bound in a function: its parameters
+/// and the abbreviations bound in its body.
+fn function_scope<'a>(
+    parameters: &Args<'a, FormalParameter<'a>>,
+    body: &Expression<'a>,
+) -> Vec<&'a str> {
+    let mut bindings = Bindings::default();
+    bindings.visit_expression(body);
+    let parameters = parameters.args.iter().filter_map(|arg| {
+        arg.0
+            .as_ref()
+            .and_then(|parameter| abbreviation(&parameter.name.token))
+    });
+    bindings.local.extend(parameters);
+    bindings.local
+}
+
+/// Collects the abbreviations bound in a scope: assigned to or used as
+/// loop variables. The functions inside the scope are scopes of their own,
+/// only the abbreviations they assign to with `<<-` are collected, in `global`.
+#[derive(Default)]
+struct Bindings<'a> {
+    local: Vec<&'a str>,
+    global: Vec<&'a str>,
+    /// How many functions around the visited expression are inside the scope.
+    depth: usize,
+}
+
+impl<'a> Bindings<'a> {
+    fn bind(&mut self, target: &Expression<'a>, global: bool) {
+        let Some(name) = assigned_symbol(target).and_then(abbreviation) else {
+            return;
+        };
+        match (global, self.depth) {
+            (true, _) => self.global.push(name),
+            (false, 0) => self.local.push(name),
+            (false, _) => {}
         }
-        Expression::FunctionCall(call) => {
-            call.args.args.iter_mut().for_each(|arg| {
-                arg.0
-                    .iter_mut()
-                    .for_each(remove_trailing_whitespace_from_function_defs)
-            });
+    }
+}
+
+impl<'a> Visitor<'a> for Bindings<'a> {
+    fn visit_expression(&mut self, expression: &Expression<'a>) {
+        if let Expression::Bop(op, lhs, rhs) = expression {
+            match op.token {
+                Token::LAssign | Token::OldAssign | Token::ColonAssign => self.bind(lhs, false),
+                Token::RAssign => self.bind(rhs, false),
+                Token::SuperAssign => self.bind(lhs, true),
+                Token::SuperRAssign => self.bind(rhs, true),
+                _ => {}
+            }
         }
-        Expression::SubsetExpression(subset) => subset.args.args.iter_mut().for_each(|arg| {
-            arg.0
-                .iter_mut()
-                .for_each(remove_trailing_whitespace_from_function_defs)
-        }),
-        Expression::ForLoopExpression(for_loop) => {
-            remove_trailing_whitespace_from_function_defs(&mut for_loop.collection);
-            remove_trailing_whitespace_from_function_defs(&mut for_loop.body);
+        walk_expression(self, expression);
+    }
+
+    fn visit_function_def(&mut self, function_def: &FunctionDefinition<'a>) {
+        self.depth += 1;
+        walk_function_def(self, function_def);
+        self.depth -= 1;
+    }
+
+    fn visit_lambda(&mut self, lambda: &Lambda<'a>) {
+        self.depth += 1;
+        walk_lambda(self, lambda);
+        self.depth -= 1;
+    }
+
+    fn visit_for_loop(&mut self, for_loop: &ForLoop<'a>) {
+        self.bind(&for_loop.identifier, false);
+        walk_for_loop(self, for_loop);
+    }
+}
+
+/// Returns the symbol an assignment to `target` binds. Assigning to a part
+/// of a variable, e.g. `x[1] <- 0` or `names(x) <- "a"`, binds the variable.
+fn assigned_symbol<'a>(target: &Expression<'a>) -> Option<&'a Token<'a>> {
+    match target {
+        Expression::Symbol(token) => Some(&token.token),
+        Expression::SubsetExpression(subset) => assigned_symbol(&subset.object_ref),
+        Expression::FunctionCall(call) => call
+            .args
+            .args
+            .first()
+            .and_then(|arg| arg.0.as_ref())
+            .and_then(|argument| argument.value.as_ref())
+            .and_then(assigned_symbol),
+        Expression::Bop(op, lhs, _) if matches!(op.token, Token::Dollar | Token::Slot) => {
+            assigned_symbol(lhs)
         }
+        Expression::Term(term) if term.term.len() == 1 => assigned_symbol(&term.term[0]),
+        _ => None,
+    }
+}
+
+/// Returns `T` or `F` if `token` is one of the abbreviations.
+fn abbreviation<'a>(token: &Token<'a>) -> Option<&'a str> {
+    match token {
+        Token::Symbol(name @ ("T" | "F")) => Some(*name),
+        _ => None,
     }
 }